@@ -12,7 +12,7 @@ impl BlockDevice for VirtIOBlock {
     }
 
     fn write_block(&mut self, sector_offset: usize, buf: &mut [u8]) {
-        self.0.read_block(sector_offset, buf).expect("写入失败")
+        self.0.write_block(sector_offset, buf).expect("写入失败")
     }
 
     fn handle_irq(&mut self) {
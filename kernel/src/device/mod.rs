@@ -161,22 +161,21 @@ impl fatfs::Write for DiskCursor {
         // 获取硬盘设备写入器（驱动？）
         let block_device = unsafe { &mut BLK_CONTROL[self.disk_index] };
 
+        let mut i = 0;
         let mut data = [0u8; 512];
-
-        if self.offset != 0 || buf.len() != 512 {
-            block_device.read_block(self.sector as usize, &mut data);
+        // 按扇区写入 不完整的扇区需要先读出原有内容
+        while i < buf.len() {
+            let len = (512 - self.offset).min(buf.len() - i);
+            if len != 512 {
+                block_device.read_block(self.sector as usize, &mut data);
+            }
+            data[self.offset..self.offset + len].copy_from_slice(&buf[i..i + len]);
+            block_device.write_block(self.sector as usize, &mut data);
+            i += len;
+            self.move_cursor(len);
         }
 
-        let (start, end) = if buf.len() == 512 {
-            (0, 512)
-        } else {
-            (self.offset, self.offset + buf.len())
-        };
-        data[start..end].clone_from_slice(&buf);
-        block_device.write_block(self.sector as usize, &mut data);
-        self.move_cursor(buf.len());
-
-        Ok(buf.len())
+        Ok(i)
     }
 
     fn write_all(&mut self, buf: &[u8]) -> Result<(), RuntimeError> {
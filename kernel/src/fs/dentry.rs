@@ -0,0 +1,88 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+
+use crate::sync::mutex::Mutex;
+
+use super::filetree::INode;
+
+// 目录项缓存的最大数量 超过后淘汰最久没有使用的目录项
+pub const DENTRY_CACHE_SIZE: usize = 1024;
+
+// 缓存的目录项 node为None时表示文件不存在
+struct Dentry {
+    node: Option<Rc<INode>>,
+    tick: usize
+}
+
+// 目录项缓存 按照(父节点, 文件名)保存查找的结果
+pub struct DentryCache {
+    entries: BTreeMap<(usize, String), Dentry>,
+    lru: BTreeMap<usize, (usize, String)>,      // 最后使用时间 -> 目录项
+    tick: usize
+}
+
+lazy_static! {
+    pub static ref DENTRY_CACHE: Mutex<DentryCache> = Mutex::new(DentryCache::new());
+}
+
+// 父节点的编号和文件名作为目录项的key
+fn key(parent: &INode, name: &str) -> (usize, String) {
+    (parent.0.borrow().id, name.to_string())
+}
+
+impl DentryCache {
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0
+        }
+    }
+
+    // 查找目录项 Some(None)表示已知文件不存在
+    pub fn get(&mut self, parent: &INode, name: &str) -> Option<Option<Rc<INode>>> {
+        let key = key(parent, name);
+        self.tick += 1;
+        let dentry = self.entries.get_mut(&key)?;
+        self.lru.remove(&dentry.tick);
+        dentry.tick = self.tick;
+        self.lru.insert(self.tick, key);
+        Some(dentry.node.clone())
+    }
+
+    // 添加目录项 超过数量时淘汰
+    pub fn insert(&mut self, parent: &INode, name: &str, node: Option<Rc<INode>>) {
+        self.remove(parent, name);
+        let key = key(parent, name);
+        self.tick += 1;
+        self.lru.insert(self.tick, key.clone());
+        self.entries.insert(key, Dentry { node, tick: self.tick });
+        if self.entries.len() > DENTRY_CACHE_SIZE {
+            self.evict();
+        }
+    }
+
+    // 删除目录项 创建 删除和重命名文件时使用
+    pub fn remove(&mut self, parent: &INode, name: &str) {
+        if let Some(dentry) = self.entries.remove(&key(parent, name)) {
+            self.lru.remove(&dentry.tick);
+        }
+    }
+
+    // 淘汰最久没有使用的目录项 正在使用的节点和还有子节点的目录不能淘汰
+    fn evict(&mut self) {
+        let victim = self.lru.iter().find(|(_, key)| {
+            match &self.entries[*key].node {
+                Some(node) => Rc::strong_count(node) == 1 && Rc::weak_count(node) == 0,
+                None => true
+            }
+        }).map(|(tick, key)| (*tick, key.clone()));
+        let (tick, key) = match victim {
+            Some(victim) => victim,
+            None => return
+        };
+        self.lru.remove(&tick);
+        self.entries.remove(&key);
+    }
+}
@@ -0,0 +1,35 @@
+use alloc::rc::Rc;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use super::file::{FileOP, FileType};
+use super::specials::SpecialDir;
+use super::specials::dev_rtc::DevRtc;
+use super::stdio::{StdNull, StdZero};
+use super::vfs::{Filesystem, Inode};
+
+// 设备文件系统 包含内核提供的设备文件
+pub struct DevFileSystem(Rc<SpecialDir>);
+
+impl DevFileSystem {
+    pub fn new() -> Rc<Self> {
+        let mut root = SpecialDir::new(&[
+            ("null", FileType::Device, || Rc::new(StdNull) as Rc<dyn FileOP>),
+            ("zero", FileType::Device, || Rc::new(StdZero) as Rc<dyn FileOP>),
+            ("rtc", FileType::Device, || Rc::new(DevRtc::new()) as Rc<dyn FileOP>)
+        ]);
+        // 共享内存的挂载点
+        root.0.push(("shm".to_string(), Rc::new(SpecialDir(Vec::new()))));
+        Rc::new(Self(Rc::new(root)))
+    }
+}
+
+impl Filesystem for DevFileSystem {
+    fn fs_type(&self) -> &'static str {
+        "devtmpfs"
+    }
+
+    fn root(&self) -> Rc<dyn Inode> {
+        self.0.clone()
+    }
+}
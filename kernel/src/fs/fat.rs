@@ -0,0 +1,202 @@
+use core::cell::RefCell;
+
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use fatfs::{Read, Write, Seek, SeekFrom};
+
+use crate::device::{DiskFile, Dir, root_dir};
+use crate::runtime_err::RuntimeError;
+
+use super::file::FileType;
+use super::vfs::{DirEntry, Filesystem, Inode};
+
+// 重命名覆盖文件时目标的临时名字
+const RENAME_TEMP: &str = ".rename.tmp";
+
+// FAT文件系统 使用全局的硬盘设备
+pub struct FatFileSystem(Dir);
+
+impl FatFileSystem {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self(root_dir()))
+    }
+}
+
+impl Filesystem for FatFileSystem {
+    fn fs_type(&self) -> &'static str {
+        "vfat"
+    }
+
+    fn root(&self) -> Rc<dyn Inode> {
+        Rc::new(FatInode::Dir(self.0.clone()))
+    }
+}
+
+// FAT中的文件或者目录
+// 写入时会分配簇并修改目录项 同一个文件的读写使用同一个文件对象
+pub enum FatInode {
+    File(RefCell<DiskFile>),
+    Dir(Dir)
+}
+
+impl FatInode {
+    fn new_file(file: DiskFile) -> Rc<Self> {
+        Rc::new(FatInode::File(RefCell::new(file)))
+    }
+
+    fn dir(&self) -> Result<&Dir, RuntimeError> {
+        match self {
+            FatInode::Dir(dir) => Ok(dir),
+            FatInode::File(_) => Err(RuntimeError::NotDir)
+        }
+    }
+
+    fn file(&self) -> Result<&RefCell<DiskFile>, RuntimeError> {
+        match self {
+            FatInode::File(file) => Ok(file),
+            FatInode::Dir(_) => Err(RuntimeError::NotRWFile)
+        }
+    }
+}
+
+// 在文件末尾填充0直到文件大小为size fatfs不能移动到文件末尾之后
+fn fill_zero(file: &mut DiskFile, size: usize) -> Result<(), RuntimeError> {
+    let mut end = file.seek(SeekFrom::End(0)).map_err(|_| RuntimeError::NotRWFile)? as usize;
+    let zero = [0u8; 512];
+    while end < size {
+        let len = (size - end).min(zero.len());
+        file.write_all(&zero[..len]).map_err(|_| RuntimeError::NotRWFile)?;
+        end += len;
+    }
+    Ok(())
+}
+
+impl Inode for FatInode {
+    fn file_type(&self) -> FileType {
+        match self {
+            FatInode::File(_) => FileType::File,
+            FatInode::Dir(_) => FileType::Directory
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            FatInode::File(file) => file.borrow().size().unwrap_or(0) as usize,
+            FatInode::Dir(_) => 0
+        }
+    }
+
+    fn read_dir(&self) -> Result<Vec<Rc<dyn DirEntry>>, RuntimeError> {
+        let dir = self.dir()?;
+        let mut entries: Vec<Rc<dyn DirEntry>> = vec![];
+        for entry in dir.iter() {
+            let entry = entry.map_err(|_| RuntimeError::NotDir)?;
+            let filename = entry.file_name();
+            if filename == "." || filename == ".." {
+                continue;
+            }
+            let inode: Rc<dyn Inode> = if entry.is_dir() {
+                Rc::new(FatInode::Dir(entry.to_dir()))
+            } else if entry.is_file() {
+                FatInode::new_file(entry.to_file())
+            } else {
+                error!("不支持的文件类型");
+                continue;
+            };
+            entries.push(Rc::new((filename, inode)));
+        }
+        Ok(entries)
+    }
+
+    // 只为找到的目录项创建节点
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, RuntimeError> {
+        let dir = self.dir()?;
+        for entry in dir.iter() {
+            let entry = entry.map_err(|_| RuntimeError::NotDir)?;
+            if entry.file_name() != name {
+                continue;
+            }
+            if entry.is_dir() {
+                return Ok(Rc::new(FatInode::Dir(entry.to_dir())));
+            } else if entry.is_file() {
+                return Ok(FatInode::new_file(entry.to_file()));
+            }
+        }
+        Err(RuntimeError::FileNotFound)
+    }
+
+    fn rename(&self, old_name: &str, new_name: &str) -> Result<(), RuntimeError> {
+        let dir = self.dir()?;
+        if old_name == new_name {
+            return Ok(());
+        }
+        if self.lookup(new_name).is_err() {
+            return dir.rename(old_name, dir, new_name).map_err(|_| RuntimeError::FileNotFound);
+        }
+        // fatfs不会覆盖已经存在的文件 先把目标改为临时名字 重命名失败时恢复目标
+        dir.rename(new_name, dir, RENAME_TEMP).map_err(|_| RuntimeError::NotRWFile)?;
+        if dir.rename(old_name, dir, new_name).is_err() {
+            let _ = dir.rename(RENAME_TEMP, dir, new_name);
+            return Err(RuntimeError::FileNotFound);
+        }
+        dir.remove(RENAME_TEMP).map_err(|_| RuntimeError::NotRWFile)
+    }
+
+    fn read_at(&self, pos: usize, buf: &mut [u8]) -> Result<usize, RuntimeError> {
+        let mut file = self.file()?.borrow_mut();
+        if pos >= file.size().unwrap_or(0) as usize {
+            return Ok(0);
+        }
+        file.seek(SeekFrom::Start(pos as u64)).map_err(|_| RuntimeError::NotRWFile)?;
+        let mut len = 0;
+        while len < buf.len() {
+            match file.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(_) => return Err(RuntimeError::NotRWFile)
+            }
+        }
+        Ok(len)
+    }
+
+    // 写入超出文件大小时扩展文件 中间的部分填充0
+    fn write_at(&self, pos: usize, buf: &[u8]) -> Result<usize, RuntimeError> {
+        let mut file = self.file()?.borrow_mut();
+        if pos > file.size().unwrap_or(0) as usize {
+            fill_zero(&mut file, pos)?;
+        }
+        file.seek(SeekFrom::Start(pos as u64)).map_err(|_| RuntimeError::NotRWFile)?;
+        file.write_all(buf).map_err(|_| RuntimeError::NotRWFile)?;
+        // 写回目录项中的文件大小和起始簇
+        file.flush().map_err(|_| RuntimeError::NotRWFile)?;
+        Ok(buf.len())
+    }
+
+    fn create(&self, name: &str, file_type: FileType) -> Result<Rc<dyn Inode>, RuntimeError> {
+        let dir = self.dir()?;
+        let inode: Rc<dyn Inode> = match file_type {
+            FileType::Directory => Rc::new(FatInode::Dir(
+                dir.create_dir(name).map_err(|_| RuntimeError::NotRWFile)?)),
+            FileType::File => FatInode::new_file(
+                dir.create_file(name).map_err(|_| RuntimeError::NotRWFile)?),
+            _ => return Err(RuntimeError::NotRWFile)
+        };
+        Ok(inode)
+    }
+
+    fn remove(&self, name: &str) -> Result<(), RuntimeError> {
+        self.dir()?.remove(name).map_err(|_| RuntimeError::FileNotFound)
+    }
+
+    // 修改文件大小 扩展的部分填充0
+    fn truncate(&self, size: usize) -> Result<(), RuntimeError> {
+        let mut file = self.file()?.borrow_mut();
+        if size > file.size().unwrap_or(0) as usize {
+            fill_zero(&mut file, size)?;
+        } else {
+            file.seek(SeekFrom::Start(size as u64)).map_err(|_| RuntimeError::NotRWFile)?;
+            file.truncate().map_err(|_| RuntimeError::NotRWFile)?;
+        }
+        file.flush().map_err(|_| RuntimeError::NotRWFile)
+    }
+}
@@ -1,16 +1,17 @@
 use core::any::{Any, TypeId};
 
 use alloc::rc::Rc;
+use alloc::vec::Vec;
 use core::cell::RefCell;
 
 
-use crate::memory::mem_map::MemMap;
 use crate::runtime_err::RuntimeError;
-use crate::memory::addr::{get_buf_from_phys_page, get_pages_num, PAGE_SIZE, VirtAddr, PhysPageNum};
-use crate::memory::page::alloc_more;
-use crate::memory::page_table::{PageMappingManager, PTEFlags};
+use crate::sys_call::consts::{EINVAL, EIO, ENOSPC};
+
+use crate::task::wait_queue::WaitEvent;
 
 use super::filetree::INode;
+use super::vfs::Metadata;
 
 #[allow(unused)]
 pub mod fcntl_cmd {
@@ -32,6 +33,10 @@ pub mod fcntl_cmd {
     pub const SETLKW: usize = 7;
     /// like F_DUPFD, but additionally set the close-on-exec flag
     pub const DUPFD_CLOEXEC: usize = 0x406;
+    /// set pipe page size array
+    pub const SETPIPE_SZ: usize = 1031;
+    /// get pipe page size array
+    pub const GETPIPE_SZ: usize = 1032;
 }
 
 // 文件类型
@@ -39,13 +44,29 @@ pub mod fcntl_cmd {
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum FileType {
     File,           // 文件
-    VirtFile,       // 虚拟文件
     Directory,      // 文件夹
+    SymLink,        // 符号链接
     Device,         // 设备
     Pipeline,       // 管道
+    Socket,         // 套接字
     None            // 空
 }
 
+impl FileType {
+    // stat中st_mode的文件类型位
+    pub fn mode(&self) -> u32 {
+        match self {
+            FileType::File => 0o100000,
+            FileType::Directory => 0o40000,
+            FileType::SymLink => 0o120000,
+            FileType::Device => 0o20000,
+            FileType::Pipeline => 0o10000,
+            FileType::Socket => 0o140000,
+            _ => 0
+        }
+    }
+}
+
 #[repr(C)]
 pub struct Kstat {
 	pub st_dev: u64,			// 设备号
@@ -68,94 +89,88 @@ pub struct Kstat {
 	pub st_ctime_nsec: u64,		// 最后创建微秒
 }
 
+impl Kstat {
+	// 使用文件类型和元数据填写文件信息
+	pub fn fill(&mut self, file_type: FileType, metadata: &Metadata) {
+		self.st_dev = 1;
+		self.st_ino = metadata.ino as u64;
+		self.st_mode = file_type.mode() | metadata.mode;
+		self.st_nlink = metadata.nlink;
+		self.st_uid = metadata.uid;
+		self.st_gid = metadata.gid;
+		self.st_rdev = 0;
+		self.__pad = 0;
+		self.st_size = metadata.size as u64;
+		self.st_blksize = 512;
+		self.__pad2 = 0;
+		self.st_blocks = metadata.blocks as u64;
+		self.st_atime_sec = metadata.atime.tv_sec as u64;
+		self.st_atime_nsec = metadata.atime.tv_nsec as u64;
+		self.st_mtime_sec = metadata.mtime.tv_sec as u64;
+		self.st_mtime_nsec = metadata.mtime.tv_nsec as u64;
+		self.st_ctime_sec = metadata.ctime.tv_sec as u64;
+		self.st_ctime_nsec = metadata.ctime.tv_nsec as u64;
+	}
+}
+
+// poll的事件
+bitflags! {
+    pub struct PollEvents: u16 {
+        const IN = 0x001;       // 可以读取
+        const PRI = 0x002;      // 有紧急数据
+        const OUT = 0x004;      // 可以写入
+        const ERR = 0x008;      // 发生错误
+        const HUP = 0x010;      // 对端已经关闭
+        const NVAL = 0x020;     // 文件描述符无效
+    }
+}
+
 pub trait FileOP: Any {
 	fn readable(&self) -> bool;
 	fn writeable(&self) -> bool;
 	fn read_at(&self, pos: usize, data: &mut [u8]) -> usize;
 	fn write_at(&self, pos: usize, data: &[u8], count: usize) -> usize;
 	fn get_size(&self) -> usize;
+	// 当前可以进行的操作 普通文件总是可以读写
+	fn poll(&self) -> PollEvents {
+		PollEvents::IN | PollEvents::OUT
+	}
+	// 读写需要阻塞时等待的事件 文件状态改变时唤醒
+	fn wait_event(&self) -> Option<WaitEvent> {
+		None
+	}
+	// 文件类型 用于fstat
+	fn file_type(&self) -> FileType {
+		FileType::File
+	}
+	// 修改文件大小 只有普通文件支持
+	fn truncate(&self, _size: usize) -> Result<(), usize> {
+		Err(EINVAL)
+	}
 }
 
 pub struct File(pub RefCell<FileInner>);
 
+// 打开的文件 普通文件的内容通过节点的页缓存读取 打开文件时不读取内容
 pub struct FileInner {
     pub file: Rc<INode>,
     pub offset: usize,
-    pub file_size: usize,
-    pub mem_size: usize,
-    pub buf: &'static mut [u8],
-    pub mem_map: Option<Rc<MemMap>>,
-    pub file_type: FileType
+    pub file_type: FileType,
+    pub entries: Vec<Rc<INode>>     // 目录的子节点 从头读取目录时获取
 }
 
 impl File {
     pub fn new(inode: Rc<INode>) -> Result<Rc<Self>, RuntimeError>{
-        if inode.is_dir() {
-            Ok(Rc::new(Self(RefCell::new(FileInner {
-                file: inode,
-                offset: 0,
-                file_size: 0,
-                buf: get_buf_from_phys_page(PhysPageNum::from(0x80020usize), 0),
-                mem_size: 0,
-                mem_map: None,
-                file_type: FileType::Directory
-            }))))
-        } else if inode.is_virt_file() {
-            Ok(Rc::new(Self(RefCell::new(FileInner {
-                file: inode,
-                offset: 0,
-                file_size: 0,
-                buf: get_buf_from_phys_page(PhysPageNum::from(0x80020usize), 0),
-                mem_size: 0,
-                mem_map: None,
-                file_type: FileType::VirtFile
-            }))))
-        } else {
-            // 申请页表存储程序
-            let elf_pages = get_pages_num(inode.get_file_size());
-            // 申请页表
-            let elf_phy_start = alloc_more(elf_pages)?;
-            let mem_map = MemMap::exists_page(elf_phy_start, elf_phy_start.0.into(),
-                elf_pages, PTEFlags::VRWX);
-            // 获取缓冲区地址并读取
-            let buf = get_buf_from_phys_page(elf_phy_start, elf_pages);
-            inode.read_to(buf)?;
-            let file_size = inode.get_file_size();
-            Ok(Rc::new(Self(RefCell::new(FileInner {
-                file: inode,
-                offset: 0,
-                file_size,
-                buf,
-                mem_size: elf_pages * PAGE_SIZE,
-                mem_map: Some(mem_map),
-                file_type: FileType::File
-            }))))
-            
-        }
-    }
-
-    pub fn cache(inode: Rc<INode>) -> Result<Rc<Self>, RuntimeError>{
-        // 申请页表存储程序
-        let elf_pages = get_pages_num(inode.get_file_size());
-        // 申请页表
-        // let elf_phy_start = alloc_more_front(elf_pages)?;
-        let elf_phy_start = alloc_more(elf_pages)?;
-        let mem_map = MemMap::exists_page(elf_phy_start, elf_phy_start.0.into(),
-            elf_pages, PTEFlags::VRWX);
-        // 获取缓冲区地址并读取
-        let buf = get_buf_from_phys_page(elf_phy_start, elf_pages);
-        inode.read_to(buf)?;
-        let file_size = inode.get_file_size();
+        let file_type = match inode.is_dir() {
+            true => FileType::Directory,
+            false => FileType::File
+        };
         Ok(Rc::new(Self(RefCell::new(FileInner {
             file: inode,
             offset: 0,
-            file_size,
-            buf,
-            mem_size: elf_pages * PAGE_SIZE,
-            mem_map: Some(mem_map),
-            file_type: FileType::File
+            file_type,
+            entries: vec![]
         }))))
-        
     }
 
     pub fn get_inode(&self) -> Rc<INode> {
@@ -163,29 +178,13 @@ impl File {
         inner.file.clone()
     }
 
-    pub fn copy_to(&self, offset: usize, buf: &mut [u8]) {
-        let inner = self.0.borrow_mut();
-        let mut len = inner.buf.len() - offset;
-        if len > buf.len() { len = buf.len(); }
-        buf[..len].copy_from_slice(&inner.buf[offset..offset + len]);
-    }
-
-    pub fn mmap(&self, pmm: Rc<PageMappingManager>, virt_addr: VirtAddr) -> Result<(), RuntimeError>{
-        let inner = self.0.borrow_mut();
-        let mem_map = inner.mem_map.clone().ok_or(RuntimeError::NoMatchedFile)?;
-        pmm.add_mapping_range(mem_map.ppn.into(), virt_addr, mem_map.page_num * PAGE_SIZE, PTEFlags::UVRWX)
-    }
-
     pub fn entry_next(&self) -> Option<(usize, Rc<INode>)> {
         let mut inner = self.0.borrow_mut();
         let offset = inner.offset;
-        let child = {
-            let children = &mut inner.file.0.borrow_mut().children;
-            if offset >= children.len() {
-                return None;
-            }
-            children[offset].clone()
-        };
+        if offset == 0 {
+            inner.entries = inner.file.clone_children();
+        }
+        let child = inner.entries.get(offset)?.clone();
         inner.offset += 1;
         Some((offset, child))
     }
@@ -206,31 +205,21 @@ impl FileOP for File {
 
     fn read_at(&self, pos: usize, data: &mut [u8]) -> usize {
         let inner = self.0.borrow_mut();
-        let remain = inner.file_size - pos;
-        let len = if remain < data.len() { remain } else { data.len() };
-        data[..len].copy_from_slice(&inner.buf[pos..pos + len]);
-        len
+        match inner.file_type {
+            FileType::File => inner.file.read_at(pos, data).unwrap_or(EIO),
+            _ => 0
+        }
     }
 
     fn write_at(&self, pos: usize, data: &[u8], count: usize) -> usize {
-        let mut inner = self.0.borrow_mut();
+        let inner = self.0.borrow_mut();
         if inner.file_type == FileType::File {
-            let end = pos + count;
-            if end >= inner.mem_size {
-                panic!("无法写入超出部分");
+            let count = count.min(data.len());
+            match inner.file.write_at(pos, &data[..count]) {
+                Ok(len) => len,
+                Err(RuntimeError::NoSpace) => ENOSPC,
+                Err(_) => EIO
             }
-
-            let start = pos;
-            inner.buf[start..end].copy_from_slice(&data);
-
-            // inner.offset += count;
-            // // 需要更新文件数据
-            // if inner.offset >= inner.file_size {
-            //     inner.file_size = inner.offset;
-            //     let _file_size = inner.file_size;
-            //     let _inode = inner.file.0.borrow_mut();
-            // }
-            count
         } else {
             // 写入虚拟文件
             count
@@ -238,7 +227,23 @@ impl FileOP for File {
     }
 
     fn get_size(&self) -> usize {
-        self.0.borrow_mut().file_size
+        let inner = self.0.borrow_mut();
+        match inner.file_type {
+            FileType::File => inner.file.get_file_size(),
+            _ => 0
+        }
+    }
+
+    fn file_type(&self) -> FileType {
+        self.get_file_type()
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), usize> {
+        let inner = self.0.borrow_mut();
+        if inner.file_type != FileType::File {
+            return Err(EINVAL);
+        }
+        inner.file.truncate(size).map_err(|_| EIO)
     }
 }
 
@@ -1,32 +1,44 @@
 
 use core::cell::RefCell;
 
-use alloc::{string::{String, ToString}, vec::Vec, rc::{Rc, Weak}};
-use fatfs::{Read, Write};
+use alloc::{string::{String, ToString}, vec::Vec, rc::{Rc, Weak}, collections::BTreeMap};
 
-use crate::{device::{DiskFile, Dir}, runtime_err::RuntimeError};
+use crate::{runtime_err::RuntimeError, task::{pipe::PipeBuf, unix_socket::UnixSocket}, interrupt::timer::TimeSpec};
+use crate::memory::{mem_map::MemMap, page_table::PTEFlags, addr::{PAGE_SIZE, get_buf_from_phys_page}};
 
-use super::{file::{FileType, File}, cache::get_cache_file, virt_file::VirtFile};
+use super::{file::{FileType, File, FileOP}, vfs, mount, dentry::DENTRY_CACHE,
+    page_cache::{PAGE_CACHE, PAGE_CACHE_SIZE, READAHEAD_PAGES}};
 
 
 pub static mut FILE_TREE: Option<Rc<INode>> = None;
 
+// 下一个节点的编号 编号不会重复使用
+static mut NEXT_INODE_ID: usize = 1;
+
+// 查找路径时最多经过的符号链接数量
+const MAX_SYMLINK_DEPTH: usize = 8;
+
+// 跨目录重命名覆盖文件时使用的临时名字
+const RENAME_TEMP: &str = ".rename.tmp";
+
 #[derive(Clone)]
 pub enum DiskFileEnum {
-    DiskFile(DiskFile),
-    DiskDir(Dir),
-    VirtFile(VirtFile),
-    VirtDir,
+    Inode(Rc<dyn vfs::Inode>),
+    Fifo(PipeBuf),
+    Socket(Weak<UnixSocket>),
     None
 }
 
 // 文件树原始树
 pub struct INodeInner {
+    pub id: usize,                      // 节点编号
     pub filename: String,               // 文件名
     pub file_type: FileType,            // 文件数类型
     pub parent: Option<Weak<INode>>,    // 父节点
-    pub children: Vec<Rc<INode>>,       // 子节点
-    pub file: DiskFileEnum              // 硬盘文件
+    pub children: Vec<Rc<INode>>,       // 内存中的子节点 文件系统中的子节点在目录项缓存中
+    pub file: DiskFileEnum,             // 硬盘文件
+    pub page_cache: BTreeMap<usize, Rc<MemMap>>,    // 页缓存 key为文件内的页号 打开和共享映射使用相同的物理页
+    pub mounted: Option<Rc<INode>>                  // 挂载在该节点上的文件系统根目录
 }
 
 pub struct INode(pub RefCell<INodeInner>);
@@ -35,15 +47,42 @@ impl INode {
     // 创建文件 创建文件时需要使用文件名
     pub fn new(filename: String, file: DiskFileEnum, 
             file_type: FileType, parent: Option<Weak<INode>>) -> Rc<Self> {
+        let id = unsafe {
+            NEXT_INODE_ID += 1;
+            NEXT_INODE_ID
+        };
         Rc::new(Self(RefCell::new(INodeInner {
+            id,
             filename, 
             file_type, 
             parent, 
             children: vec![],
-            file
+            file,
+            page_cache: BTreeMap::new(),
+            mounted: None
         })))
     }
 
+    // 创建文件系统的根目录节点 子节点在查找时读取
+    pub fn mount_root(filename: String, inode: Rc<dyn vfs::Inode>, parent: Option<Weak<INode>>) -> Rc<INode> {
+        INode::new(filename, DiskFileEnum::Inode(inode), FileType::Directory, parent)
+    }
+
+    // 创建文件系统中文件对应的子节点
+    fn new_child(self: &Rc<Self>, filename: &str, inode: Rc<dyn vfs::Inode>) -> Rc<INode> {
+        let file_type = inode.file_type();
+        INode::new(filename.to_string(), DiskFileEnum::Inode(inode), file_type, Some(Rc::downgrade(self)))
+    }
+
+    // 如果节点上挂载了文件系统 返回文件系统的根目录
+    pub fn mounted_root(self: Rc<Self>) -> Rc<INode> {
+        let mounted = self.0.borrow().mounted.clone();
+        match mounted {
+            Some(root) => root.mounted_root(),
+            None => self
+        }
+    }
+
     // 根目录节点
     pub fn root() -> Rc<INode> {
         unsafe {
@@ -54,18 +93,27 @@ impl INode {
         }
     }
 
-    // 添加节点到父节点
+    // 添加节点到父节点 同名的缓存目录项失效
     pub fn add(self: Rc<Self>, child: Rc<INode>) {
         let mut inner = self.0.borrow_mut();
         let mut cinner = child.0.borrow_mut();
         cinner.parent = Some(Rc::downgrade(&self));
+        let filename = cinner.filename.clone();
         drop(cinner);
         inner.children.push(child);
+        drop(inner);
+        DENTRY_CACHE.lock().remove(&self, &filename);
     }
 
+    // 获取子节点 经过挂载点时进入挂载的文件系统
     pub fn get_children(self: Rc<Self>, filename: &str) -> Result<Rc<INode>, RuntimeError> {
+        let node = self.mounted_root();
+        node.get_child(filename).map(|x| x.mounted_root())
+    }
+
+    fn get_child(self: Rc<Self>, filename: &str) -> Result<Rc<INode>, RuntimeError> {
         match filename {
-            "."     => Ok(self.clone()),
+            "" | "." => Ok(self.clone()),
             ".."    => {
                 let inner = self.0.borrow_mut();
                 match inner.parent.clone() {
@@ -81,78 +129,114 @@ impl INode {
                 }
             },
             _ => {
-                for child in self.clone_children() {
-                    if child.get_filename() == filename {
-                        return Ok(child.clone());
-                    }
+                let child = self.0.borrow().children.iter()
+                    .find(|x| x.get_filename() == filename).cloned();
+                match child {
+                    Some(child) => Ok(child),
+                    None => self.lookup(filename)
                 }
-                Err(RuntimeError::FileNotFound)
             }
         }
     }
 
+    // 在文件系统中查找子节点 查找结果保存在目录项缓存中 不存在时也进行缓存
+    fn lookup(self: Rc<Self>, filename: &str) -> Result<Rc<INode>, RuntimeError> {
+        if let Some(node) = DENTRY_CACHE.lock().get(&self, filename) {
+            return node.ok_or(RuntimeError::FileNotFound);
+        }
+        let dir = self.to_file().map_err(|_| RuntimeError::FileNotFound)?;
+        let node = dir.lookup(filename).ok().map(|inode| self.new_child(filename, inode));
+        DENTRY_CACHE.lock().insert(&self, filename, node.clone());
+        node.ok_or(RuntimeError::FileNotFound)
+    }
+
     pub fn find(self: Rc<Self>, path: &str) -> Result<Rc<INode>, RuntimeError> {
-        // traverse path
-        let (name, rest_opt) = get_curr_dir(path);
-        if let Some(rest) = rest_opt {
-            // 如果是文件夹
-            self.get_children(name)?.find(rest)
-        } else {
-            self.get_children(name)
+        self.walk(path, true, 0)
+    }
+
+    // 按照路径查找节点 经过符号链接时查找链接的目标 follow为false时不跟随最后一个符号链接
+    fn walk(self: Rc<Self>, path: &str, follow: bool, depth: usize) -> Result<Rc<INode>, RuntimeError> {
+        let (name, rest) = get_curr_dir(path);
+        let mut node = self.clone().get_children(name)?;
+        let mut depth = depth;
+        if node.get_file_type() == FileType::SymLink && (rest.is_some() || follow) {
+            if depth >= MAX_SYMLINK_DEPTH {
+                return Err(RuntimeError::FileNotFound);
+            }
+            depth += 1;
+            let target = node.read_link()?;
+            // 绝对路径从根目录开始 相对路径从链接所在的目录开始
+            let base = match target.starts_with('/') {
+                true => Self::root(),
+                false => self
+            };
+            node = base.walk(&target, true, depth)?;
+        }
+        match rest {
+            Some(rest) => node.walk(rest, follow, depth),
+            None => Ok(node)
         }
     }
 
-    // 根据路径 获取文件节点
-    pub fn get(current: Option<Rc<INode>>, path: &str) -> Result<Rc<INode>, RuntimeError> {
-        if let Some(node) = current {
-            node.find(path)
-        } else {
-            Self::root().find(path)
+    // 查找的起始节点 绝对路径从根目录开始
+    fn start(current: Option<Rc<INode>>, path: &str) -> Rc<INode> {
+        match path.starts_with('/') {
+            true => Self::root(),
+            false => current.unwrap_or_else(Self::root)
         }
     }
+
     // 根据路径 获取文件节点
-    pub fn open(current: Option<Rc<INode>>, path: &str) -> Result<Rc<File>, RuntimeError> {
-        let inode = Self::get(current, path)?;
-        if let Some(file) = get_cache_file(&inode.get_filename()) {
-            return Ok(file.clone());
-        }
+    pub fn get(current: Option<Rc<INode>>, path: &str) -> Result<Rc<INode>, RuntimeError> {
+        Self::start(current, path).walk(path, true, 0)
+    }
+
+    // 根据路径 获取文件节点 最后一个符号链接不跟随
+    pub fn get_nofollow(current: Option<Rc<INode>>, path: &str) -> Result<Rc<INode>, RuntimeError> {
+        Self::start(current, path).walk(path, false, 0)
+    }
+
+    // 获取路径的父目录和文件名
+    pub fn get_parent(current: Option<Rc<INode>>, path: &str) -> Result<(Rc<INode>, &str), RuntimeError> {
+        let (dir, filename) = split_path(path);
+        let pnode = match dir {
+            Some(dir) => Self::start(current, path).walk(dir, true, 0)?,
+            None => Self::start(current, path)
+        };
+        Ok((pnode.mounted_root(), filename))
+    }
+    // 打开文件节点 文件内容在读取时通过页缓存获取
+    pub fn open_node(inode: Rc<INode>) -> Result<Rc<File>, RuntimeError> {
         File::new(inode)
     }
     // 根据路径 获取文件节点
-    pub fn open_or_create(current: Option<Rc<INode>>, path: &str) -> Result<Rc<File>, RuntimeError> {
+    pub fn open(current: Option<Rc<INode>>, path: &str) -> Result<Rc<File>, RuntimeError> {
+        Self::open_node(Self::get(current, path)?)
+    }
+    // 根据路径 获取文件节点 不存在时创建文件
+    pub fn get_or_create(current: Option<Rc<INode>>, path: &str) -> Result<Rc<INode>, RuntimeError> {
         if let Ok(inode) = Self::get(current.clone(), path) {
-            if let Some(file) = get_cache_file(&inode.get_filename()) {
-                return Ok(file.clone());
-            }
-            File::new(inode)
+            Ok(inode)
         } else {
-            let (dir_path, filename) = split_path(path);
-            
-            debug!("split path: {:?}  filename: {}", dir_path, filename);
-
-            let dir_inode = 
-                dir_path.map_or(Ok(INode::root()), |x| INode::get(current, x))?;
-
-            let file = VirtFile::new(filename.to_string());
-
-            let parent_node = Some(Rc::downgrade(&dir_inode));
-            let file_node = INode::new(filename.to_string(), 
-            DiskFileEnum::VirtFile(file), FileType::VirtFile, parent_node);
-            dir_inode.clone().add(file_node.clone());
-            File::new(file_node)
-            // Err(RuntimeError::FileNotFound)
+            let (dir_inode, filename) = Self::get_parent(current, path)?;
+            dir_inode.create_child(filename, FileType::File)
         }
     }
 
     // 获取当前路径
     pub fn get_pwd(&self) -> String {
-        let tree_node = self.clone();
         let mut path = String::new();
-        loop {
-            path = path + "/" + &tree_node.get_filename();
-            if self.is_root() { break; }
+        let mut filename = self.get_filename();
+        let mut parent = self.0.borrow().parent.as_ref().and_then(|x| x.upgrade());
+        while let Some(node) = parent {
+            path = String::from("/") + &filename + &path;
+            filename = node.get_filename();
+            parent = node.0.borrow().parent.as_ref().and_then(|x| x.upgrade());
+        }
+        match path.is_empty() {
+            true => String::from("/"),
+            false => path
         }
-        path
     }
 
     // 判断当前是否为根目录
@@ -169,37 +253,52 @@ impl INode {
         }
     }
 
-    pub fn is_virt_file(&self) -> bool {
-        match self.0.borrow().file_type {
-            FileType::VirtFile => true,
-            _ => false
-        }
-    }
-
     // 获取文件名
     pub fn get_filename(&self) -> String{
         self.0.borrow_mut().filename.clone()
     }
 
-    // 获取子元素
-    pub fn clone_children(&self) -> Vec<Rc<INode>> {
-        self.0.borrow().children.clone()
+    // 获取子元素 包括内存中的节点和文件系统中的文件
+    pub fn clone_children(self: &Rc<Self>) -> Vec<Rc<INode>> {
+        let mut children = self.0.borrow().children.clone();
+        let entries = match self.to_file().and_then(|x| x.read_dir()) {
+            Ok(entries) => entries,
+            Err(_) => return children
+        };
+        for entry in entries {
+            let filename = entry.name();
+            if children.iter().any(|x| x.get_filename() == filename) {
+                continue;
+            }
+            let cached = DENTRY_CACHE.lock().get(self, &filename).flatten();
+            let node = match cached {
+                Some(node) => node,
+                None => {
+                    let node = self.new_child(&filename, entry.inode());
+                    DENTRY_CACHE.lock().insert(self, &filename, Some(node.clone()));
+                    node
+                }
+            };
+            children.push(node);
+        }
+        children
     }
 
     // 判断是否为空
-    pub fn is_empty(&self) -> bool {
-        self.0.borrow_mut().children.is_empty()
+    pub fn is_empty(self: &Rc<Self>) -> bool {
+        self.clone_children().is_empty()
     }
 
     // 删除子节点
     pub fn delete(&self, filename: &str) {
         self.0.borrow_mut().children.retain(|c| c.get_filename() != filename);
+        DENTRY_CACHE.lock().remove(self, filename);
     }
 
     // 获取文件大小
     pub fn get_file_size(&self) -> usize {
         match &self.0.borrow_mut().file {
-            DiskFileEnum::DiskFile(f) => f.size().unwrap() as usize,
+            DiskFileEnum::Inode(inode) => inode.size(),
             _ => 0
         }
     }
@@ -210,60 +309,310 @@ impl INode {
     }
 
     // 读取文件内容
-    pub fn read(&self) -> Result<Vec<u8>, RuntimeError>{
+    pub fn read(self: &Rc<Self>) -> Result<Vec<u8>, RuntimeError>{
         let mut file_vec = vec![0u8; self.get_file_size()];
-        // self.to_file()?.read_exact(&mut file_vec);
-        self.read_to(&mut file_vec)?;
+        self.read_at(0, &mut file_vec)?;
         Ok(file_vec)
     }
 
-    pub fn to_file(&self) -> Result<DiskFile, RuntimeError>{
-        if let DiskFileEnum::DiskFile(f) = &self.0.borrow().file {
-            Ok(f.clone())
+    // 获取文件系统中的节点
+    pub fn to_file(&self) -> Result<Rc<dyn vfs::Inode>, RuntimeError>{
+        if let DiskFileEnum::Inode(inode) = &self.0.borrow().file {
+            Ok(inode.clone())
         } else {
             Err(RuntimeError::NotRWFile)
         }
     }
     
-    // 读取文件内容
-    pub fn read_to(&self, buf: &mut [u8]) -> Result<usize, RuntimeError>  {
-        // 不再处理虚拟文件
-        // self.0.borrow_mut().file.read_exact(buf);
-        // 读取错误 但是会抛出异常 UnexpectedEOF
-        // self.to_file()?.read_exact(buf).expect("读取错误");
-        let mut file = self.to_file()?;
-        file.read_exact(buf);
-        Ok(buf.len())
+    // 从pos开始读取文件内容 通过页缓存读取
+    pub fn read_at(self: &Rc<Self>, pos: usize, buf: &mut [u8]) -> Result<usize, RuntimeError> {
+        let size = self.get_file_size();
+        if pos >= size {
+            return Ok(0);
+        }
+        let end = size.min(pos + buf.len());
+        let mut curr = pos;
+        while curr < end {
+            let page = self.get_cache_page(curr / PAGE_SIZE)?;
+            let offset = curr % PAGE_SIZE;
+            let len = (PAGE_SIZE - offset).min(end - curr);
+            buf[curr - pos..curr - pos + len]
+                .copy_from_slice(&get_buf_from_phys_page(page.ppn, 1)[offset..offset + len]);
+            curr += len;
+        }
+        Ok(end - pos)
     }
 
-    // 写入设备
-    pub fn write(&self, buf: &mut [u8]) -> Result<usize, RuntimeError> {
-        // self.0.borrow_mut().file.write(buf).unwrap()
-        self.to_file()?.write(buf).map_err(|_| RuntimeError::NotRWFile)
+    // 从pos开始写入文件 同时更新共享映射的缓存页
+    pub fn write_at(&self, pos: usize, buf: &[u8]) -> Result<usize, RuntimeError> {
+        let len = self.to_file()?.write_at(pos, buf)?;
+        if len == 0 {
+            return Ok(0);
+        }
+        let end = pos + len;
+        for (index, page) in self.0.borrow().page_cache.range(pos / PAGE_SIZE..=(end - 1) / PAGE_SIZE) {
+            let page_start = index * PAGE_SIZE;
+            let start = pos.max(page_start);
+            let stop = end.min(page_start + PAGE_SIZE);
+            get_buf_from_phys_page(page.ppn, 1)[start - page_start..stop - page_start]
+                .copy_from_slice(&buf[start - pos..stop - pos]);
+        }
+        Ok(len)
+    }
+
+    // 修改文件大小 缓存页中超出文件的部分清零
+    pub fn truncate(&self, size: usize) -> Result<(), RuntimeError> {
+        self.to_file()?.truncate(size)?;
+        for (index, page) in self.0.borrow().page_cache.range(size / PAGE_SIZE..) {
+            let offset = size.max(index * PAGE_SIZE) - index * PAGE_SIZE;
+            get_buf_from_phys_page(page.ppn, 1)[offset..].fill(0);
+        }
+        Ok(())
+    }
+
+    // 由文件系统驱动提供打开的文件 例如设备文件和内存文件
+    pub fn open_special(&self) -> Option<Rc<dyn FileOP>> {
+        self.to_file().ok().and_then(|x| x.open())
     }
 
-    // 创建文件夹
-    // TODO: 创建文件夹
-    pub fn mkdir(current: Option<Rc<INode>>, path: &str, _flags: u16) -> Result<Rc<INode>, RuntimeError>{
+    // 获取文件的缓存页 不存在时从硬盘读取并预读后面的页
+    pub fn get_cache_page(self: &Rc<Self>, index: usize) -> Result<Rc<MemMap>, RuntimeError> {
+        // 内存文件系统直接使用保存文件内容的页
+        if let Some(page) = self.to_file().ok().and_then(|x| x.page(index)) {
+            return Ok(page);
+        }
+        let page = self.0.borrow().page_cache.get(&index).cloned();
+        if let Some(page) = page {
+            PAGE_CACHE.lock().touch(self, index);
+            return Ok(page);
+        }
+        let size = self.get_file_size();
+        let page = self.read_page(index, size)?;
+        // 预读失败不影响当前页 遇到已经缓存的页或者文件末尾时停止
+        for next in index + 1..index + READAHEAD_PAGES {
+            if next * PAGE_SIZE >= size || self.0.borrow().page_cache.contains_key(&next) {
+                break;
+            }
+            if self.read_page(next, size).is_err() {
+                break;
+            }
+        }
+        Ok(page)
+    }
+
+    // 从硬盘读取一页加入缓存 超出文件的部分为0 缓存已满时淘汰最久没有使用的页
+    fn read_page(self: &Rc<Self>, index: usize, size: usize) -> Result<Rc<MemMap>, RuntimeError> {
+        {
+            let mut page_cache = PAGE_CACHE.lock();
+            if page_cache.len() >= PAGE_CACHE_SIZE {
+                page_cache.shrink(1);
+            }
+        }
+        let page = MemMap::new(0usize.into(), 1, PTEFlags::UVRWX)?;
+        let pos = index * PAGE_SIZE;
+        if pos < size {
+            let len = (size - pos).min(PAGE_SIZE);
+            self.to_file()?.read_at(pos, &mut get_buf_from_phys_page(page.ppn, 1)[..len])?;
+        }
+        self.0.borrow_mut().page_cache.insert(index, page.clone());
+        PAGE_CACHE.lock().touch(self, index);
+        Ok(page)
+    }
+
+    // 将缓存页写回硬盘 不改变文件大小
+    pub fn writeback_page(&self, index: usize) -> Result<(), RuntimeError> {
+        let page = match self.0.borrow().page_cache.get(&index) {
+            Some(page) => page.clone(),
+            None => return Ok(())
+        };
+        let pos = index * PAGE_SIZE;
+        let size = self.get_file_size();
+        if pos >= size {
+            return Ok(());
+        }
+        let len = (size - pos).min(PAGE_SIZE);
+        self.to_file()?.write_at(pos, &get_buf_from_phys_page(page.ppn, 1)[..len])?;
+        Ok(())
+    }
+
+    // 创建文件夹 已经存在时直接返回
+    pub fn mkdir(current: Option<Rc<INode>>, path: &str, mode: u16) -> Result<Rc<INode>, RuntimeError>{
         match Self::get(current.clone(), path) {
             Ok(inode) => Ok(inode),
             Err(_) => {
-                // 创建文件夹
-                let (dir, filename) = split_path(path);
-                let pnode = match dir {
-                    Some(path) => INode::get(current, path)?,
-                    None => INode::root()
-                };
-
-                let parent_node = Some(Rc::downgrade(&pnode));
-                let file_node = INode::new(filename.to_string(), 
-                DiskFileEnum::VirtDir, FileType::Directory, parent_node);
-                pnode.clone().add(file_node.clone());
-                Ok(file_node)
+                let (pnode, filename) = Self::get_parent(current, path)?;
+                let node = pnode.create_child(filename, FileType::Directory)?;
+                // 不保存权限的文件系统忽略
+                let _ = node.set_mode(mode as u32);
+                Ok(node)
             }
         }
     }
 
+    // 创建文件节点 命名管道创建新的管道缓冲区 其他类型在文件系统中创建文件
+    pub fn mknod(current: Option<Rc<INode>>, path: &str, file_type: FileType) -> Result<Rc<INode>, RuntimeError> {
+        match file_type {
+            FileType::Pipeline => Self::create(current, path, DiskFileEnum::Fifo(PipeBuf::new()), file_type),
+            _ => {
+                let (pnode, filename) = Self::get_parent(current, path)?;
+                pnode.create_child(filename, file_type)
+            }
+        }
+    }
+
+    // 在路径对应的目录下添加节点
+    pub fn create(current: Option<Rc<INode>>, path: &str, file: DiskFileEnum, file_type: FileType) -> Result<Rc<INode>, RuntimeError> {
+        let (dir, filename) = split_path(path);
+        let pnode = match dir {
+            Some(path) => INode::get(current, path)?,
+            None => current.unwrap_or_else(INode::root)
+        }.mounted_root();
+        let parent_node = Some(Rc::downgrade(&pnode));
+        let file_node = INode::new(filename.to_string(), file, file_type, parent_node);
+        pnode.add(file_node.clone());
+        Ok(file_node)
+    }
+
+    // 在目录下创建文件 目录或者符号链接 文件系统不支持创建时返回错误
+    pub fn create_child(self: Rc<Self>, filename: &str, file_type: FileType) -> Result<Rc<INode>, RuntimeError> {
+        let pnode = self.mounted_root();
+        let inode = pnode.to_file()?.create(filename, file_type)?;
+        let file_node = pnode.new_child(filename, inode);
+        DENTRY_CACHE.lock().insert(&pnode, filename, Some(file_node.clone()));
+        Ok(file_node)
+    }
+
+    // 创建指向target的符号链接
+    pub fn symlink(current: Option<Rc<INode>>, target: &str, path: &str) -> Result<Rc<INode>, RuntimeError> {
+        let (pnode, filename) = Self::get_parent(current, path)?;
+        let node = pnode.create_child(filename, FileType::SymLink)?;
+        node.to_file()?.write_at(0, target.as_bytes())?;
+        Ok(node)
+    }
+
+    // 读取符号链接的目标
+    pub fn read_link(&self) -> Result<String, RuntimeError> {
+        let inode = self.to_file()?;
+        let mut buf = vec![0u8; inode.size()];
+        let len = inode.read_at(0, &mut buf)?;
+        Ok(String::from_utf8_lossy(&buf[..len]).to_string())
+    }
+
+    // 在path创建当前节点的硬链接 只支持同一个文件系统中的文件
+    pub fn link(&self, current: Option<Rc<INode>>, path: &str) -> Result<(), RuntimeError> {
+        let (pnode, filename) = Self::get_parent(current, path)?;
+        pnode.to_file()?.link(filename, self.to_file()?)?;
+        DENTRY_CACHE.lock().remove(&pnode, filename);
+        Ok(())
+    }
+
+    // 获取文件的元数据 编号使用文件系统中的编号 硬链接的编号相同
+    pub fn metadata(&self) -> vfs::Metadata {
+        let mut metadata = match self.to_file() {
+            Ok(inode) => inode.metadata(),
+            Err(_) => vfs::Metadata::new(self.get_file_type(), 0)
+        };
+        if metadata.ino == 0 {
+            metadata.ino = self.0.borrow().id;
+        }
+        metadata
+    }
+
+    // 修改权限位
+    pub fn set_mode(&self, mode: u32) -> Result<(), RuntimeError> {
+        self.to_file()?.set_mode(mode)
+    }
+
+    // 修改访问时间和修改时间
+    pub fn set_times(&self, atime: Option<TimeSpec>, mtime: Option<TimeSpec>) -> Result<(), RuntimeError> {
+        self.to_file()?.set_times(atime, mtime)
+    }
+
+    // 移动节点到parent目录下并改名 文件系统中的文件只能在同一个目录下改名
+    pub fn rename(self: &Rc<Self>, parent: Rc<INode>, filename: &str) -> Result<(), RuntimeError> {
+        // 目标已经存在时 目录只能替换空目录 文件不能替换目录
+        if let Ok(target) = parent.clone().get_children(filename) {
+            if Rc::ptr_eq(&target, self) {
+                return Ok(());
+            }
+            match (self.is_dir(), target.is_dir()) {
+                (true, false) => return Err(RuntimeError::NotDir),
+                (false, true) => return Err(RuntimeError::IsDir),
+                (true, true) if !target.is_empty() => return Err(RuntimeError::NotEmpty),
+                _ => {}
+            }
+        }
+        let old_parent = self.0.borrow().parent.as_ref()
+            .and_then(|x| x.upgrade()).ok_or(RuntimeError::FileNotFound)?;
+        let old_filename = self.get_filename();
+        let pinned = old_parent.0.borrow().children.iter().any(|x| Rc::ptr_eq(x, self));
+        if pinned {
+            // 内存中的节点直接移动
+            old_parent.delete(&old_filename);
+            if let Ok(target) = parent.clone().get_child(filename) {
+                target.del_self();
+            }
+            self.0.borrow_mut().filename = filename.to_string();
+            parent.add(self.clone());
+        } else {
+            if !Rc::ptr_eq(&old_parent, &parent) {
+                // 不同文件系统之间不能移动
+                if !mount::same_fs(&old_parent, &parent) {
+                    return Err(RuntimeError::NotRWFile);
+                }
+                // 不同目录之间先建立硬链接再删除原来的目录项 不支持硬链接时返回错误
+                // 目标已经存在时先链接到临时名字 链接成功后再替换目标
+                let dir = parent.to_file()?;
+                let exists = dir.lookup(filename).is_ok();
+                let name = if exists { RENAME_TEMP } else { filename };
+                dir.link(name, self.to_file()?).map_err(|_| RuntimeError::NotRWFile)?;
+                if exists {
+                    if let Err(err) = dir.rename(RENAME_TEMP, filename) {
+                        let _ = dir.remove(RENAME_TEMP);
+                        return Err(err);
+                    }
+                }
+                old_parent.to_file()?.remove(&old_filename)?;
+                parent.delete(filename);
+                old_parent.delete(&old_filename);
+                let mut inner = self.0.borrow_mut();
+                inner.filename = filename.to_string();
+                inner.parent = Some(Rc::downgrade(&parent));
+                drop(inner);
+                DENTRY_CACHE.lock().insert(&parent, filename, Some(self.clone()));
+                return Ok(());
+            }
+            let dir = parent.to_file()?;
+            dir.rename(&old_filename, filename)?;
+            parent.delete(filename);
+            parent.delete(&old_filename);
+            // 改名后目录项的位置可能改变 重新查找文件
+            let inode = dir.lookup(filename)?;
+            let mut inner = self.0.borrow_mut();
+            inner.filename = filename.to_string();
+            inner.file = DiskFileEnum::Inode(inode);
+            drop(inner);
+            DENTRY_CACHE.lock().insert(&parent, filename, Some(self.clone()));
+        }
+        Ok(())
+    }
+
+    // 获取命名管道的缓冲区
+    pub fn get_fifo(&self) -> Option<PipeBuf> {
+        match &self.0.borrow().file {
+            DiskFileEnum::Fifo(pipe) => Some(pipe.clone()),
+            _ => None
+        }
+    }
+
+    // 获取绑定在节点上的套接字 套接字已经关闭时返回None
+    pub fn get_socket(&self) -> Option<Rc<UnixSocket>> {
+        match &self.0.borrow().file {
+            DiskFileEnum::Socket(socket) => socket.upgrade(),
+            _ => None
+        }
+    }
+
     // 删除自身
     pub fn del_self(&self) {
         let inner = self.0.borrow_mut();
@@ -272,6 +621,10 @@ impl INode {
             let parent = parent.upgrade().unwrap();
             let filename = inner.filename.clone();
             drop(inner);
+            // 文件系统不支持删除时只删除内存中的节点
+            if let Ok(inode) = parent.to_file() {
+                let _ = inode.remove(&filename);
+            }
             parent.delete(&filename);
         }
     }
@@ -316,57 +669,14 @@ fn get_curr_dir(path: &str) -> (&str, Option<&str>) {
 }
 
 // spilit_path get dir and filename
-fn split_path(path: &str) -> (Option<&str>, &str) {
+pub fn split_path(path: &str) -> (Option<&str>, &str) {
     let trimmed_path = path.trim_matches('/');
     trimmed_path.rfind('/').map_or((None, trimmed_path), |n| {
         (Some(&trimmed_path[..n]), &trimmed_path[n + 1..])
     })
 }
 
-// pub fn mount(path: &str, root_dir: Dir) {
-//     for i in root_dir.iter() {
-//         let file = i.unwrap();
-//         if file.is_dir() {
-//             // 如果是文件夹的话进行 深度遍历
-//             mount(&(path.to_string() + &file.file_name() + "/"), file.to_dir());
-//         } else {
-//             // 如果是文件的话则进行挂载
-//             INode::new(filename, file, file_type, parent)
-//         }
-//     }
-// }
-
-pub fn add_files_to_dir(dir: Dir, node: Rc<INode>) {
-    for file_entry in dir.iter() {
-        let file_entry = file_entry.expect("文件节点异常");
-        let filename = file_entry.file_name();
-        if filename == "." || filename == ".." {
-            continue;
-        }
-        if file_entry.is_dir() {
-            let child_dir = file_entry.to_dir();
-            let parent_node = Some(Rc::downgrade(&node));
-            let dir_node = INode::new(filename, 
-                DiskFileEnum::DiskDir(child_dir.clone()), FileType::Directory, parent_node);
-            node.clone().add(dir_node.clone());
-            add_files_to_dir(child_dir, dir_node);
-        } else if file_entry.is_file() {
-            let parent_node = Some(Rc::downgrade(&node));
-            let dir_node = INode::new(filename, 
-        DiskFileEnum::DiskFile(file_entry.to_file()), FileType::File, parent_node);
-            node.clone().add(dir_node.clone());
-        } else {
-            error!("不支持的文件类型");
-        }
-    }
+// 使用文件系统的根目录初始化文件树
+pub fn init(root: Rc<INode>) {
+    unsafe { FILE_TREE = Some(root); }
 }
-
-pub fn init(path: &str, root_dir: Dir) {
-    if path == "/" {
-        let inode = INode::new(String::from(""), DiskFileEnum::DiskDir(root_dir.clone()), 
-            FileType::Directory, None);
-        // 添加到文件树子节点
-        unsafe { FILE_TREE = Some(inode.clone()); }
-        add_files_to_dir(root_dir, inode);
-    }
-}
\ No newline at end of file
@@ -1,16 +1,29 @@
 pub mod file;
-mod partition;
 pub mod filetree;
 pub mod stdio;
-pub mod cache;
 pub mod specials;
-pub mod virt_file;
+pub mod vfs;
+pub mod mount;
+pub mod dentry;
+pub mod page_cache;
+pub mod fat;
+pub mod tmpfs;
+pub mod procfs;
+pub mod devfs;
 
-pub use partition::Partition;
+use alloc::rc::Rc;
 
-use crate::device::root_dir;
+use crate::device::GLOBAL_FS;
+
+use self::devfs::DevFileSystem;
+use self::fat::FatFileSystem;
+use self::filetree::INode;
+use self::procfs::ProcFileSystem;
+use self::tmpfs::TmpFileSystem;
+use self::vfs::Filesystem;
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct StatFS{
     pub f_type: u64,        //文件系统的类型
     pub f_bsize: u64,       //经优化后的传输块的大小
@@ -23,9 +36,28 @@ pub struct StatFS{
     pub f_namelen: u64,     //文件名的最大长度
 }
 
-// 初始化文件系统
+// 初始化文件系统 挂载根文件系统和内核文件系统
 pub fn init() {
-    // 不再进行文件系统的初始化？ 等待处理 
-    filetree::init("/", root_dir());
+    filetree::init(mount::mount_root("/dev/vda2", FatFileSystem::new()));
+    mount_at("proc", "/proc", ProcFileSystem::new());
+    mount_at("udev", "/dev", DevFileSystem::new());
+    mount_at("tmpfs", "/tmp", TmpFileSystem::new());
+    mount_at("tmpfs", "/var", TmpFileSystem::new());
+    mount_at("tmpfs", "/dev/shm", TmpFileSystem::new());
     info!("初始化文件系统");
+}
+
+// 关机前同步文件系统 写入的数据已经直接写入硬盘 只需要清除脏标志
+pub fn sync() {
+    if GLOBAL_FS.lock().set_dirty_flag(false).is_err() {
+        error!("同步文件系统失败");
+    }
+}
+
+// 挂载内核使用的文件系统 挂载点不存在时创建
+fn mount_at(source: &str, path: &str, fs: Rc<dyn Filesystem>) {
+    let target = INode::mkdir(None, path, 0).expect("can't create mount point");
+    if mount::mount(source, target, fs, 0).is_err() {
+        error!("挂载文件系统失败: {}", path);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,116 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+
+use crate::sync::mutex::Mutex;
+use crate::sys_call::consts::{EBUSY, EINVAL, ENOTDIR};
+
+use super::StatFS;
+use super::filetree::INode;
+use super::vfs::Filesystem;
+
+// 只读挂载
+pub const MS_RDONLY: usize = 1;
+
+// 挂载的文件系统
+pub struct MountPoint {
+    pub source: String,                 // 挂载的设备
+    pub fs: Rc<dyn Filesystem>,         // 文件系统实例
+    pub flags: usize,                   // 挂载标志
+    pub root: Rc<INode>,                // 文件系统的根目录节点
+    pub covered: Option<Rc<INode>>      // 被覆盖的挂载点 根文件系统为None
+}
+
+lazy_static! {
+    // 挂载表 按照挂载的绝对路径保存
+    pub static ref MOUNTS: Mutex<BTreeMap<String, MountPoint>> = Mutex::new(BTreeMap::new());
+}
+
+// 挂载根文件系统
+pub fn mount_root(source: &str, fs: Rc<dyn Filesystem>) -> Rc<INode> {
+    let root = INode::mount_root(String::new(), fs.root(), None);
+    MOUNTS.lock().insert("/".to_string(), MountPoint {
+        source: source.to_string(),
+        fs,
+        flags: 0,
+        root: root.clone(),
+        covered: None
+    });
+    root
+}
+
+// 将文件系统挂载到target目录 target下原有的内容被覆盖
+pub fn mount(source: &str, target: Rc<INode>, fs: Rc<dyn Filesystem>, flags: usize) -> Result<(), usize> {
+    if !target.is_dir() {
+        return Err(ENOTDIR);
+    }
+    let path = target.get_pwd();
+    let mut mounts = MOUNTS.lock();
+    if mounts.contains_key(&path) {
+        return Err(EBUSY);
+    }
+    let parent = target.0.borrow().parent.clone();
+    let root = INode::mount_root(target.get_filename(), fs.root(), parent);
+    target.0.borrow_mut().mounted = Some(root.clone());
+    mounts.insert(path, MountPoint {
+        source: source.to_string(),
+        fs,
+        flags,
+        root,
+        covered: Some(target)
+    });
+    Ok(())
+}
+
+// 卸载target上的文件系统 target下还有其他挂载的文件系统时返回EBUSY
+pub fn umount(target: Rc<INode>) -> Result<(), usize> {
+    let path = target.get_pwd();
+    let mut mounts = MOUNTS.lock();
+    let covered = match mounts.get(&path) {
+        Some(mount) if Rc::ptr_eq(&mount.root, &target) => mount.covered.clone(),
+        _ => return Err(EINVAL)
+    };
+    let prefix = path.clone() + "/";
+    if covered.is_none() || mounts.keys().any(|x| x.starts_with(&prefix)) {
+        return Err(EBUSY);
+    }
+    mounts.remove(&path);
+    if let Some(covered) = covered {
+        covered.0.borrow_mut().mounted = None;
+    }
+    Ok(())
+}
+
+// 获取节点所在文件系统的挂载路径 使用路径最长的挂载点
+fn mount_path(node: &INode) -> String {
+    let path = node.get_pwd();
+    MOUNTS.lock().keys().filter(|x| {
+        x.as_str() == "/" || path == **x || path.starts_with(&(x.to_string() + "/"))
+    }).max_by_key(|x| x.len()).cloned().expect("root filesystem not mounted")
+}
+
+// 获取节点所在文件系统的信息
+pub fn statfs(node: &INode) -> StatFS {
+    let path = mount_path(node);
+    let fs = MOUNTS.lock()[&path].fs.clone();
+    fs.statfs()
+}
+
+// 判断两个节点是否在同一个文件系统中
+pub fn same_fs(a: &INode, b: &INode) -> bool {
+    mount_path(a) == mount_path(b)
+}
+
+// 生成/proc/mounts的内容
+pub fn mounts_info() -> String {
+    let mut info = String::new();
+    for (path, mount) in MOUNTS.lock().iter() {
+        let mode = match mount.flags & MS_RDONLY {
+            0 => "rw",
+            _ => "ro"
+        };
+        info += &format!("{} {} {} {},nosuid,nodev,noexec,relatime 0 0\n",
+            mount.source, path, mount.fs.fs_type(), mode);
+    }
+    info
+}
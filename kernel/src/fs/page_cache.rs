@@ -0,0 +1,116 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::{Rc, Weak};
+use alloc::vec::Vec;
+
+use crate::memory::addr::get_pages_num;
+use crate::sync::mutex::Mutex;
+
+use super::filetree::INode;
+
+// 页缓存的最大页数 超过后淘汰最久没有使用的页
+#[cfg(not(feature = "board_k210"))]
+pub const PAGE_CACHE_SIZE: usize = 2048;
+
+#[cfg(feature = "board_k210")]
+pub const PAGE_CACHE_SIZE: usize = 256;
+
+// 读取不在缓存中的页时 连同后面的页一起读取
+pub const READAHEAD_PAGES: usize = 8;
+
+// 缓存页的使用记录 页保存在节点的page_cache中
+struct CachedPage {
+    node: Weak<INode>,
+    tick: usize
+}
+
+// 文件页缓存的淘汰顺序 打开同一个节点的文件共享节点中的缓存页
+pub struct PageCache {
+    entries: BTreeMap<(usize, usize), CachedPage>,  // (节点编号, 页号) -> 使用记录
+    lru: BTreeMap<usize, (usize, usize)>,           // 最后使用时间 -> 缓存页
+    tick: usize
+}
+
+lazy_static! {
+    pub static ref PAGE_CACHE: Mutex<PageCache> = Mutex::new(PageCache::new());
+}
+
+impl PageCache {
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0
+        }
+    }
+
+    // 缓存页的数量
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // 记录缓存页的使用 新加入的页也使用这个方法
+    pub fn touch(&mut self, node: &Rc<INode>, index: usize) {
+        let key = (node.0.borrow().id, index);
+        self.tick += 1;
+        if let Some(page) = self.entries.get_mut(&key) {
+            self.lru.remove(&page.tick);
+            page.tick = self.tick;
+        } else {
+            self.entries.insert(key, CachedPage { node: Rc::downgrade(node), tick: self.tick });
+        }
+        self.lru.insert(self.tick, key);
+    }
+
+    // 从最久没有使用的页开始释放count页 返回释放的页数
+    // 共享映射正在使用的页和正在访问的节点不能释放
+    pub fn shrink(&mut self, count: usize) -> usize {
+        let mut freed = 0;
+        let keys: Vec<(usize, usize)> = self.lru.values().cloned().collect();
+        for key in keys {
+            if freed >= count {
+                break;
+            }
+            // 节点已经释放时缓存页随节点一起释放 只需要删除记录
+            if let Some(node) = self.entries[&key].node.upgrade() {
+                let mut inner = match node.0.try_borrow_mut() {
+                    Ok(inner) => inner,
+                    Err(_) => continue
+                };
+                match inner.page_cache.get(&key.1) {
+                    Some(page) if Rc::strong_count(page) > 1 => continue,
+                    Some(_) => {
+                        inner.page_cache.remove(&key.1);
+                        freed += 1;
+                    }
+                    None => {}
+                }
+            }
+            if let Some(page) = self.entries.remove(&key) {
+                self.lru.remove(&page.tick);
+            }
+        }
+        freed
+    }
+}
+
+// 内存不足时释放所有可以释放的缓存页 申请连续的页需要尽可能多的空闲页
+pub fn reclaim() -> bool {
+    PAGE_CACHE.lock().shrink(usize::MAX) > 0
+}
+
+// 预先读取文件到页缓存 减少第一次执行时的读取
+pub fn prefetch(path: &str) {
+    let node = match INode::get(None, path) {
+        Ok(node) => node,
+        Err(_) => {
+            warn!("缓冲文件不存在: {}", path);
+            return;
+        }
+    };
+    info!("缓冲文件: {}", path);
+    for index in 0..get_pages_num(node.get_file_size()).min(PAGE_CACHE_SIZE) {
+        if node.get_cache_page(index).is_err() {
+            break;
+        }
+    }
+}
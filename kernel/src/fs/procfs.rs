@@ -0,0 +1,29 @@
+use alloc::rc::Rc;
+
+use super::file::{FileOP, FileType};
+use super::specials::SpecialDir;
+use super::specials::proc_meminfo::ProcMeminfo;
+use super::specials::proc_mounts::ProcMounts;
+use super::vfs::{Filesystem, Inode};
+
+// proc文件系统 文件内容由内核生成
+pub struct ProcFileSystem(Rc<SpecialDir>);
+
+impl ProcFileSystem {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self(Rc::new(SpecialDir::new(&[
+            ("mounts", FileType::File, || Rc::new(ProcMounts::new()) as Rc<dyn FileOP>),
+            ("meminfo", FileType::File, || Rc::new(ProcMeminfo::new()) as Rc<dyn FileOP>)
+        ]))))
+    }
+}
+
+impl Filesystem for ProcFileSystem {
+    fn fs_type(&self) -> &'static str {
+        "proc"
+    }
+
+    fn root(&self) -> Rc<dyn Inode> {
+        self.0.clone()
+    }
+}
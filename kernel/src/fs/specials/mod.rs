@@ -1,4 +1,48 @@
 pub mod proc_mounts;
 pub mod proc_meminfo;
 pub mod etc_adjtime;
-pub mod dev_rtc;
\ No newline at end of file
+pub mod dev_rtc;
+
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::runtime_err::RuntimeError;
+
+use super::file::{FileOP, FileType};
+use super::vfs::{DirEntry, Inode};
+
+// 内核生成的文件 每次打开时创建新的文件
+pub struct SpecialFile(pub FileType, pub fn() -> Rc<dyn FileOP>);
+
+impl Inode for SpecialFile {
+    fn file_type(&self) -> FileType {
+        self.0
+    }
+
+    fn open(&self) -> Option<Rc<dyn FileOP>> {
+        Some(self.1())
+    }
+}
+
+// 内核文件系统中内容固定的目录
+pub struct SpecialDir(pub Vec<(String, Rc<dyn Inode>)>);
+
+impl SpecialDir {
+    pub fn new(files: &[(&str, FileType, fn() -> Rc<dyn FileOP>)]) -> Self {
+        Self(files.iter().map(|(name, file_type, open)| {
+            let inode: Rc<dyn Inode> = Rc::new(SpecialFile(*file_type, *open));
+            (name.to_string(), inode)
+        }).collect())
+    }
+}
+
+impl Inode for SpecialDir {
+    fn file_type(&self) -> FileType {
+        FileType::Directory
+    }
+
+    fn read_dir(&self) -> Result<Vec<Rc<dyn DirEntry>>, RuntimeError> {
+        Ok(self.0.iter().map(|x| Rc::new(x.clone()) as Rc<dyn DirEntry>).collect())
+    }
+}
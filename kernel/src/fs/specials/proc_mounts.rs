@@ -1,6 +1,7 @@
 use core::cell::RefCell;
 
 use crate::fs::file::FileOP;
+use crate::fs::mount::mounts_info;
 
 pub struct ProcMounts(RefCell<bool>);
 
@@ -22,10 +23,11 @@ impl FileOP for ProcMounts {
     fn read_at(&self, _pos: usize, data: &mut [u8]) -> usize {
         let readable = *self.0.borrow_mut();
         if readable {
-            let bytes = b"fs / fs rw,nosuid,nodev,noexec,relatime 0 0";
-            data[..bytes.len()].copy_from_slice(bytes);
+            let info = mounts_info();
+            let len = info.len().min(data.len());
+            data[..len].copy_from_slice(&info.as_bytes()[..len]);
             *self.0.borrow_mut() = false;
-            bytes.len()
+            len
         } else {
             0
         }
@@ -1,5 +1,5 @@
 use crate::console::puts;
-use super::file::FileOP;
+use super::file::{FileOP, FileType};
 
 pub struct StdIn;
 pub struct StdOut;
@@ -27,6 +27,10 @@ impl FileOP for StdIn {
     fn get_size(&self) -> usize {
         0
     }
+
+    fn file_type(&self) -> FileType {
+        FileType::Device
+    }
 }
 
 impl FileOP for StdOut {
@@ -50,6 +54,10 @@ impl FileOP for StdOut {
     fn get_size(&self) -> usize {
         0
     }
+
+    fn file_type(&self) -> FileType {
+        FileType::Device
+    }
 }
 
 impl FileOP for StdErr {
@@ -75,6 +83,10 @@ impl FileOP for StdErr {
     fn get_size(&self) -> usize {
         0
     }
+
+    fn file_type(&self) -> FileType {
+        FileType::Device
+    }
 }
 
 impl FileOP for StdZero {
@@ -98,6 +110,10 @@ impl FileOP for StdZero {
     fn get_size(&self) -> usize {
         0
     }
+
+    fn file_type(&self) -> FileType {
+        FileType::Device
+    }
 }
 
 impl FileOP for StdNull {
@@ -120,4 +136,8 @@ impl FileOP for StdNull {
     fn get_size(&self) -> usize {
         0
     }
+
+    fn file_type(&self) -> FileType {
+        FileType::Device
+    }
 }
@@ -0,0 +1,399 @@
+use core::cell::{Cell, RefCell};
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::interrupt::timer::TimeSpec;
+use crate::memory::addr::{get_buf_from_phys_page, PAGE_SIZE};
+use crate::memory::mem_map::MemMap;
+use crate::memory::page::PAGE_ALLOCATOR;
+use crate::runtime_err::RuntimeError;
+
+use super::StatFS;
+use super::file::FileType;
+use super::vfs::{DirEntry, Filesystem, Inode, Metadata};
+
+// statfs中tmpfs的类型
+const TMPFS_MAGIC: u64 = 0x01021994;
+// 文件名的最大长度
+const NAME_MAX: usize = 255;
+
+// 文件系统的容量限制 文件内容按页分配
+pub struct TmpLimit {
+    max_pages: usize,
+    max_inodes: usize,
+    pages: Cell<usize>,         // 已经分配的页数
+    inodes: Cell<usize>,        // 已经创建的节点数
+    next_ino: Cell<usize>       // 下一个节点编号
+}
+
+impl TmpLimit {
+    // 默认最多使用一半的物理内存
+    fn new() -> Self {
+        let pages = PAGE_ALLOCATOR.lock().pages.len() / 2;
+        Self {
+            max_pages: pages,
+            max_inodes: pages,
+            pages: Cell::new(0),
+            inodes: Cell::new(0),
+            next_ino: Cell::new(1)
+        }
+    }
+}
+
+// 内存文件系统 文件内容保存在内存中 卸载后丢失
+pub struct TmpFileSystem {
+    root: Rc<TmpInode>,
+    limit: Rc<TmpLimit>
+}
+
+impl TmpFileSystem {
+    pub fn new() -> Rc<Self> {
+        Self::with_options("")
+    }
+
+    // 根据挂载参数创建 支持size nr_inodes和mode
+    pub fn with_options(options: &str) -> Rc<Self> {
+        let mut limit = TmpLimit::new();
+        let mut mode = 0o1777;
+        for option in options.split(',') {
+            let (key, value) = match option.split_once('=') {
+                Some(option) => option,
+                None => continue
+            };
+            match key {
+                "size" => if let Some(size) = parse_size(value, limit.max_pages * 2 * PAGE_SIZE) {
+                    limit.max_pages = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+                },
+                "nr_inodes" => if let Some(inodes) = parse_size(value, usize::MAX) {
+                    limit.max_inodes = inodes;
+                },
+                "mode" => if let Ok(value) = u32::from_str_radix(value, 8) {
+                    mode = value & 0o7777;
+                },
+                _ => { warn!("不支持的tmpfs挂载参数: {}", option); }
+            }
+        }
+        let limit = Rc::new(limit);
+        let root = TmpInode::new(&limit, FileType::Directory).expect("can't create tmpfs root");
+        root.meta.borrow_mut().mode = mode;
+        Rc::new(Self { root, limit })
+    }
+}
+
+// 解析大小 支持k m g后缀和物理内存的百分比
+fn parse_size(value: &str, total: usize) -> Option<usize> {
+    let (num, unit) = match value.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => (&value[..i], &value[i..]),
+        None => (value, "")
+    };
+    let num: usize = num.parse().ok()?;
+    match unit {
+        "" => Some(num),
+        "k" | "K" => Some(num << 10),
+        "m" | "M" => Some(num << 20),
+        "g" | "G" => Some(num << 30),
+        "%" => Some(total / 100 * num),
+        _ => None
+    }
+}
+
+impl Filesystem for TmpFileSystem {
+    fn fs_type(&self) -> &'static str {
+        "tmpfs"
+    }
+
+    fn root(&self) -> Rc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn statfs(&self) -> StatFS {
+        let limit = &self.limit;
+        StatFS {
+            f_type: TMPFS_MAGIC,
+            f_bsize: PAGE_SIZE as u64,
+            f_blocks: limit.max_pages as u64,
+            f_bfree: limit.max_pages.saturating_sub(limit.pages.get()) as u64,
+            f_bavail: limit.max_pages.saturating_sub(limit.pages.get()) as u64,
+            f_files: limit.max_inodes as u64,
+            f_ffree: limit.max_inodes.saturating_sub(limit.inodes.get()) as u64,
+            f_fsid: 0,
+            f_namelen: NAME_MAX as u64
+        }
+    }
+}
+
+// 可以修改的元数据
+struct TmpMeta {
+    mode: u32,
+    nlink: u32,
+    atime: TimeSpec,
+    mtime: TimeSpec,
+    ctime: TimeSpec
+}
+
+// 内存文件系统中的文件 目录或者符号链接 符号链接的目标保存在文件内容中
+pub struct TmpInode {
+    limit: Rc<TmpLimit>,
+    ino: usize,
+    file_type: FileType,
+    meta: RefCell<TmpMeta>,
+    size: Cell<usize>,
+    pages: RefCell<BTreeMap<usize, Rc<MemMap>>>,            // 文件内容 没有写入的页为空洞
+    children: RefCell<BTreeMap<String, Rc<TmpInode>>>       // 目录下的子节点
+}
+
+impl TmpInode {
+    pub fn new(limit: &Rc<TmpLimit>, file_type: FileType) -> Result<Rc<Self>, RuntimeError> {
+        if limit.inodes.get() >= limit.max_inodes {
+            return Err(RuntimeError::NoSpace);
+        }
+        limit.inodes.set(limit.inodes.get() + 1);
+        let ino = limit.next_ino.get();
+        limit.next_ino.set(ino + 1);
+        let now = TimeSpec::now();
+        Ok(Rc::new(Self {
+            limit: limit.clone(),
+            ino,
+            file_type,
+            meta: RefCell::new(TmpMeta {
+                mode: match file_type {
+                    FileType::Directory => 0o755,
+                    FileType::SymLink => 0o777,
+                    _ => 0o644
+                },
+                nlink: 1,
+                atime: now,
+                mtime: now,
+                ctime: now
+            }),
+            size: Cell::new(0),
+            pages: RefCell::new(BTreeMap::new()),
+            children: RefCell::new(BTreeMap::new())
+        }))
+    }
+
+    fn check_dir(&self) -> Result<(), RuntimeError> {
+        match self.file_type {
+            FileType::Directory => Ok(()),
+            _ => Err(RuntimeError::NotDir)
+        }
+    }
+
+    // 获取文件的页 不存在时分配 超过容量时返回NoSpace
+    fn get_page(&self, index: usize) -> Result<Rc<MemMap>, RuntimeError> {
+        if let Some(page) = self.pages.borrow().get(&index) {
+            return Ok(page.clone());
+        }
+        if self.limit.pages.get() >= self.limit.max_pages {
+            return Err(RuntimeError::NoSpace);
+        }
+        let page = MemMap::new_virt_file_page()?;
+        self.limit.pages.set(self.limit.pages.get() + 1);
+        self.pages.borrow_mut().insert(index, page.clone());
+        Ok(page)
+    }
+
+    // 修改内容后更新修改时间
+    fn touch(&self) {
+        let mut meta = self.meta.borrow_mut();
+        meta.mtime = TimeSpec::now();
+        meta.ctime = meta.mtime;
+    }
+
+    // 删除和添加目录项时修改链接数
+    fn add_nlink(&self, value: isize) {
+        let mut meta = self.meta.borrow_mut();
+        meta.nlink = (meta.nlink as isize + value).max(0) as u32;
+        meta.ctime = TimeSpec::now();
+    }
+}
+
+impl Drop for TmpInode {
+    fn drop(&mut self) {
+        self.limit.inodes.set(self.limit.inodes.get() - 1);
+        self.limit.pages.set(self.limit.pages.get() - self.pages.borrow().len());
+    }
+}
+
+impl Inode for TmpInode {
+    fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    fn size(&self) -> usize {
+        self.size.get()
+    }
+
+    fn read_dir(&self) -> Result<Vec<Rc<dyn DirEntry>>, RuntimeError> {
+        self.check_dir()?;
+        Ok(self.children.borrow().iter().map(|(name, inode)| {
+            let inode: Rc<dyn Inode> = inode.clone();
+            Rc::new((name.clone(), inode)) as Rc<dyn DirEntry>
+        }).collect())
+    }
+
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, RuntimeError> {
+        match self.children.borrow().get(name) {
+            Some(inode) => Ok(inode.clone()),
+            None => Err(RuntimeError::FileNotFound)
+        }
+    }
+
+    fn rename(&self, old_name: &str, new_name: &str) -> Result<(), RuntimeError> {
+        if old_name == new_name {
+            return Ok(());
+        }
+        let mut children = self.children.borrow_mut();
+        let inode = children.remove(old_name).ok_or(RuntimeError::FileNotFound)?;
+        if let Some(target) = children.insert(new_name.to_string(), inode) {
+            target.add_nlink(-1);
+        }
+        drop(children);
+        self.touch();
+        Ok(())
+    }
+
+    // 空洞部分读取为0
+    fn read_at(&self, pos: usize, buf: &mut [u8]) -> Result<usize, RuntimeError> {
+        let size = self.size.get();
+        if pos >= size {
+            return Ok(0);
+        }
+        let end = size.min(pos + buf.len());
+        let pages = self.pages.borrow();
+        let mut curr = pos;
+        while curr < end {
+            let offset = curr % PAGE_SIZE;
+            let len = (PAGE_SIZE - offset).min(end - curr);
+            let data = &mut buf[curr - pos..curr - pos + len];
+            match pages.get(&(curr / PAGE_SIZE)) {
+                Some(page) => data.copy_from_slice(&get_buf_from_phys_page(page.ppn, 1)[offset..offset + len]),
+                None => data.fill(0)
+            }
+            curr += len;
+        }
+        self.meta.borrow_mut().atime = TimeSpec::now();
+        Ok(end - pos)
+    }
+
+    // 写入时按页分配 空间不足时返回已经写入的长度
+    fn write_at(&self, pos: usize, buf: &[u8]) -> Result<usize, RuntimeError> {
+        let mut curr = pos;
+        let end = pos + buf.len();
+        while curr < end {
+            let page = match self.get_page(curr / PAGE_SIZE) {
+                Ok(page) => page,
+                Err(_) if curr > pos => break,
+                Err(err) => return Err(err)
+            };
+            let offset = curr % PAGE_SIZE;
+            let len = (PAGE_SIZE - offset).min(end - curr);
+            get_buf_from_phys_page(page.ppn, 1)[offset..offset + len]
+                .copy_from_slice(&buf[curr - pos..curr - pos + len]);
+            curr += len;
+        }
+        if curr > self.size.get() {
+            self.size.set(curr);
+        }
+        self.touch();
+        Ok(curr - pos)
+    }
+
+    // 释放超出文件大小的页 最后一页超出的部分清零
+    fn truncate(&self, size: usize) -> Result<(), RuntimeError> {
+        let mut pages = self.pages.borrow_mut();
+        let freed = pages.split_off(&((size + PAGE_SIZE - 1) / PAGE_SIZE));
+        self.limit.pages.set(self.limit.pages.get() - freed.len());
+        if size % PAGE_SIZE != 0 {
+            if let Some(page) = pages.get(&(size / PAGE_SIZE)) {
+                get_buf_from_phys_page(page.ppn, 1)[size % PAGE_SIZE..].fill(0);
+            }
+        }
+        drop(pages);
+        self.size.set(size);
+        self.touch();
+        Ok(())
+    }
+
+    fn create(&self, name: &str, file_type: FileType) -> Result<Rc<dyn Inode>, RuntimeError> {
+        self.check_dir()?;
+        if name.len() > NAME_MAX || self.children.borrow().contains_key(name) {
+            return Err(RuntimeError::NotRWFile);
+        }
+        let inode = TmpInode::new(&self.limit, file_type)?;
+        self.children.borrow_mut().insert(name.to_string(), inode.clone());
+        self.touch();
+        Ok(inode)
+    }
+
+    fn remove(&self, name: &str) -> Result<(), RuntimeError> {
+        let inode = self.children.borrow_mut().remove(name).ok_or(RuntimeError::FileNotFound)?;
+        inode.add_nlink(-1);
+        self.touch();
+        Ok(())
+    }
+
+    // 只能链接同一个tmpfs中的节点
+    fn link(&self, name: &str, inode: Rc<dyn Inode>) -> Result<(), RuntimeError> {
+        self.check_dir()?;
+        let inode = inode.downcast::<TmpInode>().map_err(|_| RuntimeError::NotRWFile)?;
+        if !Rc::ptr_eq(&inode.limit, &self.limit) || self.children.borrow().contains_key(name) {
+            return Err(RuntimeError::NotRWFile);
+        }
+        inode.add_nlink(1);
+        self.children.borrow_mut().insert(name.to_string(), inode);
+        self.touch();
+        Ok(())
+    }
+
+    fn page(&self, index: usize) -> Option<Rc<MemMap>> {
+        match self.file_type {
+            FileType::File => self.get_page(index).ok(),
+            _ => None
+        }
+    }
+
+    // 目录的链接数为2加上子目录的数量
+    fn metadata(&self) -> Metadata {
+        let meta = self.meta.borrow();
+        let nlink = match self.file_type {
+            FileType::Directory => 2 + self.children.borrow().values()
+                .filter(|x| x.file_type == FileType::Directory).count() as u32,
+            _ => meta.nlink
+        };
+        Metadata {
+            ino: self.ino,
+            mode: meta.mode,
+            nlink,
+            uid: 0,
+            gid: 0,
+            size: self.size.get(),
+            blocks: self.pages.borrow().len() * PAGE_SIZE / 512,
+            atime: meta.atime,
+            mtime: meta.mtime,
+            ctime: meta.ctime
+        }
+    }
+
+    fn set_mode(&self, mode: u32) -> Result<(), RuntimeError> {
+        let mut meta = self.meta.borrow_mut();
+        meta.mode = mode & 0o7777;
+        meta.ctime = TimeSpec::now();
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Option<TimeSpec>, mtime: Option<TimeSpec>) -> Result<(), RuntimeError> {
+        let mut meta = self.meta.borrow_mut();
+        if let Some(atime) = atime {
+            meta.atime = atime;
+        }
+        if let Some(mtime) = mtime {
+            meta.mtime = mtime;
+        }
+        meta.ctime = TimeSpec::now();
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use core::any::{Any, TypeId};
+
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::interrupt::timer::TimeSpec;
+use crate::memory::mem_map::MemMap;
+use crate::runtime_err::RuntimeError;
+
+use super::StatFS;
+use super::file::{FileOP, FileType};
+
+// 文件系统驱动 挂载时创建实例
+pub trait Filesystem {
+    // 文件系统类型 显示在/proc/mounts中
+    fn fs_type(&self) -> &'static str;
+    // 文件系统的根目录
+    fn root(&self) -> Rc<dyn Inode>;
+    // 文件系统的使用情况 用于statfs
+    fn statfs(&self) -> StatFS {
+        StatFS {
+            f_type: 32,
+            f_bsize: 512,
+            f_blocks: 80,
+            f_bfree: 40,
+            f_bavail: 0,
+            f_files: 32,
+            f_ffree: 0,
+            f_fsid: 32,
+            f_namelen: 20
+        }
+    }
+}
+
+// 文件的元数据 用于stat
+#[derive(Clone, Copy)]
+pub struct Metadata {
+    pub ino: usize,             // 节点编号 为0时使用文件树中的编号
+    pub mode: u32,              // 权限位
+    pub nlink: u32,             // 硬链接数量
+    pub uid: u32,
+    pub gid: u32,
+    pub size: usize,
+    pub blocks: usize,          // 占用的512字节块数量
+    pub atime: TimeSpec,        // 最后访问时间
+    pub mtime: TimeSpec,        // 最后修改时间
+    pub ctime: TimeSpec         // 最后改变状态时间
+}
+
+impl Metadata {
+    // 不保存元数据的文件系统使用的默认值
+    pub fn new(file_type: FileType, size: usize) -> Self {
+        let zero = TimeSpec { tv_sec: 0, tv_nsec: 0 };
+        Self {
+            ino: 0,
+            mode: match file_type {
+                FileType::Directory => 0o755,
+                _ => 0o644
+            },
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            size,
+            blocks: (size + 511) / 512,
+            atime: zero,
+            mtime: zero,
+            ctime: zero
+        }
+    }
+}
+
+// 文件系统中的文件或者目录
+pub trait Inode: Any {
+    // 文件类型
+    fn file_type(&self) -> FileType;
+    // 文件大小
+    fn size(&self) -> usize {
+        0
+    }
+    // 读取目录下的所有目录项
+    fn read_dir(&self) -> Result<Vec<Rc<dyn DirEntry>>, RuntimeError> {
+        Err(RuntimeError::NotDir)
+    }
+    // 在目录下查找文件 默认遍历整个目录
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, RuntimeError> {
+        self.read_dir()?.into_iter().find(|x| x.name() == name)
+            .map(|x| x.inode()).ok_or(RuntimeError::FileNotFound)
+    }
+    // 从pos开始读取文件内容 返回读取的长度
+    fn read_at(&self, _pos: usize, _buf: &mut [u8]) -> Result<usize, RuntimeError> {
+        Err(RuntimeError::NotRWFile)
+    }
+    // 从pos开始写入文件内容 返回写入的长度
+    fn write_at(&self, _pos: usize, _buf: &[u8]) -> Result<usize, RuntimeError> {
+        Err(RuntimeError::NotRWFile)
+    }
+    // 修改文件大小 扩展的部分填充0
+    fn truncate(&self, _size: usize) -> Result<(), RuntimeError> {
+        Err(RuntimeError::NotRWFile)
+    }
+    // 在目录下创建文件或者目录
+    fn create(&self, _name: &str, _file_type: FileType) -> Result<Rc<dyn Inode>, RuntimeError> {
+        Err(RuntimeError::NotRWFile)
+    }
+    // 删除目录下的目录项
+    fn remove(&self, _name: &str) -> Result<(), RuntimeError> {
+        Err(RuntimeError::NotRWFile)
+    }
+    // 重命名目录下的目录项 new_name已经存在时覆盖
+    fn rename(&self, _old_name: &str, _new_name: &str) -> Result<(), RuntimeError> {
+        Err(RuntimeError::NotRWFile)
+    }
+    // 在目录下创建inode的硬链接 inode需要在同一个文件系统中
+    fn link(&self, _name: &str, _inode: Rc<dyn Inode>) -> Result<(), RuntimeError> {
+        Err(RuntimeError::NotRWFile)
+    }
+    // 由文件系统驱动提供打开的文件 返回None时使用通用的File
+    fn open(&self) -> Option<Rc<dyn FileOP>> {
+        None
+    }
+    // 内存文件系统中保存文件内容的页 读写和共享映射直接使用 不经过页缓存
+    fn page(&self, _index: usize) -> Option<Rc<MemMap>> {
+        None
+    }
+    // 文件的元数据
+    fn metadata(&self) -> Metadata {
+        Metadata::new(self.file_type(), self.size())
+    }
+    // 修改权限位
+    fn set_mode(&self, _mode: u32) -> Result<(), RuntimeError> {
+        Err(RuntimeError::NotRWFile)
+    }
+    // 修改访问时间和修改时间 为None时不修改
+    fn set_times(&self, _atime: Option<TimeSpec>, _mtime: Option<TimeSpec>) -> Result<(), RuntimeError> {
+        Err(RuntimeError::NotRWFile)
+    }
+}
+
+impl dyn Inode {
+    pub fn downcast<T: 'static>(self: Rc<Self>) -> Result<Rc<T>, Rc<Self>> {
+        if TypeId::of::<T>() == self.as_ref().type_id() {
+            unsafe {
+                Ok(Rc::from_raw(Rc::into_raw(self) as _))
+            }
+        } else {
+            Err(self)
+        }
+    }
+}
+
+// 目录项 包含文件名和对应的节点
+pub trait DirEntry {
+    fn name(&self) -> String;
+    fn inode(&self) -> Rc<dyn Inode>;
+}
+
+// 内存中的文件系统直接使用文件名和节点作为目录项
+impl DirEntry for (String, Rc<dyn Inode>) {
+    fn name(&self) -> String {
+        self.0.clone()
+    }
+
+    fn inode(&self) -> Rc<dyn Inode> {
+        self.1.clone()
+    }
+}
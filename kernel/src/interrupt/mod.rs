@@ -7,7 +7,8 @@ use riscv::register::scause::Exception;
 use riscv::register::scause::Interrupt;
 use riscv::register::scause::Scause;
 
-pub use timer::TICKS;
+use crate::task::task_scheduler::get_current_task;
+use crate::memory::vma::PageFaultType;
 
 
 #[repr(C)]
@@ -61,7 +62,15 @@ fn fault(context: &mut Context, _scause: Scause, _stval: usize) {
 }
 
 // 处理缺页异常
-fn handle_page_fault(_context: &mut Context, _stval: usize) {
+fn handle_page_fault(_context: &mut Context, _stval: usize, fault_type: PageFaultType) {
+    // 内核访问用户的写时复制页面或者尚未分配的页面
+    if let Some(task) = get_current_task() {
+        // 系统调用过程中任务和进程已经被借用 这里只读取页表和内存区域
+        let process = unsafe { &*(*task.inner.as_ptr()).process.as_ptr() };
+        if let Ok(true) = process.pmm.handle_page_fault(&process.vmas, _stval, fault_type) {
+            return;
+        }
+    }
     warn!("缺页中断触发 缺页地址: {:#x} 触发地址:{:#x} 已同步映射", _stval, _context.sepc);
     panic!("end");
 }
@@ -69,19 +78,20 @@ fn handle_page_fault(_context: &mut Context, _stval: usize) {
 // 内核中断回调
 #[no_mangle]
 fn kernel_callback(context: &mut Context, scause: Scause, stval: usize) -> usize {
-    warn!("内核态中断发生: {:#x}  stval {:#x}  sepc: {:#x}", scause.bits(), stval,  context.sepc);
+    // 空闲时等待的时钟中断不输出
+    if scause.cause() != Trap::Interrupt(Interrupt::SupervisorTimer) {
+        warn!("内核态中断发生: {:#x}  stval {:#x}  sepc: {:#x}", scause.bits(), stval,  context.sepc);
+    }
     match scause.cause(){
         // 中断异常
         Trap::Exception(Exception::Breakpoint) => breakpoint(context),
-        // 时钟中断 eg: 不再内核处理时间中断 just in user
-        Trap::Interrupt(Interrupt::SupervisorTimer) => {},
+        // 时钟中断 只在调度器空闲等待定时器时产生 清除中断后回到调度器处理到期的定时器
+        Trap::Interrupt(Interrupt::SupervisorTimer) => timer::timer_handler(),
         // 缺页异常
-        Trap::Exception(Exception::StorePageFault) => handle_page_fault(context, stval),
+        Trap::Exception(Exception::StorePageFault) => handle_page_fault(context, stval, PageFaultType::Store),
         // 加载页面错误
-        Trap::Exception(Exception::LoadPageFault) => {
-            panic!("加载权限异常 地址:{:#x}", stval)
-        },
-        Trap::Exception(Exception::InstructionPageFault) => handle_page_fault(context, stval),
+        Trap::Exception(Exception::LoadPageFault) => handle_page_fault(context, stval, PageFaultType::Load),
+        Trap::Exception(Exception::InstructionPageFault) => handle_page_fault(context, stval, PageFaultType::Instruction),
         // 页面未对齐异常
         Trap::Exception(Exception::StoreMisaligned) => {
             info!("页面未对齐");
@@ -1,3 +1,5 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
 use crate::sync::mutex::Mutex;
 use crate::sbi::set_timer;
 use riscv::register::{sie, time};
@@ -9,13 +11,22 @@ const CLOCK_FREQ: usize = 12500000;
 // const CLOCK_FREQ: usize = 4030000000 / 62;
 const CLOCK_FREQ: usize = 403000000 / 62;
 
-const CHANGE_TASK_TICKS: usize = 10;
+// 时钟中断频率 可以通过 hz_250/hz_1000 特性修改
+#[cfg(feature = "hz_1000")]
+pub const HZ: usize = 1000;
+#[cfg(all(feature = "hz_250", not(feature = "hz_1000")))]
+pub const HZ: usize = 250;
+#[cfg(not(any(feature = "hz_250", feature = "hz_1000")))]
+pub const HZ: usize = 100;
 
-// const INTERVAL: usize = CLOCK_FREQ / 100;
-// const INTERVAL: usize = CLOCK_FREQ / 25;
-const INTERVAL: usize = CLOCK_FREQ / 1;
+// 每个时间片的长度 单位us
+pub const TICK_US: usize = USEC_PER_SEC / HZ;
+
+// times 系统调用返回的时钟频率 与用户态的 sysconf(_SC_CLK_TCK) 保持一致
+const USER_HZ: usize = 100;
 
 const MSEC_PER_SEC: usize = 1000;
+const USEC_PER_SEC: usize = 1_000_000;
 const NSEC_PER_SEC: usize = 1_000_000_000;
 
 // tms_utime记录的是进程执行用户代码的时间.
@@ -52,6 +63,16 @@ impl TimeSpec {
         self.tv_nsec = (ms % 1000) * 1000;
     }
 
+    // 转换为us
+    pub fn to_us(&self) -> usize {
+        self.tv_sec * USEC_PER_SEC + self.tv_nsec / 1000
+    }
+
+    // 从us转换
+    pub fn from_us(us: usize) -> Self {
+        Self { tv_sec: us / USEC_PER_SEC, tv_nsec: us % USEC_PER_SEC * 1000 }
+    }
+
     pub fn now() -> Self {
         // let ms = get_time_ms();
         // Self{
@@ -66,6 +87,41 @@ impl TimeSpec {
     }
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct TimeVal {
+    pub tv_sec: usize,      /* 秒 */
+    pub tv_usec: usize      /* 微秒, 范围在0~999999 */
+}
+
+impl TimeVal {
+    // 转换为us
+    pub fn to_us(&self) -> usize {
+        self.tv_sec * USEC_PER_SEC + self.tv_usec
+    }
+
+    // 从us转换
+    pub fn from_us(us: usize) -> Self {
+        Self { tv_sec: us / USEC_PER_SEC, tv_usec: us % USEC_PER_SEC }
+    }
+}
+
+// setitimer/getitimer 使用的结构
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ITimerVal {
+    pub it_interval: TimeVal,   // 间隔时间
+    pub it_value: TimeVal       // 剩余时间
+}
+
+// timer_settime/timer_gettime 使用的结构
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ITimerSpec {
+    pub it_interval: TimeSpec,  // 间隔时间
+    pub it_value: TimeSpec      // 剩余时间或到期时间
+}
+
 // 获取毫秒结构
 pub fn get_time_sec() -> usize {
     time::read() / CLOCK_FREQ
@@ -76,79 +132,144 @@ pub fn get_time_ms() -> usize {
 }
 
 pub fn get_time_us() -> usize {
-    time::read() / (CLOCK_FREQ / MSEC_PER_SEC / MSEC_PER_SEC)
+    let tick = time::read();
+    tick / CLOCK_FREQ * USEC_PER_SEC + tick % CLOCK_FREQ * USEC_PER_SEC / CLOCK_FREQ
 }
 
-// 下一个任务ticks
-pub struct NextTaskTicks(usize);
+// 将us转换为时钟周期
+fn us_to_tick(us: usize) -> usize {
+    us / USEC_PER_SEC * CLOCK_FREQ + us % USEC_PER_SEC * CLOCK_FREQ / USEC_PER_SEC
+}
 
-impl NextTaskTicks {
-    // 创建任务TICKS结构
-    pub fn new() -> Self {
-        NextTaskTicks(CHANGE_TASK_TICKS)
+// 定时器事件
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimerEvent {
+    Wake(usize, usize),         // 唤醒等待超时的任务 参数为(pid, tid)
+    ITimerReal(usize),          // 进程的ITIMER_REAL到期 参数为pid
+    PosixTimer(usize, usize)    // 进程的POSIX定时器到期 参数为(pid, 定时器编号)
+}
+
+// 定时器编号 (到期时间us, 序号)
+pub type TimerId = (usize, usize);
+
+// 定时器列表 按照到期时间排序
+pub struct TimerList {
+    timers: BTreeMap<TimerId, TimerEvent>,
+    next_id: usize
+}
+
+impl TimerList {
+    pub const fn new() -> Self {
+        Self {
+            timers: BTreeMap::new(),
+            next_id: 0
+        }
+    }
+
+    // 添加定时器
+    pub fn add(&mut self, deadline: usize, event: TimerEvent) -> TimerId {
+        let id = (deadline, self.next_id);
+        self.next_id += 1;
+        self.timers.insert(id, event);
+        id
+    }
+
+    // 取消定时器
+    pub fn cancel(&mut self, id: TimerId) {
+        self.timers.remove(&id);
     }
 
-    // 刷新TICKS
-    pub fn refresh(&mut self) {
-        self.0 = self.0 + CHANGE_TASK_TICKS;
+    // 取出已经到期的定时器
+    pub fn take_expired(&mut self, now: usize) -> Vec<TimerEvent> {
+        let rest = self.timers.split_off(&(now + 1, 0));
+        let expired = core::mem::replace(&mut self.timers, rest);
+        expired.into_values().collect()
     }
 
-    // 判断是否需要更换任务
-    pub fn need_change(&self, ticks: usize) -> bool {
-        ticks > self.0
+    // 最近的到期时间
+    pub fn next_deadline(&self) -> Option<usize> {
+        self.timers.keys().next().map(|x| x.0)
     }
 }
 
-lazy_static! {
-    pub static ref NEXT_TICKS: Mutex<NextTaskTicks> = Mutex::new(NextTaskTicks::new());
+pub static TIMER_LIST: Mutex<TimerList> = Mutex::new(TimerList::new());
+
+// 当前时间片结束的时间 单位us
+static mut SLICE_END: usize = 0;
+// 已经设置的下一次时钟中断时间 单位us
+static mut NEXT_EVENT: usize = usize::MAX;
+
+// 添加定时器 deadline为到期时间 单位us
+pub fn add_timer(deadline: usize, event: TimerEvent) -> TimerId {
+    TIMER_LIST.lock().add(deadline, event)
 }
 
-// 时间信息
-pub static mut TICKS: usize = 0;
-pub static mut LAST_TICKS: usize = 0;
+// 取消定时器
+pub fn cancel_timer(id: TimerId) {
+    TIMER_LIST.lock().cancel(id)
+}
+
+// 取出已经到期的定时器事件
+pub fn take_expired_timers() -> Vec<TimerEvent> {
+    TIMER_LIST.lock().take_expired(get_time_us())
+}
+
+// 最近的定时器到期时间
+pub fn next_timer_deadline() -> Option<usize> {
+    TIMER_LIST.lock().next_deadline()
+}
 
 /// 时钟中断处理器
 pub fn timer_handler() {
-    set_next_timeout();
-    unsafe {
-        TICKS=TICKS+1;
-    }
-    // 判断是否需要更换任务
-    if NEXT_TICKS.force_get().need_change(unsafe { TICKS }) {
-        // suspend_and_run_next();
-    }
+    // 单次触发 需要重新设置
+    unsafe { NEXT_EVENT = usize::MAX; }
+    set_timer(usize::MAX);
 }
 
-// 设置下一次时钟中断触发时间
-fn set_next_timeout() {
+// 设置下一次时钟中断 need_tick表示是否有其他任务需要在时间片结束时抢占
+// 只有一个任务时不产生时间片中断 只在定时器到期时触发
+pub fn set_next_event(need_tick: bool) {
+    let mut deadline = next_timer_deadline().unwrap_or(usize::MAX);
+    if need_tick {
+        deadline = deadline.min(unsafe { SLICE_END });
+    }
+    unsafe {
+        if deadline == NEXT_EVENT {
+            return;
+        }
+        NEXT_EVENT = deadline;
+    }
     // 调用sbi设置定时器
-    set_timer(time::read() + INTERVAL);
+    match deadline {
+        usize::MAX => set_timer(usize::MAX),
+        _ => set_timer(us_to_tick(deadline))
+    }
 }
 
 // 初始化定时器
 pub fn init() {
-    info!("初始化定时器");
+    info!("初始化定时器 HZ: {}", HZ);
     unsafe {
         // 开启时钟中断
         sie::set_stimer();
         // 允许中断产生
         // sstatus::set_sie();
     }
-    // 设置下一次中断产生时间
-    set_next_timeout();
+    task_time_refresh();
 }
 
+// 开始新的时间片
 pub fn task_time_refresh() {
-    NEXT_TICKS.force_get().refresh();
-    set_next_timeout();
+    unsafe { SLICE_END = get_time_us() + TICK_US; }
 }
 
-#[inline]
-pub fn get_ticks() -> usize {
-    unsafe {TICKS}
+// 判断当前时间片是否已经用完
+pub fn slice_expired() -> bool {
+    get_time_us() >= unsafe { SLICE_END }
 }
 
+// 获取时钟滴答数 单位为 1/USER_HZ 秒
 #[inline]
-pub fn set_last_ticks() {
-    unsafe {LAST_TICKS = TICKS};
-}
\ No newline at end of file
+pub fn get_ticks() -> usize {
+    get_time_us() / (USEC_PER_SEC / USER_HZ)
+}
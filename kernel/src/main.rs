@@ -32,12 +32,10 @@ extern crate alloc;
 use core::arch::global_asm;
 
 
-use alloc::{rc::Rc, string::ToString};
+use alloc::rc::Rc;
 use riscv::register::sstatus;
 use crate::fs::filetree::INode;
-use crate::fs::filetree::DiskFileEnum;
-use crate::fs::file::FileType;
-use crate::fs::cache::cache_file;
+use crate::fs::page_cache::prefetch;
 use crate::memory::page::get_free_page_num;
 mod virtio_impl;
 
@@ -122,15 +120,12 @@ pub extern "C" fn rust_main(hart_id: usize, device_tree_p_addr: usize) -> ! {
     // // let lmbench_all = INode::get(None, "busybox_cmd.txt").expect("can't find busybox");
     // lmbench_all.linkat("var/tmp/XXX");
 
-    INode::root().add(INode::new("proc".to_string(), 
-        DiskFileEnum::None, FileType::Directory, None));
-
     #[cfg(not(feature = "board_k210"))]
     {
-        // 非k210缓冲文件
-        cache_file("busybox");
-        cache_file("lua");
-        // cache_file("lmbench_all");
+        // 非k210预先读取到页缓存
+        prefetch("busybox");
+        prefetch("lua");
+        // prefetch("lmbench_all");
     }
 
     // 初始化多任务
@@ -139,6 +134,9 @@ pub extern "C" fn rust_main(hart_id: usize, device_tree_p_addr: usize) -> ! {
     // 输出剩余页表
     debug!("剩余页表: {}", get_free_page_num());
 
+    // 同步文件系统
+    fs::sync();
+
     // 调用rust api关机
     panic!("正常关机")
 }
@@ -151,24 +149,16 @@ extern "C" fn support_hart_resume(hart_id: usize, _param: usize) {
 }
 
 
-// 打印目录树
+// 打印根目录 子目录在访问时才会读取
 pub fn print_file_tree(node: Rc<INode>) {
     info!("{}", node.get_pwd());
-    print_file_tree_back(node, 0);
-}
-
-// 打印目录树 - 递归
-pub fn print_file_tree_back(node: Rc<INode>, space: usize) {
     let iter = node.clone_children();
     let mut iter = iter.iter().peekable();
     while let Some(sub_node) = iter.next() {
         if iter.peek().is_none() {
-            info!("{:>2$}└──{}", "", sub_node.get_filename(), space);
+            info!("└──{}", sub_node.get_filename());
         } else {
-            info!("{:>2$}├──{}", "", sub_node.get_filename(), space);
-        }
-        if sub_node.is_dir() {
-            print_file_tree_back(sub_node.clone(), space + 3);
+            info!("├──{}", sub_node.get_filename());
         }
     }
-}
\ No newline at end of file
+}
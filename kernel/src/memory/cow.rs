@@ -0,0 +1,132 @@
+use core::arch::asm;
+use alloc::collections::BTreeMap;
+
+use crate::sync::mutex::Mutex;
+use crate::runtime_err::RuntimeError;
+
+use super::addr::PhysAddr;
+use super::addr::VirtAddr;
+use super::addr::VirtPageNum;
+use super::addr::get_buf_from_phys_page;
+use super::mem_map::MemMap;
+use super::mem_set::MemSet;
+use super::page_table::PTEFlags;
+use super::page_table::PageMappingManager;
+use super::vma::VmaList;
+
+lazy_static! {
+    // 写时复制页面的引用计数 key为物理页号 value为以只读方式共享该页的页表项数量
+    pub static ref COW_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+}
+
+// 刷新快表
+pub fn flush_tlb() {
+    unsafe { asm!("sfence.vma") }
+}
+
+// 减少写时复制页面的引用计数
+pub fn release_cow_page(ppn: usize) {
+    let mut cow_pages = COW_PAGES.lock();
+    if let Some(count) = cow_pages.get_mut(&ppn) {
+        *count -= 1;
+        if *count == 0 {
+            cow_pages.remove(&ppn);
+        }
+    }
+}
+
+// 判断是否为写时复制页面
+pub fn is_cow_page(ppn: usize) -> bool {
+    COW_PAGES.lock().contains_key(&ppn)
+}
+
+impl PageMappingManager {
+    // 将用户页面共享到目标页表 可写页面双方都改为只读 写入时再进行复制
+    pub fn share_cow(&self, target: &PageMappingManager, vmas: &VmaList) -> Result<(), RuntimeError> {
+        let mut cow_pages = COW_PAGES.lock();
+        for (vpn, entry) in self.pte.get_user_entries() {
+            let ppn = entry.ppn();
+            let flags = entry.flags();
+            let shared = vmas.find(VirtAddr::from(vpn).0).map_or(false, |x| x.shared);
+            if shared {
+                // 共享映射的页面直接映射
+                target.add_mapping(ppn, vpn, flags)?;
+            } else if flags.contains(PTEFlags::W) || !flags.contains(PTEFlags::V) {
+                // PROT_NONE的页面不知道之后的权限 同样作为写时复制页面
+                // 第一次共享 当前页表和目标页表都计数
+                let cow_flags = flags - PTEFlags::W;
+                self.add_mapping(ppn, vpn, cow_flags)?;
+                target.add_mapping(ppn, vpn, cow_flags)?;
+                match cow_pages.get_mut(&ppn.0) {
+                    Some(count) => *count += 1,
+                    None => { cow_pages.insert(ppn.0, 2); }
+                }
+            } else {
+                // 已经是写时复制的页面只增加目标页表的计数
+                target.add_mapping(ppn, vpn, flags)?;
+                if let Some(count) = cow_pages.get_mut(&ppn.0) {
+                    *count += 1;
+                }
+            }
+        }
+        drop(cow_pages);
+
+        // 写时复制产生的页面由页表管理器持有 目标也需要持有防止提前释放
+        let user_pages = self.mem_set.borrow().0.iter()
+            .filter(|x| x.flags.contains(PTEFlags::U)).cloned().collect();
+        target.add_mem_set(&mut MemSet(user_pages));
+        flush_tlb();
+        Ok(())
+    }
+
+    // 处理写时复制缺页 返回是否为写时复制页面
+    pub fn handle_cow_fault(&self, addr: VirtAddr) -> Result<bool, RuntimeError> {
+        let entry = match self.get_entry(addr) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(false)
+        };
+        let flags = entry.flags();
+        if flags.contains(PTEFlags::W) || !flags.contains(PTEFlags::U) {
+            return Ok(false);
+        }
+        let ppn = entry.ppn();
+        let vpn = VirtPageNum::from(addr);
+        let mut cow_pages = COW_PAGES.lock();
+        let count = match cow_pages.get_mut(&ppn.0) {
+            Some(count) => count,
+            None => return Ok(false)
+        };
+
+        if *count > 1 {
+            // 仍有其他页表共享 复制一份新的页面
+            *count -= 1;
+            drop(cow_pages);
+            let mem_map = MemMap::new(vpn, 1, flags | PTEFlags::W)?;
+            get_buf_from_phys_page(mem_map.ppn, 1).copy_from_slice(get_buf_from_phys_page(ppn, 1));
+            self.add_mapping(mem_map.ppn, vpn, mem_map.flags)?;
+            self.add_mem_set(&mut MemSet(vec![mem_map]));
+            // 共享的页面由其他页表的页面集合持有 当前页表不再使用 最后一个持有者释放时回收
+            self.mem_set.borrow_mut().remove_pages(&[ppn]);
+        } else {
+            // 只剩当前页表使用 直接恢复写权限
+            cow_pages.remove(&ppn.0);
+            drop(cow_pages);
+            self.add_mapping(ppn, vpn, flags | PTEFlags::W)?;
+        }
+        flush_tlb();
+        Ok(true)
+    }
+
+    // 获取可以写入的物理地址 如果是写时复制页面则先进行复制
+    pub fn get_writable_phys_addr(&self, addr: VirtAddr) -> Result<PhysAddr, RuntimeError> {
+        self.handle_cow_fault(addr)?;
+        self.get_phys_addr(addr)
+    }
+
+    // 释放页表中写时复制页面的引用计数
+    pub fn release_cow(&self) {
+        for (_, entry) in self.pte.get_user_entries() {
+            release_cow_page(entry.ppn().0);
+        }
+    }
+}
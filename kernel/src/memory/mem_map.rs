@@ -36,6 +36,27 @@ bitflags! {
     }
 }
 
+bitflags! {
+    // mmap PROT
+    pub struct MapProt: u32 {
+        const PROT_NONE  = 0;
+        const PROT_READ  = 1;
+        const PROT_WRITE = 2;
+        const PROT_EXEC  = 4;
+    }
+}
+
+impl MapProt {
+    // 转换为页表标志 可写的页面必须可读
+    pub fn to_pte_flags(&self) -> PTEFlags {
+        let mut flags = PTEFlags::NONE;
+        if self.contains(MapProt::PROT_READ) { flags |= PTEFlags::R; }
+        if self.contains(MapProt::PROT_WRITE) { flags |= PTEFlags::R | PTEFlags::W; }
+        if self.contains(MapProt::PROT_EXEC) { flags |= PTEFlags::X; }
+        flags
+    }
+}
+
 #[derive(Clone)]
 pub struct MemMap {
     pub ppn: PhysPageNum,
@@ -67,7 +88,7 @@ impl MemMap {
         }))
     }
 
-    // 申请开始页表和页表数量 申请内存
+    // 申请一页内存 保存内存文件的内容
     pub fn new_virt_file_page() -> Result<Rc<Self>, RuntimeError> {
         let phys_num_start = alloc()?;
         Ok(Rc::new(Self {
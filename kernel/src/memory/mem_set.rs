@@ -2,7 +2,10 @@ use alloc::{vec::Vec, rc::Rc};
 
 use crate::runtime_err::RuntimeError;
 
+use super::addr::PhysPageNum;
+use super::addr::VirtPageNum;
 use super::mem_map::MemMap;
+use super::page_table::PTEFlags;
 
 #[derive(Clone)]
 pub struct MemSet(pub Vec<Rc<MemMap>>);
@@ -41,6 +44,45 @@ impl MemSet {
         (end + 1) << 12
     }
 
+    // 移除完全处于范围内的用户页面 释放对应的内存
+    pub fn remove_range(&mut self, start: VirtPageNum, end: VirtPageNum) {
+        self.0.retain(|x| !(x.flags.contains(PTEFlags::U) && x.vpn >= start && x.vpn.0 + x.page_num <= end.0));
+    }
+
+    // 移除对应物理页的单页用户页面 每个物理页只移除一次
+    pub fn remove_pages(&mut self, ppns: &[PhysPageNum]) {
+        for ppn in ppns {
+            if let Some(index) = self.0.iter().position(|x| x.flags.contains(PTEFlags::U) && x.page_num == 1 && x.ppn == *ppn) {
+                self.0.remove(index);
+            }
+        }
+    }
+
+    // 取出所有用户页面 多页的页面拆分为单页 写时复制后每一页可以单独释放
+    pub fn take_user_pages(&mut self) -> MemSet {
+        let mut pages = vec![];
+        let mut rest = vec![];
+        for map in self.0.drain(..) {
+            if !map.flags.contains(PTEFlags::U) {
+                rest.push(map);
+                continue;
+            }
+            match Rc::try_unwrap(map) {
+                Ok(map) => {
+                    for i in 0..map.page_num {
+                        pages.push(MemMap::exists_page(PhysPageNum(map.ppn.0 + i), VirtPageNum(map.vpn.0 + i), 1, map.flags));
+                    }
+                    // 物理页已经由拆分后的页面持有 原来的页面不再释放
+                    core::mem::forget(map);
+                }
+                // 仍然被其他集合持有的页面不能拆分
+                Err(map) => rest.push(map)
+            }
+        }
+        self.0 = rest;
+        MemSet(pages)
+    }
+
     // 释放占用的资源
     pub fn release(&mut self) {
         self.0.clear();
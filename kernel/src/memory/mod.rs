@@ -5,7 +5,9 @@ pub mod page;
 pub mod addr;
 pub mod page_table;
 pub mod mem_map;
-pub mod mem_set;
+pub mod mem_set;
+pub mod cow;
+pub mod vma;
 
 pub const KERNEL_STACK_SIZE: usize = 4096;
 
@@ -3,6 +3,7 @@ use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
 use crate::sync::mutex::Mutex;
+use crate::fs::page_cache::reclaim;
 use crate::memory::addr::PAGE_SIZE;
 use crate::memory::addr::PhysAddr;
 use crate::runtime_err::RuntimeError;
@@ -113,12 +114,21 @@ pub fn init_pages(page: PhysPageNum, num: usize) {
     unsafe { from_raw_parts_mut(PhysAddr::from(page).0 as *mut usize, USIZE_PER_PAGES * num) }.fill(0);
 }
 
+// 内存不足时释放文件页缓存后重试
 pub fn alloc() -> Result<PhysPageNum, RuntimeError> {
-    PAGE_ALLOCATOR.lock().alloc()
+    let page = PAGE_ALLOCATOR.lock().alloc();
+    match page {
+        Err(_) if reclaim() => PAGE_ALLOCATOR.lock().alloc(),
+        page => page
+    }
 }
 
 pub fn alloc_more(pages: usize) -> Result<PhysPageNum, RuntimeError> {
-    PAGE_ALLOCATOR.lock().alloc_more(pages)
+    let page = PAGE_ALLOCATOR.lock().alloc_more(pages);
+    match page {
+        Err(_) if reclaim() => PAGE_ALLOCATOR.lock().alloc_more(pages),
+        page => page
+    }
 }
 
 pub fn dealloc_more(page: PhysPageNum, pages: usize) {
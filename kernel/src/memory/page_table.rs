@@ -1,6 +1,7 @@
 use core::arch::asm;
 use core::slice::from_raw_parts_mut;
 use core::slice;
+use alloc::vec::Vec;
 use _core::cell::RefCell;
 use bitflags::*;
 
@@ -150,41 +151,20 @@ impl PageMapping {
 
     // 删除mapping
     pub fn remove_mapping(&self, virt_addr: VirtAddr) {
-        // 如果没有pte则申请pte
         if usize::from(self.0) == 0 {
             return;
         }
 
-        // 得到 列表中的项
-        let l2_pte_ptr = unsafe {
-            PageTableEntry::get_mut_ptr_from_phys(PhysAddr::from(self.0)).add(virt_addr.l2())
-        };
-        let mut l2_pte = unsafe { l2_pte_ptr.read() };
-
-        // 判断 是否是页表项 如果是则申请一个页防止其内容
+        let l2_pte = PageTableEntry::get_vec_from_phys(self.0.into())[virt_addr.l2()];
         if !l2_pte.is_valid_pd() {
-            // 创建一个页表放置二级页目录 并写入一级页目录的项中
-            l2_pte = PageTableEntry::new(PhysPageNum::from(PhysAddr::from(Self::alloc_pte(1).unwrap())), PTEFlags::V);
-            // 写入列表
-            unsafe {l2_pte_ptr.write(l2_pte)};
-        }
-
-        let l1_pte_ptr = unsafe {
-            PageTableEntry::get_mut_ptr_from_phys(PhysAddr::from(l2_pte.ppn())).add(virt_addr.l1())
-        };
-        let mut l1_pte = unsafe {l1_pte_ptr.read()};
-
-        // 判断 是否有指向下一级的页表
-        if !l1_pte.is_valid_pd(){
-            l1_pte = PageTableEntry::new(PhysPageNum::from(PhysAddr::from(Self::alloc_pte(0).unwrap())), PTEFlags::V);
-            unsafe{l1_pte_ptr.write(l1_pte)};
+            return;
         }
-        
-        // 写入映射项
-        unsafe {
-            PageTableEntry::get_mut_ptr_from_phys(PhysAddr::from(l1_pte.ppn()))
-                .add(virt_addr.l0()).write(PageTableEntry::new(PhysPageNum::from(PhysPageNum::from(virt_addr.l0() << 18)), PTEFlags::VRWX));
+        let l1_pte = PageTableEntry::get_vec_from_phys(l2_pte.ppn().into())[virt_addr.l1()];
+        if !l1_pte.is_valid_pd() {
+            return;
         }
+        // 清空映射项
+        PageTableEntry::get_vec_from_phys(l1_pte.ppn().into())[virt_addr.l0()] = PageTableEntry::empty();
     }
 
     // 获取物理地址
@@ -234,6 +214,15 @@ impl PageMapping {
     }
 
     pub fn get_entry(&self, virt_addr: VirtAddr) -> Result<PageTableEntry, RuntimeError> {
+        let l0_pte = self.get_leaf(virt_addr)?;
+        if !l0_pte.flags().contains(PTEFlags::V) {
+            return Err(RuntimeError::NoMatchedAddr);
+        }
+        Ok(l0_pte)
+    }
+
+    // 获取最后一级页表项 不判断是否合法 PROT_NONE的页面清除了V但仍然保留物理页
+    pub fn get_leaf(&self, virt_addr: VirtAddr) -> Result<PageTableEntry, RuntimeError> {
         // 如果没有pte则申请pte
         if usize::from(self.0) == 0 {
             return Err(RuntimeError::NoMatchedAddr);
@@ -265,7 +254,7 @@ impl PageMapping {
             PageTableEntry::get_mut_ptr_from_phys(PhysAddr::from(l1_pte.ppn())).add(virt_addr.l0())
         };
         let l0_pte = unsafe { l0_pte_ptr.read() };
-        if !l0_pte.flags().contains(PTEFlags::V) {
+        if l0_pte.flags() == PTEFlags::NONE {
             return Err(RuntimeError::NoMatchedAddr);
         }
         Ok(l0_pte)
@@ -310,6 +299,30 @@ impl PageMapping {
         }
         Ok(mem_set)
     }
+
+    // 获取所有用户页面的映射项
+    pub fn get_user_entries(&self) -> Vec<(VirtPageNum, PageTableEntry)> {
+        let mut entries = vec![];
+        if self.0 == 0 {
+            return entries;
+        }
+        let l2_vec = PageTableEntry::get_vec_from_phys(self.0.into());
+        for (i, l2_pte) in l2_vec.iter().enumerate() {
+            if !l2_pte.is_valid_pd() { continue; }
+            let l1_vec = PageTableEntry::get_vec_from_phys(l2_pte.ppn().into());
+            for (j, l1_pte) in l1_vec.iter().enumerate() {
+                if !l1_pte.is_valid_pd() { continue; }
+                let l0_vec = PageTableEntry::get_vec_from_phys(l1_pte.ppn().into());
+                for (k, l0_pte) in l0_vec.iter().enumerate() {
+                    // 仅记录用户页面 跳过恒等映射 PROT_NONE的页面没有V也需要记录
+                    if l0_pte.flags().contains(PTEFlags::U) {
+                        entries.push(((i << 18 | j << 9 | k).into(), *l0_pte));
+                    }
+                }
+            }
+        }
+        entries
+    }
 }
 
 
@@ -379,6 +392,11 @@ impl PageMappingManager {
     pub fn get_entry(&self, virt_addr: VirtAddr) -> Result<PageTableEntry, RuntimeError> {
         self.pte.get_entry(virt_addr)
     }
+
+    // 获取最后一级页表项 包括没有访问权限的页面
+    pub fn get_leaf(&self, virt_addr: VirtAddr) -> Result<PageTableEntry, RuntimeError> {
+        self.pte.get_leaf(virt_addr)
+    }
     
 
     // 更改pte
@@ -398,6 +416,11 @@ impl PageMappingManager {
 
     // 释放内存资源
     pub fn release(&self) {
+        // 已经释放过的页表不再遍历
+        if self.mem_set.borrow().0.is_empty() {
+            return;
+        }
+        self.release_cow();
         let mut mem_set = self.mem_set.borrow_mut();
         mem_set.release();
     }
@@ -0,0 +1,339 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::fs::file::File;
+use crate::fs::file::FileOP;
+use crate::fs::filetree::INode;
+use crate::runtime_err::RuntimeError;
+use crate::task::ipc::ShmMemory;
+
+use super::addr::PAGE_SIZE;
+use super::addr::VirtAddr;
+use super::addr::VirtPageNum;
+use super::addr::get_buf_from_phys_page;
+use super::cow::flush_tlb;
+use super::cow::is_cow_page;
+use super::cow::release_cow_page;
+use super::mem_map::MemMap;
+use super::mem_set::MemSet;
+use super::page_table::PTEFlags;
+use super::page_table::PageMappingManager;
+
+// mmap 自动选择地址的范围
+pub const MMAP_START: usize = 0xd000_0000;
+pub const MMAP_END: usize = 0xe000_0000;
+
+// 虚拟内存区域的来源
+#[derive(Clone)]
+pub enum VmaType {
+    Anonymous,                      // 匿名映射 缺页时分配空页
+    File(Rc<dyn FileOP>, usize),    // 文件映射 (文件, 区域开始对应的文件偏移)
+    Shm(Rc<ShmMemory>, usize),      // System V共享内存 (共享内存的页面, 区域开始对应的段内偏移)
+    Stack,                          // 用户栈
+    Heap                            // 用户堆
+}
+
+// 缺页访问类型
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PageFaultType {
+    Load,
+    Store,
+    Instruction
+}
+
+// 虚拟内存区域 [start, end)
+#[derive(Clone)]
+pub struct Vma {
+    pub start: usize,
+    pub end: usize,
+    pub flags: PTEFlags,
+    pub shared: bool,               // 是否为共享映射 共享映射在fork时不进行写时复制
+    pub vma_type: VmaType
+}
+
+impl Vma {
+    pub fn new(start: usize, end: usize, flags: PTEFlags, vma_type: VmaType) -> Self {
+        Self {
+            start: start / PAGE_SIZE * PAGE_SIZE,
+            end: (end + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE,
+            flags: flags | PTEFlags::V | PTEFlags::U,
+            shared: false,
+            vma_type
+        }
+    }
+
+    // 判断地址是否在区域内
+    pub fn contains(&self, addr: usize) -> bool {
+        addr >= self.start && addr < self.end
+    }
+
+    // 判断是否允许对应的访问
+    pub fn allow(&self, fault_type: PageFaultType) -> bool {
+        match fault_type {
+            PageFaultType::Load => self.flags.contains(PTEFlags::R),
+            PageFaultType::Store => self.flags.contains(PTEFlags::W),
+            PageFaultType::Instruction => self.flags.contains(PTEFlags::X)
+        }
+    }
+
+    // 获取共享文件映射对应的文件节点 共享映射的页面放在文件的页缓存中
+    pub fn get_shared_inode(&self) -> Option<Rc<INode>> {
+        match &self.vma_type {
+            VmaType::File(file, _) if self.shared => {
+                file.clone().downcast::<File>().ok().map(|x| x.get_inode())
+            }
+            _ => None
+        }
+    }
+
+    // 判断是否可以和后面的区域合并
+    fn can_merge(&self, next: &Vma) -> bool {
+        if self.end != next.start || self.flags != next.flags || self.shared != next.shared {
+            return false;
+        }
+        match (&self.vma_type, &next.vma_type) {
+            (VmaType::Anonymous, VmaType::Anonymous) => true,
+            (VmaType::File(file, offset), VmaType::File(next_file, next_offset)) => {
+                Rc::ptr_eq(file, next_file) && offset + (self.end - self.start) == *next_offset
+            }
+            _ => false
+        }
+    }
+}
+
+// 进程的虚拟内存区域列表 按照开始地址排序
+#[derive(Clone)]
+pub struct VmaList(pub Vec<Vma>);
+
+impl VmaList {
+    pub fn new() -> Self {
+        VmaList(vec![])
+    }
+
+    // 添加区域
+    pub fn push(&mut self, vma: Vma) {
+        let index = self.0.iter().position(|x| x.start > vma.start).unwrap_or(self.0.len());
+        self.0.insert(index, vma);
+        self.merge();
+    }
+
+    // 查找地址所在的区域
+    pub fn find(&self, addr: usize) -> Option<&Vma> {
+        self.0.iter().find(|x| x.contains(addr))
+    }
+
+    // 判断范围内是否没有区域
+    pub fn is_free(&self, start: usize, end: usize) -> bool {
+        !self.0.iter().any(|x| x.start < end && start < x.end)
+    }
+
+    // 判断范围是否完全被区域覆盖
+    pub fn is_mapped(&self, start: usize, end: usize) -> bool {
+        let mut addr = start;
+        for vma in &self.0 {
+            if vma.contains(addr) {
+                addr = vma.end;
+            }
+            if addr >= end {
+                return true;
+            }
+        }
+        false
+    }
+
+    // 查找可以放置len长度的空闲地址
+    pub fn find_free(&self, len: usize) -> Option<usize> {
+        let mut start = MMAP_START;
+        for vma in self.0.iter().filter(|x| x.end > MMAP_START) {
+            if vma.start >= start + len {
+                break;
+            }
+            if vma.end > start {
+                start = vma.end;
+            }
+        }
+        if start + len <= MMAP_END {
+            Some(start)
+        } else {
+            None
+        }
+    }
+
+    // 在addr处拆分区域
+    fn split_at(&mut self, addr: usize) {
+        if let Some(index) = self.0.iter().position(|x| x.start < addr && addr < x.end) {
+            let mut right = self.0[index].clone();
+            right.start = addr;
+            match &mut right.vma_type {
+                VmaType::File(_, offset) | VmaType::Shm(_, offset) => *offset += addr - self.0[index].start,
+                _ => {}
+            }
+            self.0[index].end = addr;
+            self.0.insert(index + 1, right);
+        }
+    }
+
+    // 合并相邻的区域
+    fn merge(&mut self) {
+        let mut i = 0;
+        while i + 1 < self.0.len() {
+            if self.0[i].can_merge(&self.0[i + 1]) {
+                let next = self.0.remove(i + 1);
+                self.0[i].end = next.end;
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    // 移除范围内的区域 返回被移除的部分
+    pub fn remove(&mut self, start: usize, end: usize) -> Vec<Vma> {
+        self.split_at(start);
+        self.split_at(end);
+        self.0.drain_filter(|x| x.start >= start && x.end <= end).collect()
+    }
+
+    // 修改范围内区域的权限
+    pub fn protect(&mut self, start: usize, end: usize, flags: PTEFlags) {
+        self.split_at(start);
+        self.split_at(end);
+        for vma in self.0.iter_mut().filter(|x| x.start >= start && x.end <= end) {
+            vma.flags = flags | PTEFlags::V | PTEFlags::U;
+        }
+        self.merge();
+    }
+
+    // 更新堆区域
+    pub fn set_heap(&mut self, start: usize, end: usize) {
+        self.0.retain(|x| !matches!(x.vma_type, VmaType::Heap));
+        if end > start {
+            self.push(Vma::new(start, end, PTEFlags::VRWX, VmaType::Heap));
+        }
+    }
+
+    // 将范围内可写的共享文件映射写回文件
+    pub fn sync(&self, start: usize, end: usize) -> Result<(), RuntimeError> {
+        let start = start / PAGE_SIZE * PAGE_SIZE;
+        for vma in self.0.iter().filter(|x| x.start < end && start < x.end && x.flags.contains(PTEFlags::W)) {
+            if let (Some(inode), VmaType::File(_, offset)) = (vma.get_shared_inode(), &vma.vma_type) {
+                for addr in (start.max(vma.start)..end.min(vma.end)).step_by(PAGE_SIZE) {
+                    inode.writeback_page((offset + addr - vma.start) / PAGE_SIZE)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 清空区域
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl PageMappingManager {
+    // 处理缺页异常 返回是否成功处理
+    pub fn handle_page_fault(&self, vmas: &VmaList, addr: usize, fault_type: PageFaultType) -> Result<bool, RuntimeError> {
+        let vma = match vmas.find(addr) {
+            Some(vma) => vma,
+            None => return Ok(false)
+        };
+        if !vma.allow(fault_type) {
+            return Ok(false);
+        }
+        // 写时复制页面
+        if fault_type == PageFaultType::Store && self.handle_cow_fault(addr.into())? {
+            return Ok(true);
+        }
+        // 页面已经存在 属于权限错误
+        if let Ok(entry) = self.get_entry(addr.into()) {
+            if entry.flags().contains(PTEFlags::U) {
+                return Ok(false);
+            }
+        }
+        self.map_vma_page(vma, addr)?;
+        flush_tlb();
+        Ok(true)
+    }
+
+    // 为区域中addr所在的页面分配内存并映射
+    fn map_vma_page(&self, vma: &Vma, addr: usize) -> Result<(), RuntimeError> {
+        let vpn = VirtPageNum::from(addr / PAGE_SIZE);
+        let page_start = addr / PAGE_SIZE * PAGE_SIZE;
+        // 共享文件映射直接使用文件的缓存页 所有进程看到相同的物理页
+        if let (Some(inode), VmaType::File(_, offset)) = (vma.get_shared_inode(), &vma.vma_type) {
+            let page = inode.get_cache_page((offset + page_start - vma.start) / PAGE_SIZE)?;
+            self.add_mapping(page.ppn, vpn, vma.flags)?;
+            self.add_mem_set(&mut MemSet(vec![page]));
+            return Ok(());
+        }
+        // 共享内存直接使用段的页面
+        if let VmaType::Shm(memory, offset) = &vma.vma_type {
+            let page = memory.pages[(offset + page_start - vma.start) / PAGE_SIZE].clone();
+            self.add_mapping(page.ppn, vpn, vma.flags)?;
+            self.add_mem_set(&mut MemSet(vec![page]));
+            return Ok(());
+        }
+        let mem_map = MemMap::new(vpn, 1, vma.flags)?;
+        if let VmaType::File(file, offset) = &vma.vma_type {
+            // 从文件中读取页面内容 超出文件的部分为0
+            let pos = offset + page_start - vma.start;
+            if pos < file.get_size() {
+                file.read_at(pos, get_buf_from_phys_page(mem_map.ppn, 1));
+            }
+        }
+        self.add_mapping(mem_map.ppn, vpn, vma.flags)?;
+        self.add_mem_set(&mut MemSet(vec![mem_map]));
+        Ok(())
+    }
+
+    // 为区域的所有页面分配内存
+    pub fn populate(&self, vma: &Vma) -> Result<(), RuntimeError> {
+        for addr in (vma.start..vma.end).step_by(PAGE_SIZE) {
+            self.map_vma_page(vma, addr)?;
+        }
+        flush_tlb();
+        Ok(())
+    }
+
+    // 取消范围内的用户页面映射 并释放对应的页面
+    pub fn unmap_range(&self, start: usize, end: usize) {
+        let mut ppns = vec![];
+        for addr in (start..end).step_by(PAGE_SIZE) {
+            if let Ok(entry) = self.get_leaf(addr.into()) {
+                if entry.flags().contains(PTEFlags::U) {
+                    release_cow_page(entry.ppn().0);
+                    self.pte.remove_mapping(addr.into());
+                    ppns.push(entry.ppn());
+                }
+            }
+        }
+        // 文件缓存页的虚拟页号不对应映射地址 按照物理页移除
+        self.mem_set.borrow_mut().remove_pages(&ppns);
+        flush_tlb();
+    }
+
+    // 修改范围内已映射页面的权限
+    pub fn protect_range(&self, start: usize, end: usize, flags: PTEFlags) -> Result<(), RuntimeError> {
+        // 没有任何权限的页面只保留U 清除V后访问产生缺页 由区域的权限判断为段错误 物理页不释放
+        let none = (flags & PTEFlags::VRWX) - PTEFlags::V == PTEFlags::NONE;
+        for addr in (start..end).step_by(PAGE_SIZE) {
+            if let Ok(entry) = self.get_leaf(addr.into()) {
+                if !entry.flags().contains(PTEFlags::U) {
+                    continue;
+                }
+                if none {
+                    self.add_mapping(entry.ppn(), VirtAddr::from(addr).into(), PTEFlags::U)?;
+                    continue;
+                }
+                let mut new_flags = flags | PTEFlags::V | PTEFlags::U;
+                // 写时复制的页面保持只读 写入时再复制
+                if is_cow_page(entry.ppn().0) {
+                    new_flags -= PTEFlags::W;
+                }
+                self.add_mapping(entry.ppn(), VirtAddr::from(addr).into(), new_flags)?;
+            }
+        }
+        flush_tlb();
+        Ok(())
+    }
+}
@@ -21,5 +21,11 @@ pub enum RuntimeError {
     WriteZero,
     UnexpectedEof,
     NotRWFile,
-    NotDir
+    NotDir,
+    // 需要的不是目录
+    IsDir,
+    // 目录不为空
+    NotEmpty,
+    // 文件系统没有空间
+    NoSpace
 }
\ No newline at end of file
@@ -13,9 +13,9 @@ pub const ENOMEM: usize = -12 as isize as usize; /* Out of memory */
 pub const EACCES: usize = -13 as isize as usize; /* Permission denied */
 pub const EFAULT: usize = -14 as isize as usize; /* Bad address */
 pub const ENOTBLK: usize = -15 as isize as usize; /* Block device required */
-pub const EBUSY: usize = -6 as isize as usize; /* Device or resource busy */
+pub const EBUSY: usize = -16 as isize as usize; /* Device or resource busy */
 pub const EEXIST: usize = -17 as isize as usize; /* File exists */
-pub const EXDEV: usize = -8 as isize as usize; /* Cross-device link */
+pub const EXDEV: usize = -18 as isize as usize; /* Cross-device link */
 pub const ENODEV: usize = -19 as isize as usize; /* No such device */
 pub const ENOTDIR: usize = -20 as isize as usize; /* Not a directory */
 pub const EISDIR: usize = -21 as isize as usize; /* Is a directory */
@@ -24,11 +24,28 @@ pub const ENFILE: usize = -23 as isize as usize; /* File table overflow */
 pub const EMFILE: usize = -24 as isize as usize; /* Too many open files */
 pub const ENOTTY: usize = -25 as isize as usize; /* Not a typewriter */
 pub const ETXTBSY: usize = -26 as isize as usize; /* Text file busy */
-pub const EFBIG: usize = -7 as isize as usize; /* File too large */
+pub const EFBIG: usize = -27 as isize as usize; /* File too large */
 pub const ENOSPC: usize = -28 as isize as usize; /* No space left on device */
 pub const ESPIPE: usize = -29 as isize as usize; /* Illegal seek */
-pub const EROFS: usize = -0 as isize as usize; /* Read-only file system */
+pub const EROFS: usize = -30 as isize as usize; /* Read-only file system */
 pub const EMLINK: usize = -31 as isize as usize; /* Too many links */
-pub const EPIPE: usize = -2 as isize as usize; /* Broken pipe */
-pub const EDOM: usize = -3 as isize as usize; /* Math argument out of domain of func */
+pub const EPIPE: usize = -32 as isize as usize; /* Broken pipe */
+pub const EDOM: usize = -33 as isize as usize; /* Math argument out of domain of func */
 pub const ERANGE: usize = -34 as isize as usize; /* Math result not representable */
+pub const ENOSYS: usize = -38 as isize as usize; /* Function not implemented */
+pub const ENOTEMPTY: usize = -39 as isize as usize; /* Directory not empty */
+pub const ELOOP: usize = -40 as isize as usize; /* Too many symbolic links encountered */
+pub const ENOMSG: usize = -42 as isize as usize; /* No message of desired type */
+pub const EIDRM: usize = -43 as isize as usize; /* Identifier removed */
+pub const ENOTSOCK: usize = -88 as isize as usize; /* Socket operation on non-socket */
+pub const EDESTADDRREQ: usize = -89 as isize as usize; /* Destination address required */
+pub const EMSGSIZE: usize = -90 as isize as usize; /* Message too long */
+pub const EPROTOTYPE: usize = -91 as isize as usize; /* Protocol wrong type for socket */
+pub const EOPNOTSUPP: usize = -95 as isize as usize; /* Operation not supported on transport endpoint */
+pub const EAFNOSUPPORT: usize = -97 as isize as usize; /* Address family not supported by protocol */
+pub const EADDRINUSE: usize = -98 as isize as usize; /* Address already in use */
+pub const EISCONN: usize = -106 as isize as usize; /* Transport endpoint is already connected */
+pub const ENOTCONN: usize = -107 as isize as usize; /* Transport endpoint is not connected */
+pub const ETOOMANYREFS: usize = -109 as isize as usize; /* Too many references: cannot splice */
+pub const ETIMEDOUT: usize = -110 as isize as usize; /* Connection timed out */
+pub const ECONNREFUSED: usize = -111 as isize as usize; /* Connection refused */
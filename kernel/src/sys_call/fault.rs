@@ -0,0 +1,140 @@
+use crate::memory::addr::UserAddr;
+use crate::runtime_err::RuntimeError;
+use crate::task::signal::SigInfo;
+use crate::task::signal::Signal;
+use crate::task::signal::SIG_DFL;
+use crate::task::signal::SIG_IGN;
+use crate::task::signal::BUS_ADRALN;
+use crate::task::signal::SEGV_MAPERR;
+use crate::task::task::Task;
+
+// 需要模拟的未对齐访存指令
+struct MisalignedAccess {
+    store: bool,        // 是否为写入
+    len: usize,         // 访问的字节数
+    signed: bool,       // 读取时是否进行符号扩展
+    reg: usize,         // 读取的目标寄存器或者写入的源寄存器
+    insn_len: usize     // 指令长度
+}
+
+impl MisalignedAccess {
+    fn load(len: usize, signed: bool, reg: usize, insn_len: usize) -> Option<Self> {
+        Some(Self { store: false, len, signed, reg, insn_len })
+    }
+
+    fn store(len: usize, reg: usize, insn_len: usize) -> Option<Self> {
+        Some(Self { store: true, len, signed: false, reg, insn_len })
+    }
+
+    // 解析访存指令 只支持整数的读取和写入 浮点和原子指令返回None
+    fn decode(insn: u32) -> Option<Self> {
+        let insn = insn as usize;
+        // 32位指令
+        if insn & 0b11 == 0b11 {
+            let rd = insn >> 7 & 0x1f;
+            let rs2 = insn >> 20 & 0x1f;
+            return match (insn & 0x7f, insn >> 12 & 0b111) {
+                // LH LW LD LHU LWU
+                (0x03, 1) => Self::load(2, true, rd, 4),
+                (0x03, 2) => Self::load(4, true, rd, 4),
+                (0x03, 3) => Self::load(8, false, rd, 4),
+                (0x03, 5) => Self::load(2, false, rd, 4),
+                (0x03, 6) => Self::load(4, false, rd, 4),
+                // SH SW SD
+                (0x23, 1) => Self::store(2, rs2, 4),
+                (0x23, 2) => Self::store(4, rs2, 4),
+                (0x23, 3) => Self::store(8, rs2, 4),
+                _ => None
+            };
+        }
+        // 压缩指令 C.LW C.LD C.SW C.SD 使用x8~x15 栈指针相对的指令使用完整的寄存器编号
+        let reg = (insn >> 2 & 0b111) + 8;
+        match (insn & 0b11, insn >> 13 & 0b111) {
+            (0b00, 0b010) => Self::load(4, true, reg, 2),
+            (0b00, 0b011) => Self::load(8, false, reg, 2),
+            (0b00, 0b110) => Self::store(4, reg, 2),
+            (0b00, 0b111) => Self::store(8, reg, 2),
+            (0b10, 0b010) => Self::load(4, true, insn >> 7 & 0x1f, 2),
+            (0b10, 0b011) => Self::load(8, false, insn >> 7 & 0x1f, 2),
+            (0b10, 0b110) => Self::store(4, insn >> 2 & 0x1f, 2),
+            (0b10, 0b111) => Self::store(8, insn >> 2 & 0x1f, 2),
+            _ => None
+        }
+    }
+}
+
+impl Task {
+    // 用户程序产生异常时发送信号 信号被屏蔽或者没有处理函数时结束进程
+    pub fn force_signal(&self, info: SigInfo) -> Result<(), RuntimeError> {
+        let signal = info.signo();
+        let inner = self.inner.borrow();
+        let handler = inner.process.borrow().sig_actions[signal].handler;
+        let blocked = inner.sig_mask.contains(signal);
+        drop(inner);
+        if handler != SIG_DFL && handler != SIG_IGN && !blocked {
+            return self.signal(info);
+        }
+        warn!("进程 {} 产生异常 信号: {} 地址: {:#x}", self.pid, signal, info.fields[0]);
+        self.get_process().borrow_mut().exit_by_signal(signal);
+        Err(RuntimeError::KillCurrentTask)
+    }
+
+    // 按字节模拟未对齐的读取和写入 无法模拟时发送SIGBUS 地址无效时发送SIGSEGV
+    pub fn misaligned_access(&self, addr: usize) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.clone();
+        let process = process.borrow();
+        let sepc = inner.context.sepc;
+        // 读取触发异常的指令 压缩指令只有2个字节
+        let read_half = |addr: usize| {
+            let bytes = [*UserAddr::<u8>::from(addr).transfer(), *UserAddr::<u8>::from(addr + 1).transfer()];
+            u16::from_le_bytes(bytes) as u32
+        };
+        let mut insn = 0;
+        if process.prepare_user_read(sepc, 2) {
+            insn = read_half(sepc);
+            if insn & 0b11 == 0b11 && process.prepare_user_read(sepc + 2, 2) {
+                insn |= read_half(sepc + 2) << 16;
+            }
+        }
+        let access = match MisalignedAccess::decode(insn) {
+            Some(access) => access,
+            None => {
+                drop(process);
+                drop(inner);
+                return self.force_signal(SigInfo::from_fault(Signal::SIGBUS as usize, BUS_ADRALN, addr));
+            }
+        };
+        let valid = match access.store {
+            true => process.prepare_user_write(addr, access.len),
+            false => process.prepare_user_read(addr, access.len)
+        };
+        drop(process);
+        if !valid {
+            drop(inner);
+            return self.force_signal(SigInfo::from_fault(Signal::SIGSEGV as usize, SEGV_MAPERR, addr));
+        }
+        if access.store {
+            let value = inner.context.x[access.reg].to_le_bytes();
+            for i in 0..access.len {
+                *UserAddr::<u8>::from(addr + i).transfer() = value[i];
+            }
+        } else {
+            let mut bytes = [0u8; 8];
+            for i in 0..access.len {
+                bytes[i] = *UserAddr::<u8>::from(addr + i).transfer();
+            }
+            let mut value = usize::from_le_bytes(bytes);
+            if access.signed {
+                let shift = 64 - access.len * 8;
+                value = ((value << shift) as isize >> shift) as usize;
+            }
+            // x0 始终为0
+            if access.reg != 0 {
+                inner.context.x[access.reg] = value;
+            }
+        }
+        inner.context.sepc += access.insn_len;
+        Ok(())
+    }
+}
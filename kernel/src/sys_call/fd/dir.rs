@@ -1,4 +1,16 @@
-use crate::{task::{task::Task, fd_table::FD_NULL}, memory::addr::UserAddr, runtime_err::RuntimeError, fs::filetree::INode};
+use crate::{task::{task::Task, fd_table::FD_NULL}, memory::addr::UserAddr, runtime_err::RuntimeError, fs::{filetree::INode, file::FileType}};
+use crate::sys_call::consts::{EEXIST, EINVAL, EIO, EISDIR, ENOENT, ENOSPC, ENOTDIR, ENOTEMPTY, EPERM, EXDEV};
+
+// mknodat的文件类型
+const S_IFMT: usize = 0o170000;
+const S_IFIFO: usize = 0o010000;
+const S_IFREG: usize = 0o100000;
+
+// linkat跟随符号链接
+const AT_SYMLINK_FOLLOW: usize = 0x400;
+
+// renameat2目标已经存在时返回EEXIST
+const RENAME_NOREPLACE: usize = 1;
 
 impl Task {
     
@@ -58,6 +70,39 @@ impl Task {
         inner.context.x[10] = 0;
         Ok(())
     }
+    // 创建文件节点 支持命名管道和普通文件
+    pub fn sys_mknodat(&self, dir_fd: usize, filename: UserAddr<u8>, mode: usize, _dev: usize) -> Result<(), RuntimeError> {
+        let filename = filename.read_string();
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+        debug!("mknodat dir_fd: {:#x}, filename: {}, mode: {:#o}", dir_fd, filename, mode);
+
+        let current = if dir_fd == FD_NULL {
+            None
+        } else {
+            let file = process.fd_table.get_file(dir_fd)?;
+            Some(file.get_inode())
+        };
+        let file_type = match mode & S_IFMT {
+            S_IFIFO => Some(FileType::Pipeline),
+            0 | S_IFREG => Some(FileType::File),
+            _ => None
+        };
+        let ret = if INode::get(current.clone(), &filename).is_ok() {
+            EEXIST
+        } else if let Some(file_type) = file_type {
+            let node = INode::mknod(current, &filename, file_type)?;
+            let _ = node.set_mode((mode & 0o7777) as u32);
+            0
+        } else {
+            // 不支持创建设备文件和套接字
+            EPERM
+        };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
     // 取消链接文件
     pub fn sys_unlinkat(&self, fd: usize, filename: UserAddr<u8>, _flags: usize) -> Result<(), RuntimeError> {
         let filename = filename.read_string();
@@ -71,12 +116,128 @@ impl Task {
             let file = process.fd_table.get_file(fd)?;
             Some(file.get_inode())
         };
-        let cnode = INode::get(current, &filename)?;
+        // 删除符号链接本身而不是链接的目标
+        let cnode = INode::get_nofollow(current, &filename)?;
         cnode.del_self();
         drop(process);
         inner.context.x[10] = 0;
         Ok(())
     }
+
+    // 创建符号链接 目标路径不需要存在
+    pub fn sys_symlinkat(&self, target: UserAddr<u8>, dir_fd: usize, path: UserAddr<u8>) -> Result<(), RuntimeError> {
+        let target = target.read_string();
+        let path = path.read_string();
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+        debug!("symlink {} to {}", path, target);
+
+        let current = if dir_fd == FD_NULL {
+            None
+        } else {
+            Some(process.fd_table.get_file(dir_fd)?.get_inode())
+        };
+        let ret = if INode::get_nofollow(current.clone(), &path).is_ok() {
+            EEXIST
+        } else {
+            match INode::symlink(current, &target, &path) {
+                Ok(_) => 0,
+                Err(RuntimeError::FileNotFound) => ENOENT,
+                Err(RuntimeError::NoSpace) => ENOSPC,
+                Err(_) => EPERM
+            }
+        };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
+    // 创建硬链接 只支持同一个文件系统中的普通文件
+    pub fn sys_linkat(&self, old_dir: usize, old_path: UserAddr<u8>, new_dir: usize, 
+            new_path: UserAddr<u8>, flags: usize) -> Result<(), RuntimeError> {
+        let old_path = old_path.read_string();
+        let new_path = new_path.read_string();
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+        debug!("link {} to {}", new_path, old_path);
+
+        let old_current = if old_dir == FD_NULL {
+            None
+        } else {
+            Some(process.fd_table.get_file(old_dir)?.get_inode())
+        };
+        let new_current = if new_dir == FD_NULL {
+            None
+        } else {
+            Some(process.fd_table.get_file(new_dir)?.get_inode())
+        };
+        // 默认不跟随符号链接 设置AT_SYMLINK_FOLLOW时链接到目标文件
+        let node = match flags & AT_SYMLINK_FOLLOW {
+            0 => INode::get_nofollow(old_current, &old_path),
+            _ => INode::get(old_current, &old_path)
+        };
+        let ret = match node {
+            Err(_) => ENOENT,
+            Ok(_) if INode::get_nofollow(new_current.clone(), &new_path).is_ok() => EEXIST,
+            Ok(node) if node.is_dir() => EPERM,
+            Ok(node) => match node.link(new_current, &new_path) {
+                Ok(_) => 0,
+                Err(RuntimeError::FileNotFound) => ENOENT,
+                Err(_) => EXDEV
+            }
+        };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
+    // 重命名文件 不同目录之间重命名需要文件系统支持硬链接
+    pub fn sys_renameat2(&self, old_dir: usize, old_path: UserAddr<u8>, new_dir: usize, 
+            new_path: UserAddr<u8>, flags: usize) -> Result<(), RuntimeError> {
+        let old_path = old_path.read_string();
+        let new_path = new_path.read_string();
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+        debug!("rename {} to {} flags: {:#x}", old_path, new_path, flags);
+
+        // 不支持RENAME_EXCHANGE和RENAME_WHITEOUT
+        if flags & !RENAME_NOREPLACE != 0 {
+            drop(process);
+            inner.context.x[10] = EINVAL;
+            return Ok(());
+        }
+        let old_current = if old_dir == FD_NULL {
+            None
+        } else {
+            Some(process.fd_table.get_file(old_dir)?.get_inode())
+        };
+        let new_current = if new_dir == FD_NULL {
+            None
+        } else {
+            Some(process.fd_table.get_file(new_dir)?.get_inode())
+        };
+        // 重命名符号链接本身
+        let node = INode::get_nofollow(old_current, &old_path)?;
+        let (parent, filename) = INode::get_parent(new_current, &new_path)?;
+        let ret = if flags & RENAME_NOREPLACE != 0 && parent.clone().get_children(filename).is_ok() {
+            EEXIST
+        } else {
+            match node.rename(parent, filename) {
+                Ok(_) => 0,
+                Err(RuntimeError::FileNotFound) => ENOENT,
+                Err(RuntimeError::NotDir) => ENOTDIR,
+                Err(RuntimeError::IsDir) => EISDIR,
+                Err(RuntimeError::NotEmpty) => ENOTEMPTY,
+                Err(RuntimeError::NoSpace) => ENOSPC,
+                // 文件系统不支持跨目录移动
+                Err(RuntimeError::NotRWFile) => EXDEV,
+                Err(_) => EIO
+            }
+        };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
     
 
 }
\ No newline at end of file
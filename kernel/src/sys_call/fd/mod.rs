@@ -3,3 +3,5 @@ pub mod dir;
 pub mod stat;
 pub mod rw;
 pub mod open;
+pub mod poll;
+pub mod mount;
@@ -0,0 +1,67 @@
+use alloc::rc::Rc;
+
+use crate::fs::devfs::DevFileSystem;
+use crate::fs::fat::FatFileSystem;
+use crate::fs::filetree::INode;
+use crate::fs::mount;
+use crate::fs::procfs::ProcFileSystem;
+use crate::fs::tmpfs::TmpFileSystem;
+use crate::fs::vfs::Filesystem;
+use crate::memory::addr::UserAddr;
+use crate::runtime_err::RuntimeError;
+use crate::sys_call::consts::{ENODEV, ENOENT};
+use crate::task::task::Task;
+
+impl Task {
+    // 获取路径对应的节点 相对路径从工作目录开始查找
+    fn mount_target(&self, path: &str) -> Result<Rc<INode>, usize> {
+        let current = match path.starts_with('/') {
+            true => None,
+            false => Some(self.inner.borrow().process.borrow().workspace.clone())
+        };
+        INode::get(current, path).map_err(|_| ENOENT)
+    }
+
+    // 挂载文件系统
+    pub fn sys_mount(&self, special: UserAddr<u8>, dir: UserAddr<u8>, fstype: UserAddr<u8>,
+            flags: usize, data: UserAddr<u8>) -> Result<(), RuntimeError> {
+        let source = match special.is_valid() {
+            true => special.read_string(),
+            false => "none".into()
+        };
+        let dir = dir.read_string();
+        let fstype = fstype.read_string();
+        debug!("mount {} to {} type: {} flags: {:#x}", source, dir, fstype, flags);
+        // 根据文件系统类型创建文件系统
+        let fs: Option<Rc<dyn Filesystem>> = match fstype.as_str() {
+            "vfat" | "fat32" | "fat" => Some(FatFileSystem::new()),
+            // tmpfs支持size nr_inodes和mode参数
+            "tmpfs" => Some(match data.is_valid() {
+                true => TmpFileSystem::with_options(&data.read_string()),
+                false => TmpFileSystem::new()
+            }),
+            "proc" => Some(ProcFileSystem::new()),
+            "devtmpfs" | "devfs" => Some(DevFileSystem::new()),
+            _ => None
+        };
+        let ret = match fs {
+            Some(fs) => self.mount_target(&dir)
+                .and_then(|target| mount::mount(&source, target, fs, flags))
+                .map_or_else(|err| err, |_| 0),
+            None => ENODEV
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 卸载文件系统
+    pub fn sys_umount2(&self, target: UserAddr<u8>, _flags: usize) -> Result<(), RuntimeError> {
+        let target = target.read_string();
+        debug!("umount {}", target);
+        let ret = self.mount_target(&target)
+            .and_then(mount::umount)
+            .map_or_else(|err| err, |_| 0);
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use alloc::{rc::Rc, string::ToString};
 
-use crate::{task::{task::Task, fd_table::{FileDesc, FD_NULL}, pipe::new_pipe}, runtime_err::RuntimeError, memory::addr::UserAddr, sys_call::OpenFlags, fs::{stdio::{StdZero, StdNull}, specials::{proc_mounts::ProcMounts, proc_meminfo::ProcMeminfo, etc_adjtime::EtcAdjtime, dev_rtc::DevRtc}, filetree::INode}, interrupt::timer::TimeSpec};
+use crate::{task::{task::Task, fd_table::{FileDesc, FD_NULL}, pipe::new_pipe}, runtime_err::RuntimeError, memory::addr::UserAddr, sys_call::{OpenFlags, consts::{EAGAIN, EEXIST, EINVAL, ENOENT}}, fs::{specials::etc_adjtime::EtcAdjtime, filetree::INode, file::{FileOP, FileType}}};
 
 impl Task {
     // 复制文件描述符
@@ -30,7 +30,7 @@ impl Task {
         Ok(())
     }
     // 打开文件
-    pub fn sys_openat(&self, fd: usize, filename: UserAddr<u8>, flags: usize, _open_mod: usize) -> Result<(), RuntimeError> {
+    pub fn sys_openat(&self, fd: usize, filename: UserAddr<u8>, flags: usize, mode: usize) -> Result<(), RuntimeError> {
         let filename = filename.read_string();
         debug!("open file: {}  flags: {:#x}", filename, flags);
         let mut inner = self.inner.borrow_mut();
@@ -39,36 +39,11 @@ impl Task {
         // 获取文件信息
         let flags = OpenFlags::from_bits_truncate(flags as u32);
 
-        if filename == "/dev/zero" {
-            let fd = process.fd_table.push(FileDesc::new(Rc::new(StdZero)));
-            drop(process);
-            inner.context.x[10] = fd;
-            return Ok(())
-        } else if filename == "/dev/null" {
-            let fd = process.fd_table.push(FileDesc::new(Rc::new(StdNull)));
-            drop(process);
-            inner.context.x[10] = fd;
-            return Ok(())
-        } else if filename == "/proc/mounts" {
-            let fd = process.fd_table.push(FileDesc::new(Rc::new(ProcMounts::new())));
-            drop(process);
-            inner.context.x[10] = fd;
-            return Ok(())
-        } else if filename == "/proc/meminfo" {
-            let fd = process.fd_table.push(FileDesc::new(Rc::new(ProcMeminfo::new())));
-            drop(process);
-            inner.context.x[10] = fd;
-            return Ok(())
-        } else if filename == "/etc/adjtime" {
+        if filename == "/etc/adjtime" {
             let fd = process.fd_table.push(FileDesc::new(Rc::new(EtcAdjtime::new())));
             drop(process);
             inner.context.x[10] = fd;
             return Ok(())
-        } else if filename == "/dev/rtc" {
-            let fd = process.fd_table.push(FileDesc::new(Rc::new(DevRtc::new())));
-            drop(process);
-            inner.context.x[10] = fd;
-            return Ok(())
         }
 
 
@@ -79,17 +54,50 @@ impl Task {
             let file = process.fd_table.get_file(fd)?;
             Some(file.get_inode())
         };
-        // 根据文件类型匹配
-        let file = if flags.contains(OpenFlags::CREATE) {
-            INode::open_or_create(current, &filename)?
-        } else {
-            INode::open(current, &filename)?
+        // 命名管道 读端和写端在同一个管道缓冲区上会合
+        if let Some(pipe) = INode::get(current.clone(), &filename).ok().and_then(|x| x.get_fifo()) {
+            let ret = match pipe.open_fifo(flags) {
+                Ok(file) => process.fd_table.push(file),
+                Err(err) => err
+            };
+            drop(process);
+            inner.context.x[10] = ret;
+            drop(inner);
+            // 阻塞模式下等待另一端打开
+            if ret == EAGAIN {
+                return self.wait_file(pipe.open_event(!flags.contains(OpenFlags::WRONLY)));
+            }
+            return Ok(())
+        }
+        // 根据文件类型匹配 O_EXCL要求文件不存在
+        let inode = match INode::get(current.clone(), &filename) {
+            Ok(_) if flags.contains(OpenFlags::CREATE | OpenFlags::EXCL) => {
+                drop(process);
+                inner.context.x[10] = EEXIST;
+                return Ok(())
+            }
+            Ok(inode) => inode,
+            Err(_) if flags.contains(OpenFlags::CREATE) => {
+                let inode = INode::get_or_create(current, &filename)?;
+                // 不保存权限的文件系统忽略
+                let _ = inode.set_mode((mode & 0o7777) as u32);
+                inode
+            }
+            Err(err) => return Err(err)
         };
-        // if flags.contains(OpenFlags::WRONLY) {
-        //     file.lseek(0, 2);
-        // }
-        let fd = process.fd_table.alloc();
-        process.fd_table.set(fd, FileDesc::new(file));
+        // 以写入方式打开时截断普通文件 不支持截断的特殊文件忽略
+        if flags.contains(OpenFlags::TRUNC) && flags.intersects(OpenFlags::WRONLY | OpenFlags::RDWR)
+            && inode.get_file_type() == FileType::File {
+            let _ = inode.truncate(0);
+        }
+        // 文件系统驱动提供的文件直接使用 其他文件使用通用的File
+        let file: Rc<dyn FileOP> = match inode.open_special() {
+            Some(file) => file,
+            None => INode::open_node(inode)?
+        };
+        let mut file_desc = FileDesc::new(file);
+        file_desc.flags = flags;
+        let fd = process.fd_table.push(file_desc);
         drop(process);
         debug!("return fd: {}", fd);
         inner.context.x[10] = fd;
@@ -106,45 +114,49 @@ impl Task {
         Ok(())
     }
 
+    // 读取符号链接的目标
     pub fn sys_readlinkat(&self, dir_fd: usize, path: UserAddr<u8>, 
         buf: UserAddr<u8>, len: usize) -> Result<(), RuntimeError> {
-        let mut inner = self.inner.borrow_mut();
         let path = path.read_string();
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
         debug!("read {} from dir_fd: {:#x} len: {}", path, dir_fd, len);
-        let path = if path == "/proc/self/exe" {
-            "/lmbench_all".to_string()
+
+        let current = if dir_fd == FD_NULL {
+            None
         } else {
-            path
+            Some(process.fd_table.get_file(dir_fd)?.get_inode())
+        };
+        let target = if path == "/proc/self/exe" {
+            Ok("/lmbench_all".to_string())
+        } else {
+            match INode::get_nofollow(current, &path) {
+                Ok(node) if node.get_file_type() == FileType::SymLink => Ok(node.read_link()?),
+                Ok(_) => Err(EINVAL),
+                Err(_) => Err(ENOENT)
+            }
+        };
+        drop(process);
+        // 缓冲区不够时截断 不写入结束符
+        inner.context.x[10] = match target {
+            Ok(target) => {
+                let target = target.as_bytes();
+                let read_len = target.len().min(len);
+                buf.transfer_vec(read_len).copy_from_slice(&target[..read_len]);
+                read_len
+            }
+            Err(err) => err
         };
-        let path = path.as_bytes();
-
-        let buf = buf.transfer_vec(len);
-        // let inode = INode::get(None, &path)?;
-        // let read_len = inode.read_to(buf)?;
-        // debug!("read_len: {:#x}", read_len);
-        buf[..path.len()].copy_from_slice(path);
-        inner.context.x[10] = path.len();
-        Ok(())
-    }
-
-    pub fn sys_ppoll(&self, fds: UserAddr<PollFD>, nfds: usize, _timeout: UserAddr<TimeSpec>) -> Result<(), RuntimeError> {
-        let fds = fds.transfer_vec(nfds);
-        let mut inner = self.inner.borrow_mut();
-        debug!("wait for fds: {}", fds.len());
-        for i in fds {
-            debug!("wait fd: {}", i.fd);
-        }
-        inner.context.x[10] = 1;
         Ok(())
     }
 
     // 管道符
-    pub fn sys_pipe2(&self, req_ptr: UserAddr<u32>) -> Result<(), RuntimeError> {
+    pub fn sys_pipe2(&self, req_ptr: UserAddr<u32>, flags: usize) -> Result<(), RuntimeError> {
         let pipe_arr =  req_ptr.transfer_vec(2);
         let mut inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
         // 创建pipe
-        let (read_pipe, write_pipe) = new_pipe();
+        let (read_pipe, write_pipe) = new_pipe(OpenFlags::from_bits_truncate(flags as u32));
         // 写入数据
         pipe_arr[0] = process.fd_table.push(read_pipe) as u32;
         pipe_arr[1] = process.fd_table.push(write_pipe) as u32;
@@ -157,10 +169,3 @@ impl Task {
 
 
 }
-
-#[repr(C)]
-pub struct PollFD {
-    pub fd: u32,
-    pub envents: u16,
-    pub revents: u16
-}
\ No newline at end of file
@@ -0,0 +1,315 @@
+use core::mem::size_of;
+
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::fs::file::FileOP;
+use crate::fs::file::PollEvents;
+use crate::interrupt::timer::TimeSpec;
+use crate::interrupt::timer::get_time_us;
+use crate::memory::addr::UserAddr;
+use crate::runtime_err::RuntimeError;
+use crate::sys_call::OpenFlags;
+use crate::sys_call::consts::{EBADF, EEXIST, EINTR, EINVAL, ELOOP, ENOENT};
+use crate::task::epoll::*;
+use crate::task::eventfd::{EventFd, EFD_CLOEXEC, EFD_NONBLOCK, EFD_SEMAPHORE};
+use crate::task::fd_table::FileDesc;
+use crate::task::signal::SigSet;
+use crate::task::signalfd::SignalFd;
+use crate::task::task::Task;
+use crate::task::task_scheduler::wait_poll;
+use crate::task::wait_queue::WaitEvent;
+
+// select 最多支持的文件描述符数量
+const FD_SETSIZE: usize = 1024;
+
+#[repr(C)]
+pub struct PollFD {
+    pub fd: u32,
+    pub envents: u16,
+    pub revents: u16
+}
+
+// pselect6 的第六个参数
+#[repr(C)]
+pub struct PselectSigMask {
+    mask: UserAddr<SigSet>,
+    size: usize
+}
+
+impl Task {
+    // 获取文件当前可以进行的操作 signalfd需要检查任务的信号
+    fn poll_file(&self, file: &Rc<dyn FileOP>) -> PollEvents {
+        match file.clone().downcast::<SignalFd>() {
+            Ok(signalfd) => {
+                let inner = self.inner.borrow();
+                let process = inner.process.borrow();
+                let pending = inner.pending.set.union(&process.pending.set);
+                match pending.intersect(&signalfd.mask.borrow()).is_empty() {
+                    true => PollEvents::empty(),
+                    false => PollEvents::IN
+                }
+            }
+            Err(file) => file.poll()
+        }
+    }
+
+    // 等待文件状态改变的事件 signalfd等待对应的信号
+    fn poll_event(&self, file: &Rc<dyn FileOP>) -> Option<WaitEvent> {
+        match file.clone().downcast::<SignalFd>() {
+            Ok(signalfd) => Some(WaitEvent::Signal(*signalfd.mask.borrow())),
+            Err(file) => file.wait_event()
+        }
+    }
+
+    // 获取等待的截止时间 唤醒后重新执行时使用第一次计算的截止时间 timeout单位为us
+    fn poll_deadline(&self, timeout: Option<usize>) -> Option<usize> {
+        self.inner.borrow_mut().poll_deadline.take().or_else(|| timeout.map(|x| get_time_us() + x))
+    }
+
+    // 判断是否已经超时
+    fn poll_expired(deadline: Option<usize>) -> bool {
+        deadline.map_or(false, |x| get_time_us() >= x)
+    }
+
+    // 没有就绪的文件时挂起 回退pc 任意事件发生或者超时后重新执行系统调用
+    // sigmask不为空时在等待期间替换信号掩码 已经存在可以处理的信号时返回EINTR
+    fn poll_wait(&self, events: Vec<WaitEvent>, deadline: Option<usize>, sigmask: UserAddr<SigSet>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        if sigmask.is_valid() {
+            let mut mask = *sigmask.transfer();
+            mask.remove_unblockable();
+            inner.saved_mask = Some(inner.sig_mask);
+            inner.sig_mask = mask;
+            let process = inner.process.borrow();
+            let pending = process.unignored(inner.pending.set.union(&process.pending.set)).unmasked(&mask);
+            drop(process);
+            if !pending.is_empty() {
+                inner.context.x[10] = EINTR;
+                return Ok(());
+            }
+        }
+        inner.context.sepc -= 4;
+        inner.poll_deadline = deadline;
+        drop(inner);
+        wait_poll(events, deadline);
+        Ok(())
+    }
+
+    // 等待多个文件描述符 timeout为空时一直等待
+    pub fn sys_ppoll(&self, fds: UserAddr<PollFD>, nfds: usize, timeout: UserAddr<TimeSpec>,
+            sigmask: UserAddr<SigSet>, sigsetsize: usize) -> Result<(), RuntimeError> {
+        if sigmask.is_valid() && sigsetsize != size_of::<SigSet>() {
+            self.inner.borrow_mut().context.x[10] = EINVAL;
+            return Ok(());
+        }
+        let timeout = match timeout.is_valid() {
+            true => Some(timeout.transfer().to_us()),
+            false => None
+        };
+        let deadline = self.poll_deadline(timeout);
+        let fds = fds.transfer_vec(nfds);
+        let process = self.get_process();
+        let files: Vec<Option<Rc<dyn FileOP>>> = fds.iter()
+            .map(|x| process.borrow_mut().fd_table.get(x.fd as usize).ok().map(|x| x.file.clone())).collect();
+        let mut ready = 0;
+        let mut events = vec![];
+        for (pollfd, file) in fds.iter_mut().zip(files) {
+            // 负数的文件描述符被忽略
+            if (pollfd.fd as i32) < 0 {
+                pollfd.revents = 0;
+                continue;
+            }
+            let mask = PollEvents::from_bits_truncate(pollfd.envents) | PollEvents::ERR | PollEvents::HUP;
+            let revents = match file {
+                Some(file) => {
+                    events.extend(self.poll_event(&file));
+                    self.poll_file(&file) & mask
+                }
+                None => PollEvents::NVAL
+            };
+            pollfd.revents = revents.bits();
+            if !revents.is_empty() {
+                ready += 1;
+            }
+        }
+        if ready == 0 && !Self::poll_expired(deadline) {
+            return self.poll_wait(events, deadline, sigmask);
+        }
+        self.inner.borrow_mut().context.x[10] = ready;
+        Ok(())
+    }
+
+    // 等待读、写和异常的文件描述符集合 返回时集合中只保留就绪的文件描述符
+    pub fn sys_pselect6(&self, nfds: usize, readfds: UserAddr<u64>, writefds: UserAddr<u64>, exceptfds: UserAddr<u64>,
+            timeout: UserAddr<TimeSpec>, sigmask: UserAddr<PselectSigMask>) -> Result<(), RuntimeError> {
+        let sigmask = match sigmask.is_valid() {
+            true => {
+                let sigmask = sigmask.transfer();
+                if sigmask.mask.is_valid() && sigmask.size != size_of::<SigSet>() {
+                    self.inner.borrow_mut().context.x[10] = EINVAL;
+                    return Ok(());
+                }
+                sigmask.mask
+            }
+            false => 0.into()
+        };
+        if nfds > FD_SETSIZE {
+            self.inner.borrow_mut().context.x[10] = EINVAL;
+            return Ok(());
+        }
+        let timeout = match timeout.is_valid() {
+            true => Some(timeout.transfer().to_us()),
+            false => None
+        };
+        let deadline = self.poll_deadline(timeout);
+        let words = (nfds + 63) / 64;
+        // 读取用户的集合 为空时视为没有文件描述符
+        let read_set = |set: UserAddr<u64>| match set.is_valid() {
+            true => set.transfer_vec(words).to_vec(),
+            false => vec![0u64; words]
+        };
+        let sets = [read_set(readfds), read_set(writefds), read_set(exceptfds)];
+        let masks = [
+            PollEvents::IN | PollEvents::HUP | PollEvents::ERR,
+            PollEvents::OUT | PollEvents::ERR,
+            PollEvents::PRI
+        ];
+        let mut results = [vec![0u64; words], vec![0u64; words], vec![0u64; words]];
+        let mut ready = 0;
+        let mut events = vec![];
+        let process = self.get_process();
+        for fd in 0..nfds {
+            let (word, bit) = (fd / 64, 1u64 << (fd % 64));
+            if sets.iter().all(|x| x[word] & bit == 0) {
+                continue;
+            }
+            let file = match process.borrow_mut().fd_table.get(fd) {
+                Ok(file) => file.file.clone(),
+                Err(_) => {
+                    self.inner.borrow_mut().context.x[10] = EBADF;
+                    return Ok(());
+                }
+            };
+            events.extend(self.poll_event(&file));
+            let revents = self.poll_file(&file);
+            for i in 0..3 {
+                if sets[i][word] & bit != 0 && !(revents & masks[i]).is_empty() {
+                    results[i][word] |= bit;
+                    ready += 1;
+                }
+            }
+        }
+        if ready == 0 && !Self::poll_expired(deadline) {
+            return self.poll_wait(events, deadline, sigmask);
+        }
+        for (set, result) in [readfds, writefds, exceptfds].iter().zip(results) {
+            if set.is_valid() {
+                set.transfer_vec(words).copy_from_slice(&result);
+            }
+        }
+        self.inner.borrow_mut().context.x[10] = ready;
+        Ok(())
+    }
+
+    // 创建epoll实例
+    pub fn sys_epoll_create1(&self, flags: usize) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        if flags & !EPOLL_CLOEXEC != 0 {
+            inner.context.x[10] = EINVAL;
+            return Ok(());
+        }
+        let fd = inner.process.borrow_mut().fd_table.push(FileDesc::new(EpollFile::new()));
+        inner.context.x[10] = fd;
+        Ok(())
+    }
+
+    // 添加、修改或者删除epoll监听的文件
+    pub fn sys_epoll_ctl(&self, epfd: usize, op: usize, fd: usize, event: UserAddr<EpollEvent>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let mut process = inner.process.borrow_mut();
+        let epoll = process.fd_table.get(epfd)?.downcast::<EpollFile>();
+        let file = process.fd_table.get(fd)?.file.clone();
+        drop(process);
+        let epoll = match epoll {
+            Ok(epoll) if epfd != fd => epoll,
+            _ => {
+                inner.context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        // 监听的epoll不能包含当前的epoll
+        if op == EPOLL_CTL_ADD && file.clone().downcast::<EpollFile>().map_or(false, |x| x.contains(&epoll)) {
+            inner.context.x[10] = ELOOP;
+            return Ok(());
+        }
+        let mut items = epoll.0.borrow_mut();
+        let ret = match op {
+            EPOLL_CTL_ADD if items.contains_key(&fd) => EEXIST,
+            EPOLL_CTL_ADD => {
+                let event = event.transfer();
+                items.insert(fd, EpollItem::new(&file, event.events, event.data));
+                0
+            }
+            EPOLL_CTL_MOD => match items.get_mut(&fd) {
+                Some(item) => {
+                    let event = event.transfer();
+                    *item = EpollItem::new(&file, event.events, event.data);
+                    0
+                }
+                None => ENOENT
+            }
+            EPOLL_CTL_DEL => match items.remove(&fd) {
+                Some(_) => 0,
+                None => ENOENT
+            }
+            _ => EINVAL
+        };
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
+    // 等待epoll监听的文件就绪 timeout单位为ms 小于0时一直等待
+    pub fn sys_epoll_pwait(&self, epfd: usize, events: UserAddr<EpollEvent>, max_events: usize, timeout: isize,
+            sigmask: UserAddr<SigSet>, sigsetsize: usize) -> Result<(), RuntimeError> {
+        let epoll = self.get_process().borrow_mut().fd_table.get(epfd)?.downcast::<EpollFile>();
+        let epoll = match epoll {
+            Ok(epoll) if (max_events as isize) > 0 && (!sigmask.is_valid() || sigsetsize == size_of::<SigSet>()) => epoll,
+            _ => {
+                self.inner.borrow_mut().context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        let timeout = match timeout < 0 {
+            true => None,
+            false => Some(timeout as usize * 1000)
+        };
+        let deadline = self.poll_deadline(timeout);
+        let ready = epoll.ready_events(max_events, |file| self.poll_file(file));
+        if ready.is_empty() && !Self::poll_expired(deadline) {
+            let mut wait_events = epoll.wait_events();
+            wait_events.push(epoll.event());
+            return self.poll_wait(wait_events, deadline, sigmask);
+        }
+        events.transfer_vec(ready.len()).copy_from_slice(&ready);
+        self.inner.borrow_mut().context.x[10] = ready.len();
+        Ok(())
+    }
+
+    // 创建eventfd
+    pub fn sys_eventfd2(&self, initval: usize, flags: usize) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        if flags & !(EFD_SEMAPHORE | EFD_NONBLOCK | EFD_CLOEXEC) != 0 {
+            inner.context.x[10] = EINVAL;
+            return Ok(());
+        }
+        let mut file = FileDesc::new(Rc::new(EventFd::new(initval as u32 as u64, flags & EFD_SEMAPHORE != 0)));
+        file.flags = OpenFlags::RDWR;
+        if flags & EFD_NONBLOCK != 0 {
+            file.flags |= OpenFlags::NONBLOCK;
+        }
+        let fd = inner.process.borrow_mut().fd_table.push(file);
+        inner.context.x[10] = fd;
+        Ok(())
+    }
+}
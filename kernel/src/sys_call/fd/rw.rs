@@ -1,23 +1,34 @@
-use crate::{task::{task::Task, fd_table::IoVec}, memory::addr::UserAddr, runtime_err::RuntimeError, fs::file::FileOP};
+use crate::{task::{task::Task, fd_table::IoVec, signalfd::SignalFd, task_scheduler::{wait_current, signal_task}, wait_queue::WaitEvent, signal::{SigInfo, Signal, SI_KERNEL}}, memory::addr::UserAddr, runtime_err::RuntimeError, fs::file::FileOP, sys_call::{OpenFlags, consts::{EAGAIN, EINVAL, EPIPE}}};
 
 impl Task {
     // 读取
     pub fn sys_read(&self, fd: usize, buf_ptr: UserAddr<u8>, count: usize) -> Result<(), RuntimeError> {
         debug!("sys_read, fd: {}, buf_ptr: {:#x}, count: {}", fd, buf_ptr.bits(), count);
         let buf = buf_ptr.transfer_vec(count);
-        let mut inner = self.inner.borrow_mut();
+        let inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
 
         // 判断文件描述符是否存在
         let reader = process.fd_table.get(fd)?;
+        // signalfd需要读取任务的信号队列
+        if let Ok(signalfd) = reader.downcast::<SignalFd>() {
+            drop(process);
+            drop(inner);
+            return self.read_signalfd(signalfd, buf);
+        }
         let value = if reader.readable() {
             reader.read(buf)
         } else {
             usize::MAX
         };
+        let event = reader.block_event(value);
         drop(process);
+        drop(inner);
+        if let Some(event) = event {
+            return self.wait_file(event);
+        }
         debug!("read_size = {}", value);
-        inner.context.x[10] = value;
+        self.inner.borrow_mut().context.x[10] = value;
         Ok(())
     }
 
@@ -25,44 +36,68 @@ impl Task {
     pub fn sys_write(&self, fd: usize, buf_ptr: UserAddr<u8>, count: usize) -> Result<(), RuntimeError> {
         debug!("write fd: {} buf_ptr: {:#x} count: {}", fd, buf_ptr.bits(), count);
         let buf = buf_ptr.transfer_vec(count);
-        let mut inner = self.inner.borrow_mut();
+        let inner = self.inner.borrow_mut();
+        // 重新执行时跳过已经写入管道的部分
+        let done = inner.pipe_written;
         let mut process = inner.process.borrow_mut();
         
         // 判断文件描述符是否存在
         let writer = process.fd_table.get(fd)?;
         let value = if writer.writeable() {
-            writer.write(buf, buf.len())
+            writer.write(&buf[done..], buf.len() - done)
         } else {
             usize::MAX
         };
+        let event = writer.block_event(value).or(writer.partial_event(value, buf.len() - done));
         drop(process);
-        inner.context.x[10] = value;
-        Ok(())
+        drop(inner);
+        self.finish_write(done, value, event)
     }
     // 写入
     pub fn sys_writev(&self, fd: usize, iov: UserAddr<IoVec>, iovcnt: usize) -> Result<(), RuntimeError> {
         let iov_vec = iov.transfer_vec(iovcnt);
         
-        let mut inner = self.inner.borrow_mut();
+        let inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
         
+        let done = inner.pipe_written;
+        let total: usize = iov_vec.iter().map(|x| x.iov_len).sum();
         let fd = process.fd_table.get(fd)?;
         let mut cnt = 0;
+        let mut skip = done;
         for i in iov_vec {
             // let buf = get_buf_from_phys_addr(i.iov_base.translate(process.pmm.clone()), 
             //     i.iov_len);
+            // 跳过已经写入管道的部分
+            let offset = skip.min(i.iov_len);
+            skip -= offset;
+            if offset == i.iov_len {
+                continue;
+            }
             let buf = i.iov_base.transfer_vec(i.iov_len);
-            cnt += fd.write(buf, i.iov_len);
+            let ret = fd.write(&buf[offset..], i.iov_len - offset);
+            // 出错时返回已经写入的长度 没有写入时返回错误码
+            if (ret as isize) < 0 {
+                if cnt == 0 {
+                    cnt = ret;
+                }
+                break;
+            }
+            cnt += ret;
+            if ret < i.iov_len - offset {
+                break;
+            }
         }
+        let event = fd.block_event(cnt).or(fd.partial_event(cnt, total - done));
         drop(process);
-        inner.context.x[10] = cnt;
-        Ok(())
+        drop(inner);
+        self.finish_write(done, cnt, event)
     }
 
     pub fn sys_readv(&self, fd: usize, iov: UserAddr<IoVec>, iovcnt: usize) -> Result<(), RuntimeError> {
         let iov_vec = iov.transfer_vec(iovcnt);
 
-        let mut inner = self.inner.borrow_mut();
+        let inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
         
         let fd = process.fd_table.get(fd)?;
@@ -71,10 +106,26 @@ impl Task {
             // let buf = get_buf_from_phys_addr(i.iov_base, 
                 // i.iov_len);
             let buf = i.iov_base.transfer_vec(i.iov_len);
-            cnt += fd.read(buf);
+            let ret = fd.read(buf);
+            // 出错时返回已经读取的长度 没有读取时返回错误码
+            if (ret as isize) < 0 {
+                if cnt == 0 {
+                    cnt = ret;
+                }
+                break;
+            }
+            cnt += ret;
+            if ret < i.iov_len {
+                break;
+            }
         }
+        let event = fd.block_event(cnt);
         drop(process);
-        inner.context.x[10] = cnt;
+        drop(inner);
+        if let Some(event) = event {
+            return self.wait_file(event);
+        }
+        self.inner.borrow_mut().context.x[10] = cnt;
         Ok(())
     }
 
@@ -104,6 +155,25 @@ impl Task {
         Ok(())
     }
 
+    // 修改文件大小 只能修改以写入方式打开的文件
+    pub fn sys_ftruncate(&self, fd: usize, len: usize) -> Result<(), RuntimeError> {
+        debug!("ftruncate fd: {} len: {}", fd, len);
+        let mut inner = self.inner.borrow_mut();
+        let mut process = inner.process.borrow_mut();
+        let file = process.fd_table.get(fd)?;
+        let ret = if (len as isize) < 0 || !file.flags.intersects(OpenFlags::WRONLY | OpenFlags::RDWR) {
+            EINVAL
+        } else {
+            match file.file.truncate(len) {
+                Ok(()) => 0,
+                Err(err) => err
+            }
+        };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
     pub fn sys_sendfile(&self, out_fd: usize, in_fd: usize, offset_ptr: usize, count: usize) -> Result<(), RuntimeError> {
         debug!("out_fd: {}  in_fd: {}  offset_ptr: {:#x}   count: {}", out_fd, in_fd, offset_ptr, count);
         let mut inner = self.inner.borrow_mut();
@@ -122,4 +192,40 @@ impl Task {
         Ok(())
     }
 
-}
\ No newline at end of file
+    // 阻塞模式的读写需要等待时挂起任务 回退pc 唤醒后重新执行系统调用
+    pub fn wait_file(&self, event: WaitEvent) -> Result<(), RuntimeError> {
+        self.inner.borrow_mut().context.sepc -= 4;
+        wait_current(event, None);
+        Ok(())
+    }
+
+    // 结束写入 done为之前已经写入管道的长度 ret为本次写入的结果
+    // 阻塞模式的管道没有完全写入时记录已经写入的长度并等待 读端关闭或者被信号打断时返回已经写入的长度
+    fn finish_write(&self, done: usize, ret: usize, event: Option<WaitEvent>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(event) = event {
+            if ret != EAGAIN {
+                inner.pipe_written = done + ret;
+            }
+            drop(inner);
+            return self.wait_file(event);
+        }
+        inner.pipe_written = 0;
+        drop(inner);
+        self.check_broken_pipe(ret);
+        let ret = match (ret as isize) < 0 {
+            true if done > 0 => done,
+            true => ret,
+            false => done + ret
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 写入读端已经关闭的管道时向当前线程发送SIGPIPE
+    pub fn check_broken_pipe(&self, ret: usize) {
+        if ret == EPIPE {
+            signal_task(self.pid, self.tid, SigInfo::new(Signal::SIGPIPE as usize, SI_KERNEL));
+        }
+    }
+}
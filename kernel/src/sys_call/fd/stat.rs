@@ -1,50 +1,30 @@
-use crate::{task::{task::Task, fd_table::FD_NULL}, memory::addr::UserAddr, fs::{file::{Kstat, FileType}, filetree::INode, StatFS}, runtime_err::RuntimeError};
+use crate::{task::{task::Task, fd_table::FD_NULL}, memory::addr::UserAddr, runtime_err::RuntimeError};
+use crate::fs::{file::{Kstat, FileType, File}, filetree::INode, vfs::Metadata, mount, StatFS};
+
+// 不跟随最后一个符号链接
+const AT_SYMLINK_NOFOLLOW: usize = 0x100;
 
 impl Task {
     pub fn sys_fstat(&self, fd: usize, buf_ptr: UserAddr<Kstat>) -> Result<(), RuntimeError> {
         debug!("sys_fstat: {}", fd);
         let kstat = buf_ptr.transfer();
         let mut inner = self.inner.borrow_mut();
-        let process = inner.process.borrow_mut();
+        let mut process = inner.process.borrow_mut();
 
-        // // 判断文件描述符是否存在
-        let inode = process.fd_table.get_file(fd)?;
-        let inode = inode.get_inode();
-        let _inode = inode.0.borrow_mut();
-        kstat.st_dev = 1;
-        kstat.st_ino = 1;
-        kstat.st_mode = 0;
-        kstat.st_nlink = 1;
-        kstat.st_uid = 0;
-        kstat.st_gid = 0;
-        kstat.st_rdev = 0;
-        kstat.__pad = 0;
-        kstat.st_blksize = 512; // 磁盘扇区大小
-        // kstat.st_size = inode.size as u64;
-        // kstat.st_blocks = ((inode.size - 1 + 512) / 512) as u64;
-        // kstat.st_atime_sec = inode.st_atime_sec;
-        // kstat.st_atime_nsec = inode.st_atime_nsec;
-        // kstat.st_mtime_sec = inode.st_mtime_sec;
-        // kstat.st_mtime_nsec = inode.st_mtime_nsec;
-        // kstat.st_ctime_sec = inode.st_ctime_sec;
-        // kstat.st_ctime_nsec = inode.st_ctime_nsec;
-
-        // debug
-        kstat.st_size = 0;
-        kstat.st_blocks = 0;
-        kstat.st_atime_sec  = 0;
-        kstat.st_atime_nsec = 0;
-        kstat.st_mtime_sec  = 0;
-        kstat.st_mtime_nsec = 0;
-        kstat.st_ctime_sec  = 0;
-        kstat.st_ctime_nsec = 0;
+        // 文件树中的文件使用节点的元数据 管道和标准输入输出使用默认值
+        let file = process.fd_table.get(fd)?.file.clone();
+        let metadata = match file.clone().downcast::<File>() {
+            Ok(file) => file.get_inode().metadata(),
+            Err(_) => Metadata::new(file.file_type(), 0)
+        };
+        kstat.fill(file.file_type(), &metadata);
         drop(process);
         inner.context.x[10] = 0;
         Ok(())
     }
 
     // 获取文件信息
-    pub fn sys_fstatat(&self, dir_fd: usize, filename: UserAddr<u8>, stat_ptr: UserAddr<Kstat>, _flags: usize) -> Result<(), RuntimeError> {
+    pub fn sys_fstatat(&self, dir_fd: usize, filename: UserAddr<u8>, stat_ptr: UserAddr<Kstat>, flags: usize) -> Result<(), RuntimeError> {
         let filename = filename.read_string();
         let kstat = stat_ptr.transfer();
         debug!("sys_fstatat: dir_fd {:#x}, filename: {}, filename_len: {}", dir_fd, filename, filename.len());
@@ -52,49 +32,54 @@ impl Task {
         let mut inner = self.inner.borrow_mut();
         let process = inner.process.borrow_mut();
 
-        if filename != "/dev/null" {
-            // 判断文件描述符是否存在
-            let file = if dir_fd == FD_NULL {
-                None
-            } else {
-                let file = process.fd_table.get_file(dir_fd)?;
-                Some(file.get_inode())
-            };
+        // 判断文件描述符是否存在
+        let current = if dir_fd == FD_NULL {
+            None
+        } else {
+            let file = process.fd_table.get_file(dir_fd)?;
+            Some(file.get_inode())
+        };
+
+        // 设置AT_SYMLINK_NOFOLLOW时获取符号链接本身的信息
+        let inode = match flags & AT_SYMLINK_NOFOLLOW {
+            0 => INode::get(current, &filename)?,
+            _ => INode::get_nofollow(current, &filename)?
+        };
+        kstat.fill(inode.get_file_type(), &inode.metadata());
+        drop(process);
+        inner.context.x[10] = 0;
+        Ok(())
+    }
 
-            let inode = INode::get(file, &filename)?;
-            let inode = inode.0.borrow_mut();
-            kstat.st_dev = 1;
-            kstat.st_ino = 1;
-            // kstat_ptr.st_mode = 0;
-            if inode.file_type == FileType::Directory {
-                kstat.st_mode = 0o40000;
-            } else {
-                kstat.st_mode = 0;
-            }
-            // kstat.st_nlink = inode.nlinkes as u32;
-            // kstat.st_uid = 0;
-            // kstat.st_gid = 0;
-            // kstat.st_rdev = 0;
-            // kstat.__pad = 0;
-            // kstat.st_size = inode.size as u64;
-            // kstat.st_blksize = 512;
-            // kstat.st_blocks = ((inode.size - 1 + 512) / 512) as u64;
-            // kstat.st_atime_sec = inode.st_atime_sec;
-            // kstat.st_atime_nsec = inode.st_atime_nsec;
-            // kstat.st_mtime_sec = inode.st_mtime_sec;
-            // kstat.st_mtime_nsec = inode.st_mtime_nsec;
-            // kstat.st_ctime_sec = inode.st_ctime_sec;
-            // kstat.st_ctime_nsec = inode.st_ctime_nsec;
-            drop(process);
-            inner.context.x[10] = 0;
-            Ok(())
+    // 修改打开的文件的权限
+    pub fn sys_fchmod(&self, fd: usize, mode: usize) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+        let file = process.fd_table.get_file(fd)?;
+        // 不保存权限的文件系统忽略
+        let _ = file.get_inode().set_mode((mode & 0o7777) as u32);
+        drop(process);
+        inner.context.x[10] = 0;
+        Ok(())
+    }
+
+    // 修改文件的权限
+    pub fn sys_fchmodat(&self, dir_fd: usize, filename: UserAddr<u8>, mode: usize, _flags: usize) -> Result<(), RuntimeError> {
+        let filename = filename.read_string();
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+        debug!("chmod {} mode: {:#o}", filename, mode);
+
+        let current = if dir_fd == FD_NULL {
+            None
         } else {
-            // kstat.
-            kstat.st_mode = 0o20000;
-            drop(process);
-            inner.context.x[10] = 0;
-            Ok(())
-        }
+            Some(process.fd_table.get_file(dir_fd)?.get_inode())
+        };
+        let inode = INode::get(current, &filename)?;
+        let _ = inode.set_mode((mode & 0o7777) as u32);
+        drop(process);
+        inner.context.x[10] = 0;
+        Ok(())
     }
 
     // 获取文件信息
@@ -122,6 +107,9 @@ impl Task {
             buf[pos] = match inode.get_file_type() {
                 FileType::File => 8,
                 FileType::Directory => 4,
+                FileType::Pipeline => 1,
+                FileType::Socket => 12,
+                FileType::SymLink => 10,
                 _ => 0
             };
             pos += 1;
@@ -142,21 +130,35 @@ impl Task {
         Ok(())
     }
 
-    pub fn sys_statfs(&self, _fd: usize, buf_ptr: UserAddr<StatFS>) -> Result<(), RuntimeError> {
+    // 获取路径所在文件系统的信息
+    pub fn sys_statfs(&self, path: UserAddr<u8>, buf_ptr: UserAddr<StatFS>) -> Result<(), RuntimeError> {
+        let path = path.read_string();
         let buf = buf_ptr.transfer();
-        
-        buf.f_type = 32;
-        buf.f_bsize = 512;
-        buf.f_blocks = 80;
-        buf.f_bfree = 40;
-        buf.f_bavail = 0;
-        buf.f_files = 32;
-        buf.f_ffree = 0;
-        buf.f_fsid = 32;
-        buf.f_namelen = 20;
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+        // 相对路径从工作目录开始查找
+        let current = match path.starts_with('/') {
+            true => None,
+            false => Some(process.workspace.clone())
+        };
+        drop(process);
 
+        let node = INode::get(current, &path)?;
+        *buf = mount::statfs(&node);
+        inner.context.x[10] = 0;
+        Ok(())
+    }
+
+    // 获取打开的文件所在文件系统的信息
+    pub fn sys_fstatfs(&self, fd: usize, buf_ptr: UserAddr<StatFS>) -> Result<(), RuntimeError> {
+        let buf = buf_ptr.transfer();
         let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+        let file = process.fd_table.get_file(fd)?;
+        drop(process);
+
+        *buf = mount::statfs(&file.get_inode());
         inner.context.x[10] = 0;
         Ok(())
-    }    
-}
\ No newline at end of file
+    }
+}
@@ -0,0 +1,521 @@
+use alloc::vec::Vec;
+
+use crate::interrupt::timer::TimeSpec;
+use crate::interrupt::timer::get_time_sec;
+use crate::interrupt::timer::get_time_us;
+use crate::memory::addr::PAGE_SIZE;
+use crate::memory::addr::UserAddr;
+use crate::memory::addr::get_pages_num;
+use crate::memory::page_table::PTEFlags;
+use crate::memory::vma::Vma;
+use crate::memory::vma::VmaType;
+use crate::runtime_err::RuntimeError;
+use crate::sys_call::consts::{E2BIG, EAGAIN, EINVAL, ENOMEM, ENOMSG, ERANGE};
+use crate::task::ipc::*;
+use crate::task::task::Task;
+use crate::task::task_scheduler::wait_current;
+use crate::task::wait_queue::WaitEvent;
+
+// ctl命令中的IPC_64标志 使用64位的结构
+const IPC_64: usize = 0x100;
+
+// shmctl获取的共享内存段信息
+#[repr(C)]
+pub struct ShmidDs {
+    pub perm: IpcPerm,
+    pub segsz: usize,
+    pub atime: usize,
+    pub dtime: usize,
+    pub ctime: usize,
+    pub cpid: i32,
+    pub lpid: i32,
+    pub nattch: usize,
+    _unused: [usize; 2]
+}
+
+// semctl获取的信号量集合信息
+#[repr(C)]
+pub struct SemidDs {
+    pub perm: IpcPerm,
+    pub otime: usize,
+    pub ctime: usize,
+    pub nsems: usize,
+    _unused: [usize; 2]
+}
+
+// msgctl获取的消息队列信息
+#[repr(C)]
+pub struct MsqidDs {
+    pub perm: IpcPerm,
+    pub stime: usize,
+    pub rtime: usize,
+    pub ctime: usize,
+    pub cbytes: usize,
+    pub qnum: usize,
+    pub qbytes: usize,
+    pub lspid: i32,
+    pub lrpid: i32,
+    _unused: [usize; 2]
+}
+
+impl Task {
+    // 获取或者创建共享内存段
+    pub fn sys_shmget(&self, key: usize, size: usize, flags: usize) -> Result<(), RuntimeError> {
+        let mut ns = IPC_NS.lock();
+        let pid = self.pid;
+        let ret = match ns.shm.get_or_create(key, flags, || {
+            if size == 0 {
+                return Err(EINVAL);
+            }
+            ShmSegment::new(key, size, flags, pid).map_err(|_| ENOMEM)
+        }) {
+            // 已经存在的段不能小于请求的大小
+            Ok((id, false)) if ns.shm.get(id).map_or(false, |x| x.size < size) => EINVAL,
+            Ok((id, _)) => id,
+            Err(err) => err
+        };
+        drop(ns);
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 将共享内存段映射到进程 返回映射的地址
+    pub fn sys_shmat(&self, shmid: usize, addr: usize, flags: usize) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let (memory, size) = match IPC_NS.lock().shm.get(shmid) {
+            Some(segment) => (segment.memory.clone(), segment.size),
+            None => {
+                inner.context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        let len = get_pages_num(size) * PAGE_SIZE;
+        let mut process = inner.process.borrow_mut();
+        // SHM_RND时向下对齐 否则地址需要对齐
+        let addr = match flags & SHM_RND != 0 {
+            true => addr / PAGE_SIZE * PAGE_SIZE,
+            false => addr
+        };
+        let start = if addr == 0 {
+            process.vmas.find_free(len)
+        } else if addr % PAGE_SIZE != 0 {
+            None
+        } else if flags & SHM_REMAP != 0 {
+            process.unmap(addr, addr + len);
+            Some(addr)
+        } else if process.vmas.is_free(addr, addr + len) {
+            Some(addr)
+        } else {
+            None
+        };
+        let start = match start {
+            Some(start) => start,
+            None => {
+                drop(process);
+                inner.context.x[10] = match addr {
+                    0 => ENOMEM,
+                    _ => EINVAL
+                };
+                return Ok(());
+            }
+        };
+        let mut pte_flags = match flags & SHM_RDONLY != 0 {
+            true => PTEFlags::R,
+            false => PTEFlags::R | PTEFlags::W
+        };
+        if flags & SHM_EXEC != 0 {
+            pte_flags |= PTEFlags::X;
+        }
+        let mut vma = Vma::new(start, start + len, pte_flags, VmaType::Shm(memory.clone(), 0));
+        vma.shared = true;
+        process.pmm.populate(&vma)?;
+        process.vmas.push(vma);
+        process.shm_attaches.push(ShmAttach { id: shmid, addr: start, memory });
+        drop(process);
+        let mut ns = IPC_NS.lock();
+        ns.shm_attach(shmid);
+        if let Some(segment) = ns.shm.get_mut(shmid) {
+            segment.atime = get_time_sec();
+            segment.lpid = self.pid;
+        }
+        drop(ns);
+        inner.context.x[10] = start;
+        Ok(())
+    }
+
+    // 取消映射在addr处的共享内存段
+    pub fn sys_shmdt(&self, addr: usize) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let mut process = inner.process.borrow_mut();
+        let attach = match process.shm_attaches.iter().position(|x| x.addr == addr) {
+            Some(index) => process.shm_attaches.remove(index),
+            None => {
+                drop(process);
+                inner.context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        // 移除这次映射的所有区域
+        let ranges: Vec<(usize, usize)> = process.vmas.0.iter()
+            .filter(|x| attach.owns(x))
+            .map(|x| (x.start, x.end)).collect();
+        for (start, end) in ranges {
+            process.unmap(start, end);
+        }
+        drop(process);
+        let mut ns = IPC_NS.lock();
+        if let Some(segment) = ns.shm.get_mut(attach.id) {
+            segment.dtime = get_time_sec();
+            segment.lpid = self.pid;
+        }
+        ns.shm_detach(attach.id);
+        drop(ns);
+        inner.context.x[10] = 0;
+        Ok(())
+    }
+
+    // 控制共享内存段
+    pub fn sys_shmctl(&self, shmid: usize, cmd: usize, buf: usize) -> Result<(), RuntimeError> {
+        let mut ns = IPC_NS.lock();
+        let segment = match ns.shm.get_mut(shmid) {
+            Some(segment) => segment,
+            None => {
+                drop(ns);
+                self.inner.borrow_mut().context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        let ret = match cmd & !IPC_64 {
+            IPC_STAT => {
+                let ds = UserAddr::<ShmidDs>::from(buf).transfer();
+                ds.perm = segment.perm;
+                ds.segsz = segment.size;
+                ds.atime = segment.atime;
+                ds.dtime = segment.dtime;
+                ds.ctime = segment.ctime;
+                ds.cpid = segment.cpid as i32;
+                ds.lpid = segment.lpid as i32;
+                ds.nattch = segment.nattch;
+                0
+            }
+            IPC_SET => {
+                let ds = UserAddr::<ShmidDs>::from(buf).transfer();
+                segment.perm.mode = (segment.perm.mode & !0o777) | (ds.perm.mode & 0o777);
+                segment.ctime = get_time_sec();
+                0
+            }
+            // 标记删除 最后一个映射取消后释放
+            IPC_RMID => {
+                segment.perm.mode |= SHM_DEST;
+                segment.perm.key = IPC_PRIVATE as i32;
+                ns.shm.remove_key(shmid);
+                ns.release_shm();
+                0
+            }
+            _ => EINVAL
+        };
+        drop(ns);
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 获取或者创建信号量集合
+    pub fn sys_semget(&self, key: usize, nsems: usize, flags: usize) -> Result<(), RuntimeError> {
+        let mut ns = IPC_NS.lock();
+        let ret = match ns.sem.get_or_create(key, flags, || {
+            if nsems == 0 || nsems > SEMMSL {
+                return Err(EINVAL);
+            }
+            Ok(SemSet::new(key, nsems, flags))
+        }) {
+            // 已经存在的集合不能少于请求的数量
+            Ok((id, false)) if ns.sem.get(id).map_or(false, |x| x.sems.len() < nsems) => EINVAL,
+            Ok((id, _)) => id,
+            Err(err) => err
+        };
+        drop(ns);
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 执行信号量操作
+    pub fn sys_semop(&self, semid: usize, sops: UserAddr<SemBuf>, nsops: usize) -> Result<(), RuntimeError> {
+        self.sys_semtimedop(semid, sops, nsops, 0.into())
+    }
+
+    // 执行信号量操作 需要等待时阻塞到可以执行或者超时
+    pub fn sys_semtimedop(&self, semid: usize, sops: UserAddr<SemBuf>, nsops: usize, timeout: UserAddr<TimeSpec>) -> Result<(), RuntimeError> {
+        if nsops == 0 || nsops > SEMOPM {
+            self.inner.borrow_mut().context.x[10] = match nsops {
+                0 => EINVAL,
+                _ => E2BIG
+            };
+            return Ok(());
+        }
+        let ops = sops.transfer_vec(nsops).to_vec();
+        let mut ns = IPC_NS.lock();
+        let set = match ns.sem.get_mut(semid) {
+            Some(set) => set,
+            None => {
+                drop(ns);
+                self.inner.borrow_mut().context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        set.waiters.remove(&(self.pid, self.tid));
+        let ret = match set.apply(&ops, self.pid) {
+            Ok(()) => {
+                let undo = self.get_process().borrow().sem_undo.clone();
+                undo.borrow_mut().record(semid, &ops);
+                0
+            }
+            // 等待其他任务修改信号量 超时后返回EAGAIN
+            Err(EAGAIN) if ops.iter().all(|x| x.sem_flg as usize & IPC_NOWAIT == 0) => {
+                let timeout = match timeout.is_valid() {
+                    true => Some(get_time_us() + timeout.transfer().to_us()),
+                    false => None
+                };
+                set.waiters.insert((self.pid, self.tid), ops);
+                drop(ns);
+                self.inner.borrow_mut().context.x[10] = EAGAIN;
+                wait_current(WaitEvent::Sem(semid), timeout);
+                return Ok(());
+            }
+            Err(err) => err
+        };
+        drop(ns);
+        // 信号量发生变化 唤醒等待的任务重新尝试
+        if ret == 0 {
+            ipc_wake(WaitEvent::Sem(semid));
+        }
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 控制信号量集合 arg为数值或者用户地址
+    pub fn sys_semctl(&self, semid: usize, semnum: usize, cmd: usize, arg: usize) -> Result<(), RuntimeError> {
+        let mut ns = IPC_NS.lock();
+        let set = match ns.sem.get_mut(semid) {
+            Some(set) => set,
+            None => {
+                drop(ns);
+                self.inner.borrow_mut().context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        let cmd = cmd & !IPC_64;
+        // 单个信号量的操作需要检查编号
+        if matches!(cmd, GETPID | GETVAL | GETNCNT | GETZCNT | SETVAL) && semnum >= set.sems.len() {
+            drop(ns);
+            self.inner.borrow_mut().context.x[10] = EINVAL;
+            return Ok(());
+        }
+        let mut changed = false;
+        let ret = match cmd {
+            IPC_STAT => {
+                let ds = UserAddr::<SemidDs>::from(arg).transfer();
+                ds.perm = set.perm;
+                ds.otime = set.otime;
+                ds.ctime = set.ctime;
+                ds.nsems = set.sems.len();
+                0
+            }
+            IPC_SET => {
+                let ds = UserAddr::<SemidDs>::from(arg).transfer();
+                set.perm.mode = ds.perm.mode & 0o777;
+                set.ctime = get_time_sec();
+                0
+            }
+            IPC_RMID => {
+                ns.sem.remove(semid);
+                0
+            }
+            GETPID => set.sems[semnum].pid,
+            GETVAL => set.sems[semnum].val as usize,
+            GETNCNT => set.wait_count(semid, semnum, false),
+            GETZCNT => set.wait_count(semid, semnum, true),
+            GETALL => {
+                let vals = UserAddr::<u16>::from(arg).transfer_vec(set.sems.len());
+                for (val, sem) in vals.iter_mut().zip(&set.sems) {
+                    *val = sem.val as u16;
+                }
+                0
+            }
+            SETVAL => match arg as i32 {
+                val if !(0..=SEMVMX).contains(&val) => ERANGE,
+                val => {
+                    set.sems[semnum] = Sem { val, pid: self.pid };
+                    set.ctime = get_time_sec();
+                    changed = true;
+                    0
+                }
+            }
+            SETALL => {
+                let vals = UserAddr::<u16>::from(arg).transfer_vec(set.sems.len());
+                match vals.iter().any(|x| *x as i32 > SEMVMX) {
+                    true => ERANGE,
+                    false => {
+                        for (sem, val) in set.sems.iter_mut().zip(vals.iter()) {
+                            *sem = Sem { val: *val as i32, pid: self.pid };
+                        }
+                        set.ctime = get_time_sec();
+                        changed = true;
+                        0
+                    }
+                }
+            }
+            _ => EINVAL
+        };
+        drop(ns);
+        // 设置信号量的值后清除所有进程对应的撤销记录
+        if changed {
+            let undo = self.get_process().borrow().sem_undo.clone();
+            undo.borrow_mut().0.retain(|(id, num), _| *id != semid || (cmd == SETVAL && *num != semnum));
+        }
+        match cmd {
+            IPC_RMID => ipc_wake_removed(WaitEvent::Sem(semid)),
+            _ if changed => ipc_wake(WaitEvent::Sem(semid)),
+            _ => {}
+        }
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 获取或者创建消息队列
+    pub fn sys_msgget(&self, key: usize, flags: usize) -> Result<(), RuntimeError> {
+        let ret = match IPC_NS.lock().msg.get_or_create(key, flags, || Ok(MsgQueue::new(key, flags))) {
+            Ok((id, _)) => id,
+            Err(err) => err
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 发送消息 msgp指向消息类型和内容 队列已满时阻塞
+    pub fn sys_msgsnd(&self, msqid: usize, msgp: usize, msgsz: usize, flags: usize) -> Result<(), RuntimeError> {
+        let mut ns = IPC_NS.lock();
+        let queue = match ns.msg.get_mut(msqid) {
+            Some(queue) => queue,
+            None => {
+                drop(ns);
+                self.inner.borrow_mut().context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        let mtype = *UserAddr::<isize>::from(msgp).transfer();
+        if mtype <= 0 || msgsz > MSGMAX {
+            drop(ns);
+            self.inner.borrow_mut().context.x[10] = EINVAL;
+            return Ok(());
+        }
+        // 队列已满时等待接收
+        if queue.bytes + msgsz > queue.qbytes {
+            drop(ns);
+            self.inner.borrow_mut().context.x[10] = EAGAIN;
+            if flags & IPC_NOWAIT == 0 {
+                wait_current(WaitEvent::Msg(msqid), None);
+            }
+            return Ok(());
+        }
+        let data = UserAddr::<u8>::from(msgp + 8).transfer_vec(msgsz).to_vec();
+        queue.messages.push_back((mtype as usize, data));
+        queue.bytes += msgsz;
+        queue.lspid = self.pid;
+        queue.stime = get_time_sec();
+        drop(ns);
+        ipc_wake(WaitEvent::Msg(msqid));
+        self.inner.borrow_mut().context.x[10] = 0;
+        Ok(())
+    }
+
+    // 接收消息 返回消息内容的长度 没有符合条件的消息时阻塞
+    pub fn sys_msgrcv(&self, msqid: usize, msgp: usize, msgsz: usize, msgtyp: isize, flags: usize) -> Result<(), RuntimeError> {
+        let mut ns = IPC_NS.lock();
+        let queue = match ns.msg.get_mut(msqid) {
+            Some(queue) => queue,
+            None => {
+                drop(ns);
+                self.inner.borrow_mut().context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        let index = match queue.find(msgtyp, flags & MSG_EXCEPT != 0) {
+            Some(index) => index,
+            None => {
+                drop(ns);
+                self.inner.borrow_mut().context.x[10] = ENOMSG;
+                if flags & IPC_NOWAIT == 0 {
+                    wait_current(WaitEvent::Msg(msqid), None);
+                }
+                return Ok(());
+            }
+        };
+        // 消息过长时不取出 MSG_NOERROR时截断
+        if queue.messages[index].1.len() > msgsz && flags & MSG_NOERROR == 0 {
+            drop(ns);
+            self.inner.borrow_mut().context.x[10] = E2BIG;
+            return Ok(());
+        }
+        let (mtype, data) = queue.messages.remove(index).unwrap();
+        queue.bytes -= data.len();
+        queue.lrpid = self.pid;
+        queue.rtime = get_time_sec();
+        drop(ns);
+        let len = data.len().min(msgsz);
+        *UserAddr::<usize>::from(msgp).transfer() = mtype;
+        UserAddr::<u8>::from(msgp + 8).transfer_vec(len).copy_from_slice(&data[..len]);
+        ipc_wake(WaitEvent::Msg(msqid));
+        self.inner.borrow_mut().context.x[10] = len;
+        Ok(())
+    }
+
+    // 控制消息队列
+    pub fn sys_msgctl(&self, msqid: usize, cmd: usize, buf: usize) -> Result<(), RuntimeError> {
+        let mut ns = IPC_NS.lock();
+        let queue = match ns.msg.get_mut(msqid) {
+            Some(queue) => queue,
+            None => {
+                drop(ns);
+                self.inner.borrow_mut().context.x[10] = EINVAL;
+                return Ok(());
+            }
+        };
+        let cmd = cmd & !IPC_64;
+        let ret = match cmd {
+            IPC_STAT => {
+                let ds = UserAddr::<MsqidDs>::from(buf).transfer();
+                ds.perm = queue.perm;
+                ds.stime = queue.stime;
+                ds.rtime = queue.rtime;
+                ds.ctime = queue.ctime;
+                ds.cbytes = queue.bytes;
+                ds.qnum = queue.messages.len();
+                ds.qbytes = queue.qbytes;
+                ds.lspid = queue.lspid as i32;
+                ds.lrpid = queue.lrpid as i32;
+                0
+            }
+            IPC_SET => {
+                let ds = UserAddr::<MsqidDs>::from(buf).transfer();
+                queue.perm.mode = ds.perm.mode & 0o777;
+                queue.qbytes = ds.qbytes;
+                queue.ctime = get_time_sec();
+                0
+            }
+            IPC_RMID => {
+                ns.msg.remove(msqid);
+                0
+            }
+            _ => EINVAL
+        };
+        drop(ns);
+        // 队列被删除或者容量变化 唤醒等待的任务
+        match cmd {
+            IPC_RMID if ret == 0 => ipc_wake_removed(WaitEvent::Msg(msqid)),
+            IPC_SET if ret == 0 => ipc_wake(WaitEvent::Msg(msqid)),
+            _ => {}
+        }
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+}
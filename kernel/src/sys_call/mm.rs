@@ -1,18 +1,37 @@
-use crate::fs::file::FileOP;
-use crate::memory::mem_map::MemMap;
 use crate::memory::mem_map::MapFlags;
-use crate::memory::page::alloc;
-use crate::memory::page::get_free_page_num;
-use crate::memory::page_table::PTEFlags;
+use crate::memory::mem_map::MapProt;
+use crate::memory::vma::PageFaultType;
+use crate::memory::vma::Vma;
+use crate::memory::vma::VmaType;
+use crate::task::signal::Signal;
+use crate::task::signal::SigInfo;
+use crate::task::signal::SEGV_ACCERR;
+use crate::task::signal::SEGV_MAPERR;
 use crate::runtime_err::RuntimeError;
 use crate::task::task::Task;
 use crate::memory::addr::PAGE_SIZE;
-use crate::memory::addr::VirtAddr;
-use crate::memory::addr::get_buf_from_phys_addr;
-use crate::task::fd_table::FD_NULL;
+use crate::memory::addr::get_pages_num;
 use crate::task::fd_table::FD_RANDOM;
+use crate::sys_call::consts::EBADF;
+use crate::sys_call::consts::EINVAL;
+use crate::sys_call::consts::ENOMEM;
 
 impl Task {
+    // 处理用户态缺页异常 无法处理时向进程发送SIGSEGV
+    pub fn page_fault(&self, addr: usize, fault_type: PageFaultType) -> Result<(), RuntimeError> {
+        let inner = self.inner.borrow();
+        let process = inner.process.borrow();
+        if process.pmm.handle_page_fault(&process.vmas, addr, fault_type)? {
+            return Ok(());
+        }
+        warn!("段错误 pid: {} 地址: {:#x} 调用地址: {:#x} 类型: {:?}", self.pid, addr, inner.context.sepc, fault_type);
+        // 地址属于某个内存区域时为权限错误
+        let code = if process.vmas.find(addr).is_some() { SEGV_ACCERR } else { SEGV_MAPERR };
+        drop(process);
+        drop(inner);
+        self.force_signal(SigInfo::from_fault(Signal::SIGSEGV as usize, code, addr))
+    }
+
     pub fn sys_brk(&self, top_pos: usize) -> Result<(), RuntimeError> {
         let mut inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
@@ -27,6 +46,8 @@ impl Task {
             } else {
                 process.heap.set_heap_top(top_pos)?
             };
+            let (heap_start, heap_end) = (process.heap.start, process.heap.end);
+            process.vmas.set_heap(heap_start, heap_end);
             debug!("[sys_brk] brk_addr: {:X}; new_addr: {:X} caller addr: {:X}", top_pos, ret, inner.context.sepc);
             drop(process);
             inner.context.x[10] = ret;
@@ -34,97 +55,134 @@ impl Task {
         Ok(())
     }
 
-    pub fn sys_mmap(&self, start: usize, len: usize, _prot: usize, 
+    pub fn sys_mmap(&self, start: usize, len: usize, prot: usize, 
             flags: usize, fd: usize, offset: usize) -> Result<(), RuntimeError> {
         let mut inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
-        debug!("start: {:#x}, len: {}", start, len);
-        let start = if start == 0 {
-            let latest_addr = process.mem_set.get_last_addr();
-            if latest_addr < 0xd000_0000 {
-                0xd000_0000
-            } else {
-                latest_addr
-            }
-        } else {
-            start
-        };
-        if len == 0x80000 || len == 524288 {
-            debug!("wrap? len: {}", len / PAGE_SIZE);
-            let start_page = start / PAGE_SIZE;
-            debug!("start: {:#x}", start);
-            let end_page = start_page + (len / PAGE_SIZE);
-            debug!("free pae: {:#x}  start_page: {:#x} end_page: {:#x}", get_free_page_num(), start_page, end_page);
-            let mem_map = MemMap::new(start_page.into(), 1, PTEFlags::UVRWX)?;
-            for i in start_page..end_page {
-                process.pmm.add_mapping(mem_map.ppn, i.into(), PTEFlags::UVRWX)?;
-            }
-            process.mem_set.0.push(mem_map);
+        debug!("mmap start: {:#x}, len: {:#x}, prot: {}, flags: {:#x}, fd: {:#x}, offset: {:#x}", start, len, prot, flags, fd, offset);
+        let flags = MapFlags::from_bits_truncate(flags as u32);
+        let prot = MapProt::from_bits_truncate(prot as u32);
+
+        if len == 0 || offset % PAGE_SIZE != 0 {
             drop(process);
-            inner.context.x[10] = start;
+            inner.context.x[10] = EINVAL;
             return Ok(());
         }
-        debug!("mmap start: {:#x}, len: {:#x}, prot: {}, flags: {}, fd: {:#x}, offset: {:#x}", start, len, _prot, flags, fd, offset);
-        let flags = MapFlags::from_bits_truncate(flags as u32);
-        let mut p_start = process.pmm.get_phys_addr(start.into())?;
-        debug!("申请: {:#x}", p_start.0);
-        if p_start.0 < 0x8000_0000 {
-            let page_num = len / PAGE_SIZE;
-            let mem_map = MemMap::new(VirtAddr::from(start).into(), page_num, PTEFlags::UVRWX)?;
-            p_start = mem_map.ppn.into();
-            process.pmm.add_mapping_by_map(&mem_map)?;
-
-            // let parent = process.parent.clone();
-            // if let Some(parent) = parent.map_or(None, |x| x.upgrade()) {
-            //     let mut parent = parent.borrow_mut();
-            //     parent.pmm.add_mapping_by_map(&mem_map)?;
-            //     parent.mem_set.0.push(mem_map.clone());
+        let len = get_pages_num(len) * PAGE_SIZE;
 
-            //     let parent = parent.parent.clone();
-            //     if let Some(parent) = parent.map_or(None, |x| x.upgrade()) {
-            //         let mut parent = parent.borrow_mut();
-            //         parent.pmm.add_mapping_by_map(&mem_map)?;
-            //         parent.mem_set.0.push(mem_map.clone());
+        // 获取映射的来源
+        let vma_type = if flags.contains(MapFlags::MAP_ANONYMOUS) || fd == FD_RANDOM {
+            VmaType::Anonymous
+        } else {
+            match process.fd_table.get(fd) {
+                Ok(file) => VmaType::File(file.file.clone(), offset),
+                Err(_) => {
+                    drop(process);
+                    inner.context.x[10] = EBADF;
+                    return Ok(());
+                }
+            }
+        };
 
-            //     }
+        // 获取映射的地址
+        let start = if flags.contains(MapFlags::MAP_FIXED) {
+            if start % PAGE_SIZE != 0 {
+                drop(process);
+                inner.context.x[10] = EINVAL;
+                return Ok(());
+            }
+            // 覆盖原有的映射
+            process.unmap(start, start + len);
+            start
+        } else if start != 0 && start % PAGE_SIZE == 0 && process.vmas.is_free(start, start + len) {
+            start
+        } else {
+            match process.vmas.find_free(len) {
+                Some(start) => start,
+                None => {
+                    drop(process);
+                    inner.context.x[10] = ENOMEM;
+                    return Ok(());
+                }
+            }
+        };
 
-            // }
-            process.mem_set.0.push(mem_map);
+        let mut vma = Vma::new(start, start + len, prot.to_pte_flags(), vma_type);
+        vma.shared = flags.contains(MapFlags::MAP_SHARED);
+        // 共享匿名映射需要在fork之前分配页面 共享文件映射使用文件的页缓存 私有映射在缺页时分配
+        if (vma.shared && matches!(vma.vma_type, VmaType::Anonymous)) || flags.contains(MapFlags::MAP_POPULATE) {
+            // 内存不足时释放已经分配的页面 不加入映射区域
+            if process.pmm.populate(&vma).is_err() {
+                process.pmm.unmap_range(vma.start, vma.end);
+                drop(process);
+                inner.context.x[10] = ENOMEM;
+                return Ok(());
+            }
         }
-        let buf = get_buf_from_phys_addr(p_start, len);
+        process.vmas.push(vma);
+        drop(process);
+        inner.context.x[10] = start;
+        Ok(())
+    }
 
-        if flags.contains(MapFlags::MAP_FIXED) {
-            warn!("contains: fixed");
-        }
-        if fd == FD_NULL {
-            todo!()
-        } else if fd == FD_RANDOM {
+    pub fn sys_mprotect(&self, addr: usize, len: usize, prot: usize) -> Result<(), RuntimeError> {
+        debug!("保护页面: {:#x}  len: {:#x}", addr, len);
+        let mut inner = self.inner.borrow_mut();
+        let mut process = inner.process.borrow_mut();
+        if addr % PAGE_SIZE != 0 {
             drop(process);
-            inner.context.x[10] = start;
-            Ok(())
-        } else {
-            let file = process.fd_table.get_file(fd)?;
-            debug!("file size: {:#x}", file.get_size());
-            file.copy_to(offset, buf);
+            inner.context.x[10] = EINVAL;
+            return Ok(());
+        }
+        let end = addr + get_pages_num(len) * PAGE_SIZE;
+        // 范围内存在没有映射的地址
+        if !process.vmas.is_mapped(addr, end) {
             drop(process);
-            inner.context.x[10] = start;
-            Ok(())
+            inner.context.x[10] = ENOMEM;
+            return Ok(());
         }
+        let flags = MapProt::from_bits_truncate(prot as u32).to_pte_flags();
+        process.vmas.protect(addr, end, flags);
+        process.pmm.protect_range(addr, end, flags)?;
+        drop(process);
+        inner.context.x[10] = 0;
+        Ok(())
     }
 
-    pub fn sys_mprotect(&self, _addr: usize, _len: usize, _prot: usize) -> Result<(), RuntimeError> {
-        debug!("保护页面: {:#x}  len: {:#x}", _addr, _len);
+    pub fn sys_munmap(&self, start: usize, len: usize) -> Result<(), RuntimeError> {
         let mut inner = self.inner.borrow_mut();
+        let mut process = inner.process.borrow_mut();
+        if start % PAGE_SIZE != 0 || len == 0 {
+            drop(process);
+            inner.context.x[10] = EINVAL;
+            return Ok(());
+        }
+        let end = start + get_pages_num(len) * PAGE_SIZE;
+        process.unmap(start, end);
+        drop(process);
         inner.context.x[10] = 0;
         Ok(())
     }
 
-    pub fn sys_munmap(&self, start: usize, _len: usize) -> Result<(), RuntimeError> {
+    pub fn sys_msync(&self, start: usize, len: usize, _flags: usize) -> Result<(), RuntimeError> {
         let mut inner = self.inner.borrow_mut();
         let process = inner.process.borrow_mut();
-        process.pmm.remove_mapping(start.into());
+        if start % PAGE_SIZE != 0 {
+            drop(process);
+            inner.context.x[10] = EINVAL;
+            return Ok(());
+        }
+        let end = start + get_pages_num(len) * PAGE_SIZE;
+        // 范围内存在没有映射的地址
+        if !process.vmas.is_mapped(start, end) {
+            drop(process);
+            inner.context.x[10] = ENOMEM;
+            return Ok(());
+        }
+        // 写入是同步完成的 MS_ASYNC和MS_SYNC的处理相同
+        process.vmas.sync(start, end)?;
         drop(process);
         inner.context.x[10] = 0;
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,3 @@
-use alloc::vec::Vec;
 use riscv::register::scause;
 use riscv::register::scause::Trap;
 use riscv::register::scause::Exception;
@@ -6,15 +5,37 @@ use riscv::register::scause::Interrupt;
 use riscv::register::stval;
 use riscv::register::sstatus;
 use crate::interrupt::timer;
-use crate::sync::mutex::Mutex;
 use crate::sys_call::consts::ENOENT;
 use crate::task::task_scheduler::kill_task;
 use crate::sys_call::consts::EBADF;
-use crate::interrupt::timer::set_last_ticks;
+use crate::sys_call::consts::ENOSPC;
 use crate::runtime_err::RuntimeError;
+use core::mem::size_of;
 use crate::task::signal::SignalUserContext;
+use crate::task::signal::SigFrame;
+use crate::task::signal::SigInfo;
+use crate::task::signal::SigAction;
+use crate::task::signal::Signal;
+use crate::task::signal::SignalStack;
+use crate::task::signal::SignalStackFlags;
+use crate::task::signal::CLD_STOPPED;
+use crate::task::signal::BUS_ADRALN;
+use crate::task::signal::ILL_ILLOPC;
+use crate::task::signal::ILL_ILLTRP;
+use crate::task::signal::TRAP_BRKPT;
+use crate::memory::addr::UserAddr;
+use crate::task::signal::SigDefault;
+use crate::task::signal::SIG_DFL;
+use crate::task::signal::SIG_IGN;
+use crate::task::signal::default_action;
+use crate::task::signal::is_unblockable;
+use crate::task::task_scheduler::wait_current;
+use crate::task::wait_queue::WaitEvent;
 use crate::task::task::Task;
+use crate::memory::vma::PageFaultType;
 use crate::task::task_scheduler::switch_next;
+use crate::task::task_scheduler::need_resched;
+use crate::task::task_scheduler::wake_expired;
 
 pub mod fd;
 pub mod task;
@@ -23,18 +44,31 @@ pub mod mm;
 pub mod consts;
 pub mod signal;
 pub mod net;
+pub mod fault;
+pub mod ipc;
 
 // 中断调用列表
 pub const SYS_GETCWD:usize  = 17;
+pub const SYS_EVENTFD2: usize = 19;
+pub const SYS_EPOLL_CREATE1: usize = 20;
+pub const SYS_EPOLL_CTL: usize = 21;
+pub const SYS_EPOLL_PWAIT: usize = 22;
 pub const SYS_DUP: usize    = 23;
 pub const SYS_DUP3: usize   = 24;
 pub const SYS_FCNTL: usize  = 25;
+pub const SYS_MKNODAT:usize = 33;
 pub const SYS_MKDIRAT:usize = 34;
 pub const SYS_UNLINKAT:usize= 35;
+pub const SYS_SYMLINKAT: usize = 36;
+pub const SYS_LINKAT: usize = 37;
 pub const SYS_UMOUNT2: usize= 39;
 pub const SYS_MOUNT: usize  = 40;
 pub const SYS_STATFS: usize = 43;
+pub const SYS_FSTATFS: usize = 44;
+pub const SYS_FTRUNCATE: usize = 46;
 pub const SYS_CHDIR: usize  = 49;
+pub const SYS_FCHMOD: usize = 52;
+pub const SYS_FCHMODAT: usize = 53;
 pub const SYS_OPENAT:usize  = 56;
 pub const SYS_CLOSE: usize  = 57;
 pub const SYS_PIPE2: usize  = 59;
@@ -46,7 +80,9 @@ pub const SYS_READV:  usize  = 65;
 pub const SYS_WRITEV: usize = 66;
 pub const SYS_PREAD: usize  = 67;
 pub const SYS_SENDFILE: usize = 71;
+pub const SYS_PSELECT6: usize = 72;
 pub const SYS_PPOLL: usize = 73;
+pub const SYS_SIGNALFD4: usize = 74;
 pub const SYS_READLINKAT: usize = 78;
 pub const SYS_FSTATAT: usize= 79;
 pub const SYS_FSTAT: usize  = 80;
@@ -56,16 +92,39 @@ pub const SYS_EXIT_GROUP: usize = 94;
 pub const SYS_SET_TID_ADDRESS: usize = 96;
 pub const SYS_FUTEX: usize  = 98;
 pub const SYS_NANOSLEEP: usize = 101;
+pub const SYS_GETITIMER: usize = 102;
+pub const SYS_SETITIMER: usize = 103;
+pub const SYS_TIMER_CREATE: usize = 107;
+pub const SYS_TIMER_GETTIME: usize = 108;
+pub const SYS_TIMER_GETOVERRUN: usize = 109;
+pub const SYS_TIMER_SETTIME: usize = 110;
+pub const SYS_TIMER_DELETE: usize = 111;
 pub const SYS_GETTIME: usize = 113;
+pub const SYS_SCHED_SETPARAM: usize = 118;
+pub const SYS_SCHED_SETSCHEDULER: usize = 119;
+pub const SYS_SCHED_GETSCHEDULER: usize = 120;
+pub const SYS_SCHED_GETPARAM: usize = 121;
+pub const SYS_SCHED_SETAFFINITY: usize = 122;
+pub const SYS_SCHED_GETAFFINITY: usize = 123;
 pub const SYS_SCHED_YIELD: usize = 124;
+pub const SYS_SCHED_GET_PRIORITY_MAX: usize = 125;
+pub const SYS_SCHED_GET_PRIORITY_MIN: usize = 126;
+pub const SYS_SETPRIORITY: usize = 140;
+pub const SYS_GETPRIORITY: usize = 141;
 pub const SYS_KILL: usize = 129;
 pub const SYS_TKILL: usize = 130;
 pub const SYS_TGKILL: usize = 131;
+pub const SYS_SIGALTSTACK: usize = 132;
+pub const SYS_SIGSUSPEND: usize = 133;
 pub const SYS_SIGACTION: usize = 134;
 pub const SYS_SIGPROCMASK: usize = 135;
+pub const SYS_SIGPENDING: usize = 136;
 pub const SYS_SIGTIMEDWAIT: usize = 137;
+pub const SYS_SIGQUEUEINFO: usize = 138;
 pub const SYS_SIGRETURN: usize = 139;
 pub const SYS_TIMES: usize  = 153;
+pub const SYS_SETPGID: usize = 154;
+pub const SYS_GETPGID: usize = 155;
 pub const SYS_UNAME: usize  = 160;
 pub const SYS_GETRUSAGE: usize = 165;
 pub const SYS_GETTIMEOFDAY: usize= 169;
@@ -75,21 +134,45 @@ pub const SYS_GETUID: usize = 174;
 // pub const SYS_GETEUID: usize = 175;
 pub const SYS_GETGID: usize = 176;
 pub const SYS_GETTID: usize = 178;
+pub const SYS_MSGGET: usize = 186;
+pub const SYS_MSGCTL: usize = 187;
+pub const SYS_MSGRCV: usize = 188;
+pub const SYS_MSGSND: usize = 189;
+pub const SYS_SEMGET: usize = 190;
+pub const SYS_SEMCTL: usize = 191;
+pub const SYS_SEMTIMEDOP: usize = 192;
+pub const SYS_SEMOP: usize = 193;
+pub const SYS_SHMGET: usize = 194;
+pub const SYS_SHMCTL: usize = 195;
+pub const SYS_SHMAT: usize = 196;
+pub const SYS_SHMDT: usize = 197;
 pub const SYS_SOCKET: usize = 198;
+pub const SYS_SOCKETPAIR: usize = 199;
 pub const SYS_BIND: usize   = 200;
 pub const SYS_LISTEN: usize = 201;
+pub const SYS_ACCEPT: usize = 202;
 pub const SYS_CONNECT: usize = 203;
 pub const SYS_GETSOCKNAME: usize = 204;
+pub const SYS_GETPEERNAME: usize = 205;
 pub const SYS_SENDTO: usize = 206;
 pub const SYS_RECVFROM: usize = 207;
 pub const SYS_SETSOCKOPT: usize = 208;
+pub const SYS_SHUTDOWN: usize = 210;
+pub const SYS_SENDMSG: usize = 211;
+pub const SYS_RECVMSG: usize = 212;
 pub const SYS_BRK:   usize  = 214;
 pub const SYS_CLONE: usize  = 220;
 pub const SYS_EXECVE:usize  = 221;
 pub const SYS_MMAP: usize   = 222;
 pub const SYS_MPROTECT:usize= 226;
 pub const SYS_MUNMAP:usize  = 215;
+pub const SYS_MSYNC: usize  = 227;
+pub const SYS_ACCEPT4: usize = 242;
 pub const SYS_WAIT4: usize  = 260;
+pub const SYS_RENAMEAT2: usize = 276;
+
+// 读取地址未对齐的异常编号
+const LOAD_MISALIGNED: usize = 4;
 
 // 系统调用错误码
 pub const SYS_CALL_ERR: usize = -1 as isize as usize;
@@ -102,14 +185,19 @@ bitflags! {
         const WRONLY = 1 << 0;
         const RDWR = 1 << 1;
         const CREATE = 1 << 6;
-        const TRUNC = 1 << 10;
+        const EXCL = 1 << 7;
+        const TRUNC = 1 << 9;
+        const APPEND = 1 << 10;
+        const NONBLOCK = 1 << 11;
         const O_DIRECTORY = 1 << 21;
+        const CLOEXEC = 1 << 19;
     }
 
     pub struct SignalFlag: usize {
         const SA_NOCLDSTOP = 0x1;
         const SA_NOCLDWAIT = 0x2;
         const SA_SIGINFO   = 0x4;
+        const SA_ONSTACK   = 0x08000000;
         const SA_RESTART   = 0x10000000;
         const SA_NODEFER   = 0x40000000;
         const SA_RESETHAND = 0x80000000;
@@ -179,24 +267,40 @@ impl Task {
             SYS_DUP3 => self.sys_dup3(args[0], args[1]),
             // 控制资源
             SYS_FCNTL => self.sys_fcntl(args[0], args[1], args[2]),
+            // 创建文件节点
+            SYS_MKNODAT => self.sys_mknodat(args[0], args[1].into(), args[2], args[3]),
             // 创建文件夹
             SYS_MKDIRAT => self.sys_mkdirat(args[0], args[1].into(), args[2]),
             // 取消link
             SYS_UNLINKAT => self.sys_unlinkat(args[0], args[1].into(), args[2]),
+            // 创建符号链接
+            SYS_SYMLINKAT => self.sys_symlinkat(args[0].into(), args[1], args[2].into()),
+            // 创建硬链接
+            SYS_LINKAT => self.sys_linkat(args[0], args[1].into(), args[2], args[3].into(), args[4]),
+            // 重命名文件
+            SYS_RENAMEAT2 => self.sys_renameat2(args[0], args[1].into(), args[2], args[3].into(), args[4]),
             // umount设备
-            SYS_UMOUNT2 => Ok(()),
+            SYS_UMOUNT2 => self.sys_umount2(args[0].into(), args[1]),
             // mount设备
-            SYS_MOUNT => Ok(()),
+            SYS_MOUNT => self.sys_mount(args[0].into(), args[1].into(), args[2].into(), args[3], args[4].into()),
             // 获取文件系统信息
-            SYS_STATFS => self.sys_statfs(args[0], args[1].into()),
+            SYS_STATFS => self.sys_statfs(args[0].into(), args[1].into()),
+            // 获取打开的文件所在文件系统信息
+            SYS_FSTATFS => self.sys_fstatfs(args[0], args[1].into()),
+            // 修改文件大小
+            SYS_FTRUNCATE => self.sys_ftruncate(args[0], args[1]),
             // 改变文件信息
             SYS_CHDIR => self.sys_chdir(args[0].into()),
+            // 修改文件权限
+            SYS_FCHMOD => self.sys_fchmod(args[0], args[1]),
+            // 修改路径对应文件的权限
+            SYS_FCHMODAT => self.sys_fchmodat(args[0], args[1].into(), args[2], args[3]),
             // 打开文件地址
             SYS_OPENAT => self.sys_openat(args[0], args[1].into(), args[2], args[3]),
             // 关闭文件描述符
             SYS_CLOSE => self.sys_close(args[0]),
             // 进行PIPE
-            SYS_PIPE2 => self.sys_pipe2(args[0].into()),
+            SYS_PIPE2 => self.sys_pipe2(args[0].into(), args[1]),
             // 获取文件节点
             SYS_GETDENTS => self.sys_getdents(args[0], args[1].into(), args[2]),
             // 移动读取位置
@@ -214,7 +318,19 @@ impl Task {
             // 发送文件
             SYS_SENDFILE => self.sys_sendfile(args[0], args[1], args[2], args[3]),
             // 等待ppoll
-            SYS_PPOLL => self.sys_ppoll(args[0].into(), args[1], args[2].into()),
+            SYS_PPOLL => self.sys_ppoll(args[0].into(), args[1], args[2].into(), args[3].into(), args[4]),
+            // 等待文件描述符集合
+            SYS_PSELECT6 => self.sys_pselect6(args[0], args[1].into(), args[2].into(), args[3].into(), args[4].into(), args[5].into()),
+            // 创建epoll实例
+            SYS_EPOLL_CREATE1 => self.sys_epoll_create1(args[0]),
+            // 修改epoll监听的文件
+            SYS_EPOLL_CTL => self.sys_epoll_ctl(args[0], args[1], args[2], args[3].into()),
+            // 等待epoll监听的文件就绪
+            SYS_EPOLL_PWAIT => self.sys_epoll_pwait(args[0], args[1].into(), args[2], args[3] as isize, args[4].into(), args[5]),
+            // 创建eventfd
+            SYS_EVENTFD2 => self.sys_eventfd2(args[0], args[1]),
+            // 创建signalfd
+            SYS_SIGNALFD4 => self.sys_signalfd4(args[0], args[1].into(), args[2], args[3]),
             // 读取文件数据
             SYS_READLINKAT => self.sys_readlinkat(args[0], args[1].into(), args[2].into(), args[3]),
             // 获取文件数据信息
@@ -230,13 +346,40 @@ impl Task {
             // 设置tid
             SYS_SET_TID_ADDRESS => self.sys_set_tid_address(args[0].into()),
             // 互斥锁
-            SYS_FUTEX => self.sys_futex(args[0].into(), args[1] as u32, args[2] as _, args[3], args[4]),
+            SYS_FUTEX => self.sys_futex(args[0].into(), args[1] as u32, args[2] as _, args[3], args[4].into(), args[5] as _),
             // 文件休眠
             SYS_NANOSLEEP => self.sys_nanosleep(args[0].into(), args[1].into()),
+            // 获取间隔定时器
+            SYS_GETITIMER => self.sys_getitimer(args[0], args[1].into()),
+            // 设置间隔定时器
+            SYS_SETITIMER => self.sys_setitimer(args[0], args[1].into(), args[2].into()),
+            // 创建POSIX定时器
+            SYS_TIMER_CREATE => self.sys_timer_create(args[0], args[1].into(), args[2].into()),
+            // 获取POSIX定时器
+            SYS_TIMER_GETTIME => self.sys_timer_gettime(args[0], args[1].into()),
+            // 获取POSIX定时器超限次数
+            SYS_TIMER_GETOVERRUN => self.sys_timer_getoverrun(args[0]),
+            // 设置POSIX定时器
+            SYS_TIMER_SETTIME => self.sys_timer_settime(args[0], args[1], args[2].into(), args[3].into()),
+            // 删除POSIX定时器
+            SYS_TIMER_DELETE => self.sys_timer_delete(args[0]),
             // 获取系统时间
             SYS_GETTIME => self.sys_gettime(args[0], args[1].into()),
             // 转移文件权限
             SYS_SCHED_YIELD => self.sys_sched_yield(),
+            // 设置调度策略
+            SYS_SCHED_SETSCHEDULER => self.sys_sched_setscheduler(args[0], args[1], args[2].into()),
+            SYS_SCHED_GETSCHEDULER => self.sys_sched_getscheduler(args[0]),
+            SYS_SCHED_SETPARAM => self.sys_sched_setparam(args[0], args[1].into()),
+            SYS_SCHED_GETPARAM => self.sys_sched_getparam(args[0], args[1].into()),
+            SYS_SCHED_GET_PRIORITY_MAX => self.sys_sched_get_priority(args[0], true),
+            SYS_SCHED_GET_PRIORITY_MIN => self.sys_sched_get_priority(args[0], false),
+            // 设置任务运行的核心
+            SYS_SCHED_SETAFFINITY => self.sys_sched_setaffinity(args[0], args[1], args[2].into()),
+            SYS_SCHED_GETAFFINITY => self.sys_sched_getaffinity(args[0], args[1], args[2].into()),
+            // 设置nice值
+            SYS_SETPRIORITY => self.sys_setpriority(args[0], args[1], args[2] as isize),
+            SYS_GETPRIORITY => self.sys_getpriority(args[0], args[1]),
             // 结束进程
             SYS_KILL => self.sys_kill(args[0], args[1]),
             // 结束任务进程
@@ -247,16 +390,24 @@ impl Task {
             SYS_SIGACTION => self.sys_sigaction(args[0], args[1].into(),args[2].into(), args[3]),
             // 遮盖信号
             SYS_SIGPROCMASK => self.sys_sigprocmask(args[0] as _, args[1].into(),args[2].into(), args[3] as _),
-            //
-            // SYS_SIGTIMEDWAIT => {
-            //     let mut inner = self.inner.borrow_mut();
-            //     inner.context.x[10] = 0;
-            //     Ok(())
-            // }
+            // 挂起等待信号
+            SYS_SIGSUSPEND => self.sys_sigsuspend(args[0].into()),
+            // 获取等待的信号
+            SYS_SIGPENDING => self.sys_sigpending(args[0].into()),
+            // 同步等待信号
+            SYS_SIGTIMEDWAIT => self.sys_sigtimedwait(args[0].into(), args[1].into(), args[2].into()),
+            // 发送附带数据的信号
+            SYS_SIGQUEUEINFO => self.sys_sigqueueinfo(args[0], args[1], args[2].into()),
             // 信号返回程序
             SYS_SIGRETURN => self.sys_sigreturn(),
+            // 设置信号栈
+            SYS_SIGALTSTACK => self.sys_sigaltstack(args[0].into(), args[1].into()),
             // 获取文件时间
             SYS_TIMES => self.sys_times(args[0]),
+            // 设置进程组
+            SYS_SETPGID => self.sys_setpgid(args[0], args[1]),
+            // 获取进程组
+            SYS_GETPGID => self.sys_getpgid(args[0]),
             // 获取系统信息
             SYS_UNAME => self.sys_uname(args[0].into()),
             // 获取任务获取信息
@@ -279,22 +430,60 @@ impl Task {
             },
             // 获取tid
             SYS_GETTID => self.sys_gettid(),
+            // 获取消息队列
+            SYS_MSGGET => self.sys_msgget(args[0], args[1]),
+            // 控制消息队列
+            SYS_MSGCTL => self.sys_msgctl(args[0], args[1], args[2]),
+            // 接收消息
+            SYS_MSGRCV => self.sys_msgrcv(args[0], args[1], args[2], args[3] as isize, args[4]),
+            // 发送消息
+            SYS_MSGSND => self.sys_msgsnd(args[0], args[1], args[2], args[3]),
+            // 获取信号量集合
+            SYS_SEMGET => self.sys_semget(args[0], args[1], args[2]),
+            // 控制信号量集合
+            SYS_SEMCTL => self.sys_semctl(args[0], args[1], args[2], args[3]),
+            // 执行信号量操作 可以设置超时
+            SYS_SEMTIMEDOP => self.sys_semtimedop(args[0], args[1].into(), args[2], args[3].into()),
+            // 执行信号量操作
+            SYS_SEMOP => self.sys_semop(args[0], args[1].into(), args[2]),
+            // 获取共享内存段
+            SYS_SHMGET => self.sys_shmget(args[0], args[1], args[2]),
+            // 控制共享内存段
+            SYS_SHMCTL => self.sys_shmctl(args[0], args[1], args[2]),
+            // 映射共享内存段
+            SYS_SHMAT => self.sys_shmat(args[0], args[1], args[2]),
+            // 取消共享内存段的映射
+            SYS_SHMDT => self.sys_shmdt(args[0]),
             // 申请socket
             SYS_SOCKET => self.sys_socket(args[0], args[1], args[2]),
+            // 创建一对连接的socket
+            SYS_SOCKETPAIR => self.sys_socketpair(args[0], args[1], args[2], args[3].into()),
             // 绑定
-            SYS_BIND => self.sys_bind(),
+            SYS_BIND => self.sys_bind(args[0], args[1].into(), args[2]),
             // 监听socket
-            SYS_LISTEN => self.sys_listen(),
+            SYS_LISTEN => self.sys_listen(args[0], args[1]),
+            // 接受连接
+            SYS_ACCEPT => self.sys_accept(args[0], args[1].into(), args[2].into()),
+            // 接受连接 可以设置新socket的标志
+            SYS_ACCEPT4 => self.sys_accept4(args[0], args[1].into(), args[2].into(), args[3]),
             // 连接connect
-            SYS_CONNECT => self.sys_connect(),
+            SYS_CONNECT => self.sys_connect(args[0], args[1].into(), args[2]),
             // 获取socket名称
-            SYS_GETSOCKNAME => self.sys_getsockname(),
+            SYS_GETSOCKNAME => self.sys_getsockname(args[0], args[1].into(), args[2].into()),
+            // 获取对端socket名称
+            SYS_GETPEERNAME => self.sys_getpeername(args[0], args[1].into(), args[2].into()),
             // 发送
             SYS_SENDTO => self.sys_sendto(args[0], args[1].into(), args[2], args[3], args[4].into(), args[5]),
             // 接收数据
             SYS_RECVFROM => self.sys_recvfrom(args[0],args[1].into(), args[2], args[3], args[4].into(), args[5]),
             // 设置socket属性
             SYS_SETSOCKOPT => self.sys_setsockopt(),
+            // 关闭socket的读写
+            SYS_SHUTDOWN => self.sys_shutdown(args[0], args[1]),
+            // 发送消息
+            SYS_SENDMSG => self.sys_sendmsg(args[0], args[1].into(), args[2]),
+            // 接收消息
+            SYS_RECVMSG => self.sys_recvmsg(args[0], args[1].into(), args[2]),
             // 申请堆空间
             SYS_BRK => self.sys_brk(args[0]),
             // 复制进程信息
@@ -307,6 +496,8 @@ impl Task {
             SYS_MPROTECT => self.sys_mprotect(args[0], args[1], args[2]),
             // 取消文件映射
             SYS_MUNMAP => self.sys_munmap(args[0], args[1]),
+            // 同步文件映射
+            SYS_MSYNC => self.sys_msync(args[0], args[1], args[2]),
             // 等待进程
             SYS_WAIT4 => self.sys_wait4(args[0], args[1].into(), args[2]),
             _ => {
@@ -341,6 +532,11 @@ impl Task {
                     warn!("文件未找到  EBADF");
                     inner.context.x[10] = EBADF;
                 }
+                RuntimeError::NoSpace => {
+                    let mut inner = self.inner.borrow_mut();
+                    warn!("文件系统没有空间");
+                    inner.context.x[10] = ENOSPC;
+                }
                 // 统一处理任务切换
                 RuntimeError::ChangeTask => switch_next(),
                 _ => {
@@ -350,9 +546,74 @@ impl Task {
         }
     }
 
-    pub fn signal(&self, signal: usize) -> Result<(), RuntimeError> {
+    // 处理等待的信号 任务返回用户态之前调用
+    // 线程的信号优先于进程的信号 可以嵌套进入多个信号处理函数
+    pub fn handle_signals(&self) -> Result<(), RuntimeError> {
+        self.inner.borrow_mut().finish_sleep();
+        loop {
+            let mut inner = self.inner.borrow_mut();
+            let process = inner.process.clone();
+            let mut process = process.borrow_mut();
+            // 进程被停止时挂起任务 等待SIGCONT
+            if process.stopped {
+                drop(process);
+                drop(inner);
+                wait_current(WaitEvent::Stopped(self.pid), None);
+                return Ok(());
+            }
+            let mask = inner.sig_mask;
+            let info = match inner.pending.take_unmasked(&mask) {
+                Some(info) => info,
+                None => match process.pending.take_unmasked(&mask) {
+                    Some(info) => info,
+                    None => {
+                        // 没有进入信号处理函数 被打断的系统调用重新执行 恢复rt_sigsuspend替换的信号掩码
+                        inner.restart_syscall(true);
+                        if let Some(mask) = inner.saved_mask.take() {
+                            inner.sig_mask = mask;
+                        }
+                        return Ok(());
+                    }
+                }
+            };
+            let signal = info.signo();
+            let handler = match is_unblockable(signal) {
+                true => SIG_DFL,
+                false => process.sig_actions[signal].handler
+            };
+            match handler {
+                SIG_IGN => continue,
+                SIG_DFL => match default_action(signal) {
+                    SigDefault::Ignore | SigDefault::Continue => continue,
+                    SigDefault::Stop => {
+                        debug!("进程 {} 被信号 {} 停止", self.pid, signal);
+                        process.stopped = true;
+                        process.notify_parent_stop(CLD_STOPPED, signal);
+                        continue;
+                    }
+                    // 结束进程 core标志记录在进程状态中
+                    SigDefault::Terminate | SigDefault::Core => {
+                        debug!("进程 {} 被信号 {} 结束", self.pid, signal);
+                        drop(inner);
+                        process.exit_by_signal(signal);
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    drop(process);
+                    drop(inner);
+                    return self.signal(info);
+                }
+            }
+        }
+    }
+
+    // 进入信号处理函数 在用户栈(或信号栈)上压入rt_sigframe 在sigreturn时恢复
+    pub fn signal(&self, info: SigInfo) -> Result<(), RuntimeError> {
+        let signal = info.signo();
         let mut inner = self.inner.borrow_mut();
-        let mut process = inner.process.borrow_mut();
+        let process = inner.process.clone();
+        let mut process = process.borrow_mut();
 
         process.pmm.change_satp();
         
@@ -360,47 +621,69 @@ impl Task {
 
         let handler = sig_action.handler;
         // 如果没有处理器
-        if handler == 0 {
+        if handler == SIG_DFL || handler == SIG_IGN {
             return Ok(());
         }
-        // 保存上下文
-        let mut temp_context = inner.context.clone();
-        let pmm = process.pmm.clone();
-        // 获取临时页表 对数据进行处理
-        let ucontext = process.heap.get_temp(pmm)?.tranfer::<SignalUserContext>();
-        // 中断正在处理中
-        if ucontext.context.x[0] != 0 {
-            return Ok(());
+        let flags = SignalFlag::from_bits_truncate(sig_action.flags);
+        // 被打断的系统调用 设置了SA_RESTART时重新执行
+        inner.finish_partial_write();
+        inner.restart_syscall(flags.contains(SignalFlag::SA_RESTART));
+
+        // 设置了SA_ONSTACK并且不在信号栈上时切换到信号栈
+        let sp = inner.context.x[2];
+        let altstack = inner.sig_altstack;
+        let on_altstack = altstack.contains(sp);
+        let stack_top = if flags.contains(SignalFlag::SA_ONSTACK) && !on_altstack
+            && !altstack.flags.contains(SignalStackFlags::DISABLE) {
+            if altstack.flags.contains(SignalStackFlags::AUTODISARM) {
+                inner.sig_altstack = SignalStack::disabled();
+            }
+            altstack.sp + altstack.size
+        } else {
+            sp
+        };
+        let frame_size = size_of::<SigFrame>();
+        let frame_addr = (stack_top - frame_size) & !0xf;
+        // 无法写入信号帧时结束进程
+        if !process.prepare_user_write(frame_addr, frame_size) {
+            warn!("无法写入信号帧 pid: {} 地址: {:#x}", self.pid, frame_addr);
+            drop(inner);
+            process.exit_by_signal(Signal::SIGSEGV as usize);
+            return Err(RuntimeError::KillCurrentTask);
+        }
+        if flags.contains(SignalFlag::SA_RESETHAND) {
+            process.sig_actions[signal] = SigAction::empty();
         }
-        let restorer = sig_action.restorer;
-        let _flags = SignalFlag::from_bits_truncate(sig_action.flags);
-        
         drop(process);
-        inner.context.sepc = handler;
-        inner.context.x[1] = restorer;
-        inner.context.x[10] = signal;
-        inner.context.x[11] = 0;
-        inner.context.x[12] = 0xe0000000;
-        ucontext.context.clone_from(&temp_context);
-        ucontext.context.x[0] = ucontext.context.sepc;
-        drop(inner);
 
-        debug!("handle signal: {}  handler: {:#x}", signal, handler);
-        loop {
-            self.run();
-            if let Err(RuntimeError::SigReturn) = self.interrupt() {
-                break;
-            }
+        // 保存上下文、信号掩码和信号栈 x[0]的位置为用户可见的pc
+        let frame = UserAddr::<SigFrame>::from(frame_addr).transfer();
+        frame.info = info;
+        frame.ucontext.flags = 0;
+        frame.ucontext.link = 0;
+        frame.ucontext.stack = altstack;
+        if on_altstack {
+            frame.ucontext.stack.flags |= SignalStackFlags::ONSTACK;
         }
-        // 修改回调地址
-        temp_context.sepc = ucontext.context.x[0];
+        // rt_sigsuspend期间进入处理函数时 sigreturn恢复调用之前的信号掩码
+        frame.ucontext.sig_mask = inner.saved_mask.take().unwrap_or(inner.sig_mask);
+        frame.ucontext.context.clone_from(&inner.context);
+        frame.ucontext.context.x[0] = inner.context.sepc;
 
-        // 恢复上下文 并 移除临时页
-        let mut inner = self.inner.borrow_mut();
-        let process = inner.process.borrow_mut();
-        process.heap.release_temp();
-        drop(process);
-        inner.context.clone_from(&temp_context);
+        // 执行处理函数期间屏蔽sa_mask中的信号 没有设置SA_NODEFER时屏蔽当前信号
+        inner.sig_mask.block(&sig_action.mask);
+        if !flags.contains(SignalFlag::SA_NODEFER) {
+            inner.sig_mask.add(signal);
+        }
+        inner.sig_mask.remove_unblockable();
+
+        inner.context.sepc = handler;
+        inner.context.x[1] = sig_action.restorer;
+        inner.context.x[2] = frame_addr;
+        inner.context.x[10] = signal;
+        inner.context.x[11] = &frame.info as *const SigInfo as usize;
+        inner.context.x[12] = &frame.ucontext as *const SignalUserContext as usize;
+        debug!("handle signal: {}  handler: {:#x}", signal, handler);
         Ok(())
     }
 
@@ -413,31 +696,35 @@ impl Task {
         let mut task_inner = self.inner.borrow_mut();
         let context = &mut task_inner.context;
         // warn!("中断发生: {:#x}, 地址: {:#x}", scause.bits(), context.sepc);
-        // 更新TICKS
-        set_last_ticks();
 
         // 匹配中断原因
         match scause.cause(){
             // 断点中断
             Trap::Exception(Exception::Breakpoint) => {
                 warn!("break中断产生 中断地址 {:#x}", context.sepc);
-                context.sepc = context.sepc + 2;
+                let info = SigInfo::from_fault(Signal::SIGTRAP as usize, TRAP_BRKPT, context.sepc);
+                drop(task_inner);
+                self.force_signal(info)?;
             },
             // 时钟中断
             Trap::Interrupt(Interrupt::SupervisorTimer) => {
+                drop(task_inner);
                 timer::timer_handler();
+                // 唤醒到期的任务
+                wake_expired();
+                // 时间片用完后由调度策略判断是否需要切换任务
+                if timer::slice_expired() {
+                    if need_resched() {
+                        return Err(RuntimeError::ChangeTask);
+                    }
+                    timer::task_time_refresh();
+                }
             },
             // 页处理错误
             Trap::Exception(Exception::StorePageFault) | Trap::Exception(Exception::StoreFault) => {
-                error!("缺页中断触发 缺页地址: {:#x} 触发地址:{:#x} 已同步映射", stval, context.sepc);
-                drop(context);
-                if stval > 0xef00_0000 && stval < 0xf00010000 {
-                    error!("处理缺页中断;");
-                    let mut process = task_inner.process.borrow_mut();
-                    process.stack.alloc_until(stval)?;
-                } else {
-                    panic!("无法 恢复的缺页中断");
-                }
+                debug!("缺页中断触发 缺页地址: {:#x} 触发地址:{:#x}", stval, context.sepc);
+                drop(task_inner);
+                self.page_fault(stval, PageFaultType::Store)?;
             },
             // 用户请求
             Trap::Exception(Exception::UserEnvCall) => {
@@ -455,57 +742,61 @@ impl Task {
                 args.copy_from_slice(&context.x[10..17]);
                 let call_type = context.x[17];
                 drop(context);
+                // 保存第一个参数 被信号打断后重新执行时恢复
+                task_inner.orig_a0 = args[0];
                 drop(task_inner);
-                
 
                 self.sys_call(call_type, args)?;
             },
             // 加载页面错误
-            Trap::Exception(Exception::LoadPageFault) => {
-                panic!("加载权限异常 地址:{:#x} 调用地址: {:#x}", stval, context.sepc)
+            Trap::Exception(Exception::LoadPageFault) | Trap::Exception(Exception::LoadFault) => {
+                debug!("加载缺页 地址:{:#x} 调用地址: {:#x}", stval, context.sepc);
+                drop(task_inner);
+                self.page_fault(stval, PageFaultType::Load)?;
             },
-            // 页面未对齐错误
+            // 未对齐的读取和写入 模拟执行
             Trap::Exception(Exception::StoreMisaligned) => {
-                warn!("页面未对齐");
+                debug!("未对齐写入 地址: {:#x} 调用地址: {:#x}", stval, context.sepc);
+                drop(task_inner);
+                self.misaligned_access(stval)?;
             }
+            // 指令地址未对齐
+            Trap::Exception(Exception::InstructionMisaligned) => {
+                drop(task_inner);
+                self.force_signal(SigInfo::from_fault(Signal::SIGBUS as usize, BUS_ADRALN, stval))?;
+            }
+            // 非法指令
             Trap::Exception(Exception::IllegalInstruction) => {
                 warn!("中断 {:#x} 地址 {:#x} stval: {:#x}", scause.bits(), context.sepc, stval);
-                // panic!("指令页错误");
-
+                let info = SigInfo::from_fault(Signal::SIGILL as usize, ILL_ILLOPC, context.sepc);
+                drop(task_inner);
+                self.force_signal(info)?;
             }
-            Trap::Exception(Exception::InstructionPageFault) => {
-                warn!("中断 {:#x} 地址 {:#x} stval: {:#x}", scause.bits(), context.sepc, stval);
-                panic!("指令页错误");
+            Trap::Exception(Exception::InstructionPageFault) | Trap::Exception(Exception::InstructionFault) => {
+                debug!("指令缺页 {:#x} 地址 {:#x} stval: {:#x}", scause.bits(), context.sepc, stval);
+                drop(task_inner);
+                self.page_fault(stval, PageFaultType::Instruction)?;
+            }
+            // 未对齐的读取 部分版本的riscv库没有LoadMisaligned 使用异常编号判断
+            Trap::Exception(_) if scause.bits() == LOAD_MISALIGNED => {
+                debug!("未对齐读取 地址: {:#x} 调用地址: {:#x}", stval, context.sepc);
+                drop(task_inner);
+                self.misaligned_access(stval)?;
             }
-            // 其他情况，终止当前线程
+            // 其他异常 发送SIGILL
+            Trap::Exception(_) => {
+                warn!("未知 异常 {:#x} 地址 {:#x} stval: {:#x}", scause.bits(), context.sepc, stval);
+                let info = SigInfo::from_fault(Signal::SIGILL as usize, ILL_ILLTRP, context.sepc);
+                drop(task_inner);
+                self.force_signal(info)?;
+            },
+            // 其他中断
             _ => {
                 warn!("未知 中断 {:#x} 地址 {:#x} stval: {:#x}", scause.bits(), context.sepc, stval);
-                return Err(RuntimeError::KillCurrentTask);
             },
         }
     
-        // 更新TICKS
-        set_last_ticks();
 
         Ok(())
     }
 }
-
-lazy_static! {
-    pub static ref VFORK_WAIT_LIST: Mutex<Vec<usize>> = Mutex::new(Vec::new());
-}
-
-pub fn is_vfork_wait(pid: usize) -> bool {
-    let vfork_wait_list = VFORK_WAIT_LIST.lock();
-    vfork_wait_list.iter().find(|&&x| x == pid).is_some()
-}
-
-pub fn add_vfork_wait(pid: usize) {
-    let mut vfork_wait_list = VFORK_WAIT_LIST.lock();
-    vfork_wait_list.push(pid);
-}
-
-pub fn remove_vfork_wait(pid: usize) {
-    let mut vfork_wait_list = VFORK_WAIT_LIST.lock();
-    vfork_wait_list.drain_filter(|&mut x| x==pid);
-}
\ No newline at end of file
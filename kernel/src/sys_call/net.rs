@@ -1,12 +1,23 @@
 
 use alloc::collections::VecDeque;
 use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::cell::RefCell;
 use crate::fs::file::FileOP;
+use crate::fs::file::FileType;
 use crate::fs::file::fcntl_cmd;
+use crate::fs::filetree::DiskFileEnum;
+use crate::fs::filetree::INode;
 use crate::memory::addr::UserAddr;
 use crate::runtime_err::RuntimeError;
 use crate::task::fd_table::FileDesc;
+use crate::task::fd_table::IoVec;
+use crate::task::pipe::PipeReader;
+use crate::task::pipe::PipeWriter;
+use crate::sys_call::OpenFlags;
+use crate::sys_call::consts::{EADDRINUSE, EAGAIN, EBADF, ECONNREFUSED, EINVAL, ENOENT, ENOTCONN, ENOTSOCK, EOPNOTSUPP, EPROTOTYPE};
+use crate::task::unix_socket::{UnixRecv, UnixSocket, AF_UNIX, SOCK_CLOEXEC, SOCK_DGRAM, SOCK_NONBLOCK, SOCK_SEQPACKET, SOCK_STREAM, SOCK_TYPE_MASK, UNIX_PATH_MAX};
 use crate::task::task::Task;
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -72,27 +83,184 @@ impl FileOP for SocketFile {
     }
 }
 
+// send和recv的标志
+const MSG_PEEK: usize = 0x2;
+const MSG_CTRUNC: usize = 0x8;
+const MSG_TRUNC: usize = 0x20;
+const MSG_DONTWAIT: usize = 0x40;
+const MSG_NOSIGNAL: usize = 0x4000;
+
+// shutdown的方式
+const SHUT_RD: usize = 0;
+const SHUT_WR: usize = 1;
+const SHUT_RDWR: usize = 2;
+
+// 控制消息的类型
+const SOL_SOCKET: u32 = 1;
+const SCM_RIGHTS: u32 = 1;
+// 控制消息头的长度 包括cmsg_len、cmsg_level和cmsg_type
+const CMSG_HDR_LEN: usize = 16;
+
+// sendmsg和recvmsg使用的消息头
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MsgHdr {
+    pub name: usize,        // 地址
+    pub namelen: u32,       // 地址长度
+    pub iov: usize,         // IoVec数组
+    pub iovlen: usize,      // IoVec数量
+    pub control: usize,     // 控制消息
+    pub controllen: usize,  // 控制消息长度
+    pub flags: u32          // 接收时返回的标志
+}
+
+// 读取sockaddr_un中的路径 不支持抽象命名空间
+fn read_unix_path(addr: usize, len: usize) -> Result<String, usize> {
+    if addr == 0 || len <= 2 {
+        return Err(EINVAL);
+    }
+    if *UserAddr::<u16>::from(addr).transfer() as usize != AF_UNIX {
+        return Err(EINVAL);
+    }
+    let bytes = UserAddr::<u8>::from(addr + 2).transfer_vec((len - 2).min(UNIX_PATH_MAX));
+    let end = bytes.iter().position(|x| *x == 0).unwrap_or(bytes.len());
+    if end == 0 {
+        return Err(EINVAL);
+    }
+    Ok(String::from_utf8_lossy(&bytes[..end]).to_string())
+}
+
+// 写入sockaddr_un 最多写入max字节 返回地址的实际长度
+fn write_unix_addr(addr: usize, max: usize, path: &Option<String>) -> usize {
+    let mut sa = vec![0u8; 2];
+    sa.copy_from_slice(&(AF_UNIX as u16).to_ne_bytes());
+    if let Some(path) = path {
+        sa.extend_from_slice(path.as_bytes());
+        sa.push(0);
+    }
+    if addr != 0 {
+        let len = max.min(sa.len());
+        UserAddr::<u8>::from(addr).transfer_vec(len).copy_from_slice(&sa[..len]);
+    }
+    sa.len()
+}
+
+// 创建套接字的文件描述符 SOCK_NONBLOCK对应O_NONBLOCK
+fn socket_desc(socket: Rc<UnixSocket>, flags: usize) -> FileDesc {
+    let mut file = FileDesc::new(socket);
+    file.flags = OpenFlags::RDWR;
+    if flags & SOCK_NONBLOCK != 0 {
+        file.flags |= OpenFlags::NONBLOCK;
+    }
+    file
+}
+
 impl Task {
-    pub fn sys_socket(&self, _domain: usize, _ty: usize, _protocol: usize) -> Result<(), RuntimeError> {
-        let file = SocketFile::new();
+    pub fn sys_socket(&self, domain: usize, ty: usize, _protocol: usize) -> Result<(), RuntimeError> {
         let mut inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
 
-        let fd = process.fd_table.push_sock(FileDesc::new(file));
+        let fd = if domain == AF_UNIX {
+            let valid = ty & !(SOCK_TYPE_MASK | SOCK_NONBLOCK | SOCK_CLOEXEC) == 0;
+            match ty & SOCK_TYPE_MASK {
+                SOCK_STREAM | SOCK_DGRAM | SOCK_SEQPACKET if valid =>
+                    process.fd_table.push(socket_desc(UnixSocket::new(ty & SOCK_TYPE_MASK), ty)),
+                _ => EINVAL
+            }
+        } else {
+            process.fd_table.push_sock(FileDesc::new(SocketFile::new()))
+        };
         drop(process);
         inner.context.x[10] = fd;
         Ok(())
     }
 
-    pub fn sys_bind(&self) -> Result<(), RuntimeError> {
+    // 创建一对互相连接的本地套接字
+    pub fn sys_socketpair(&self, domain: usize, ty: usize, _protocol: usize, sv: UserAddr<[u32; 2]>) -> Result<(), RuntimeError> {
         let mut inner = self.inner.borrow_mut();
-        inner.context.x[10] = 0;
+        let mut process = inner.process.borrow_mut();
+
+        let valid = ty & !(SOCK_TYPE_MASK | SOCK_NONBLOCK | SOCK_CLOEXEC) == 0;
+        let ret = match ty & SOCK_TYPE_MASK {
+            _ if domain != AF_UNIX => EOPNOTSUPP,
+            SOCK_STREAM | SOCK_DGRAM | SOCK_SEQPACKET if valid => {
+                let (first, second) = UnixSocket::pair(ty & SOCK_TYPE_MASK);
+                let sv = sv.transfer();
+                sv[0] = process.fd_table.push(socket_desc(first, ty)) as u32;
+                sv[1] = process.fd_table.push(socket_desc(second, ty)) as u32;
+                0
+            }
+            _ => EINVAL
+        };
+        drop(process);
+        inner.context.x[10] = ret;
         Ok(())
     }
 
-    pub fn sys_getsockname(&self) -> Result<(), RuntimeError> {
-        let mut inner = self.inner.borrow_mut();
-        inner.context.x[10] = 0;
+    // 获取套接字路径查找的起点 相对路径从工作目录开始查找
+    fn unix_path_start(&self, path: &str) -> Option<Rc<INode>> {
+        match path.starts_with('/') {
+            true => None,
+            false => Some(self.get_process().borrow().workspace.clone())
+        }
+    }
+
+    // 根据sockaddr_un查找绑定的套接字
+    fn find_unix_socket(&self, addr: usize, len: usize) -> Result<Rc<UnixSocket>, usize> {
+        let path = read_unix_path(addr, len)?;
+        let inode = INode::get(self.unix_path_start(&path), &path).map_err(|_| ENOENT)?;
+        inode.get_socket().ok_or(ECONNREFUSED)
+    }
+
+    // 获取文件描述符对应的本地套接字和是否为非阻塞模式
+    fn get_unix_socket(&self, fd: usize) -> Result<Option<(Rc<UnixSocket>, bool)>, RuntimeError> {
+        let process = self.get_process();
+        let mut process = process.borrow_mut();
+        let file = process.fd_table.get(fd)?;
+        Ok(file.downcast::<UnixSocket>().ok().map(|x| (x, file.nonblock())))
+    }
+
+    // 绑定路径 在文件树中创建套接字节点
+    pub fn sys_bind(&self, fd: usize, addr: UserAddr<u8>, addr_len: usize) -> Result<(), RuntimeError> {
+        let ret = match self.get_unix_socket(fd)? {
+            Some((socket, _)) => match read_unix_path(addr.bits(), addr_len) {
+                Err(err) => err,
+                Ok(_) if socket.path().is_some() => EINVAL,
+                Ok(path) if INode::get(self.unix_path_start(&path), &path).is_ok() => EADDRINUSE,
+                Ok(path) => {
+                    let file = DiskFileEnum::Socket(Rc::downgrade(&socket));
+                    match INode::create(self.unix_path_start(&path), &path, file, FileType::Socket) {
+                        Ok(_) => socket.bind(path),
+                        Err(_) => ENOENT
+                    }
+                }
+            },
+            None => 0
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    pub fn sys_getsockname(&self, fd: usize, addr: UserAddr<u8>, addr_len: UserAddr<u32>) -> Result<(), RuntimeError> {
+        if let Some((socket, _)) = self.get_unix_socket(fd)? {
+            let addr_len = addr_len.transfer();
+            *addr_len = write_unix_addr(addr.bits(), *addr_len as usize, &socket.path()) as u32;
+        }
+        self.inner.borrow_mut().context.x[10] = 0;
+        Ok(())
+    }
+
+    // 获取连接的对端地址
+    pub fn sys_getpeername(&self, fd: usize, addr: UserAddr<u8>, addr_len: UserAddr<u32>) -> Result<(), RuntimeError> {
+        let ret = match self.get_unix_socket(fd)?.and_then(|(socket, _)| socket.peer()) {
+            Some(peer) => {
+                let addr_len = addr_len.transfer();
+                *addr_len = write_unix_addr(addr.bits(), *addr_len as usize, &peer.path()) as u32;
+                0
+            }
+            None => ENOTCONN
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
         Ok(())
     }
 
@@ -102,8 +270,34 @@ impl Task {
         Ok(())
     }
 
-    pub fn sys_sendto(&self, _fd: usize, _buf: UserAddr<u8>, _len: usize, _flags: usize,
-                            _sa: UserAddr<SocketAddr>, _sa_size: usize) -> Result<(), RuntimeError> {
+    // 通过本地套接字发送数据 阻塞模式下等待接收端的空间 发送失败后重新执行系统调用
+    fn unix_send(&self, socket: Rc<UnixSocket>, nonblock: bool, flags: usize, data: &[u8],
+                    fds: Vec<FileDesc>, target: Option<Rc<UnixSocket>>) -> Result<(), RuntimeError> {
+        let ret = socket.send(data, fds, target);
+        if ret == EAGAIN && !nonblock && flags & MSG_DONTWAIT == 0 {
+            return self.wait_file(socket.event());
+        }
+        if flags & MSG_NOSIGNAL == 0 {
+            self.check_broken_pipe(ret);
+        }
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 通过本地套接字接收数据 返回None时已经设置返回值或者进入等待
+    fn unix_recv(&self, socket: &Rc<UnixSocket>, nonblock: bool, flags: usize, buf: &mut [u8]) -> Result<Option<UnixRecv>, RuntimeError> {
+        match socket.recv(buf, flags & MSG_PEEK != 0) {
+            Ok(result) => Ok(Some(result)),
+            Err(EAGAIN) if !nonblock && flags & MSG_DONTWAIT == 0 => self.wait_file(socket.event()).map(|_| None),
+            Err(err) => {
+                self.inner.borrow_mut().context.x[10] = err;
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn sys_sendto(&self, fd: usize, buf: UserAddr<u8>, len: usize, flags: usize,
+                            sa: UserAddr<SocketAddr>, sa_size: usize) -> Result<(), RuntimeError> {
         // let sa = sa.transfer();
         // let mut inner = self.inner.borrow_mut();
         // let process = inner.process.borrow_mut();
@@ -116,11 +310,25 @@ impl Task {
         // drop(process);
 
         // inner.context.x[10] = send_size;
-        Ok(())
+        let (socket, nonblock) = match self.get_unix_socket(fd)? {
+            Some(socket) => socket,
+            None => return Ok(())
+        };
+        let target = match sa.is_valid() {
+            true => match self.find_unix_socket(sa.bits(), sa_size) {
+                Ok(target) => Some(target),
+                Err(err) => {
+                    self.inner.borrow_mut().context.x[10] = err;
+                    return Ok(());
+                }
+            },
+            false => None
+        };
+        self.unix_send(socket, nonblock, flags, buf.transfer_vec(len), vec![], target)
     }
 
-    pub fn sys_recvfrom(&self, _fd: usize, _buf: UserAddr<u8>, _len: usize, _flags: usize,
-        _sa: UserAddr<SocketAddr>, _addr_len: usize) -> Result<(), RuntimeError> {
+    pub fn sys_recvfrom(&self, fd: usize, buf: UserAddr<u8>, len: usize, flags: usize,
+        sa: UserAddr<SocketAddr>, addr_len: usize) -> Result<(), RuntimeError> {
 
         // let sa = sa.transfer();
         // let mut inner = self.inner.borrow_mut();
@@ -130,29 +338,217 @@ impl Task {
 
         // let read_len = file.read(buf);
         // inner.context.x[10] = read_len;
+        let (socket, nonblock) = match self.get_unix_socket(fd)? {
+            Some(socket) => socket,
+            None => return Ok(())
+        };
+        let result = match self.unix_recv(&socket, nonblock, flags, buf.transfer_vec(len))? {
+            Some(result) => result,
+            None => return Ok(())
+        };
+        if sa.is_valid() && addr_len != 0 {
+            let addr_len = UserAddr::<u32>::from(addr_len).transfer();
+            *addr_len = write_unix_addr(sa.bits(), *addr_len as usize, &result.from) as u32;
+        }
+        self.inner.borrow_mut().context.x[10] = result.len;
         Ok(())
     }
 
-    pub fn sys_listen(&self) -> Result<(), RuntimeError> {
-        let mut inner = self.inner.borrow_mut();
-        inner.context.x[10] = 0;
+    // 读取SCM_RIGHTS控制消息中的文件描述符
+    fn read_rights(&self, control: usize, len: usize) -> Result<Vec<FileDesc>, usize> {
+        let process = self.get_process();
+        let mut process = process.borrow_mut();
+        let mut fds = vec![];
+        let mut pos = 0;
+        while control != 0 && pos + CMSG_HDR_LEN <= len {
+            let cmsg_len = *UserAddr::<usize>::from(control + pos).transfer();
+            let level = *UserAddr::<u32>::from(control + pos + 8).transfer();
+            let ty = *UserAddr::<u32>::from(control + pos + 12).transfer();
+            if cmsg_len < CMSG_HDR_LEN || pos + cmsg_len > len {
+                return Err(EINVAL);
+            }
+            if level == SOL_SOCKET && ty == SCM_RIGHTS {
+                let raw = UserAddr::<i32>::from(control + pos + CMSG_HDR_LEN).transfer_vec((cmsg_len - CMSG_HDR_LEN) / 4);
+                for fd in raw {
+                    fds.push(process.fd_table.get(*fd as usize).map_err(|_| EBADF)?.clone());
+                }
+            }
+            // 控制消息按8字节对齐
+            pos += (cmsg_len + 7) & !7;
+        }
+        Ok(fds)
+    }
+
+    // 将接收的文件描述符加入文件描述符表并写入SCM_RIGHTS控制消息 返回控制消息的长度
+    fn write_rights(&self, control: usize, len: usize, fds: Vec<FileDesc>, flags: &mut u32) -> usize {
+        // 空间不足时只传递可以容纳的文件描述符
+        let count = match control {
+            0 => 0,
+            _ => len.saturating_sub(CMSG_HDR_LEN) / 4
+        }.min(fds.len());
+        if count < fds.len() {
+            *flags |= MSG_CTRUNC as u32;
+        }
+        if count == 0 {
+            return 0;
+        }
+        let process = self.get_process();
+        let mut process = process.borrow_mut();
+        let raw = UserAddr::<i32>::from(control + CMSG_HDR_LEN).transfer_vec(count);
+        for (i, file) in fds.into_iter().take(count).enumerate() {
+            raw[i] = process.fd_table.push(file) as i32;
+        }
+        let cmsg_len = CMSG_HDR_LEN + count * 4;
+        *UserAddr::<usize>::from(control).transfer() = cmsg_len;
+        *UserAddr::<u32>::from(control + 8).transfer() = SOL_SOCKET;
+        *UserAddr::<u32>::from(control + 12).transfer() = SCM_RIGHTS;
+        ((cmsg_len + 7) & !7).min(len)
+    }
+
+    // 发送消息 支持通过SCM_RIGHTS传递文件描述符
+    pub fn sys_sendmsg(&self, fd: usize, msg: UserAddr<MsgHdr>, flags: usize) -> Result<(), RuntimeError> {
+        let (socket, nonblock) = match self.get_unix_socket(fd)? {
+            Some(socket) => socket,
+            None => {
+                self.inner.borrow_mut().context.x[10] = EOPNOTSUPP;
+                return Ok(());
+            }
+        };
+        let msg = *msg.transfer();
+        let mut data = vec![];
+        for iov in UserAddr::<IoVec>::from(msg.iov).transfer_vec(msg.iovlen) {
+            data.extend_from_slice(iov.iov_base.transfer_vec(iov.iov_len));
+        }
+        let target = match msg.name {
+            0 => Ok(None),
+            name => self.find_unix_socket(name, msg.namelen as usize).map(Some)
+        };
+        let fds = self.read_rights(msg.control, msg.controllen);
+        match (target, fds) {
+            (Ok(target), Ok(fds)) => self.unix_send(socket, nonblock, flags, &data, fds, target),
+            (Err(err), _) | (_, Err(err)) => {
+                self.inner.borrow_mut().context.x[10] = err;
+                Ok(())
+            }
+        }
+    }
+
+    // 接收消息 传递的文件描述符加入当前进程
+    pub fn sys_recvmsg(&self, fd: usize, msg: UserAddr<MsgHdr>, flags: usize) -> Result<(), RuntimeError> {
+        let (socket, nonblock) = match self.get_unix_socket(fd)? {
+            Some(socket) => socket,
+            None => {
+                self.inner.borrow_mut().context.x[10] = EOPNOTSUPP;
+                return Ok(());
+            }
+        };
+        let msg = msg.transfer();
+        let iovs = UserAddr::<IoVec>::from(msg.iov).transfer_vec(msg.iovlen);
+        let mut buf = vec![0u8; iovs.iter().map(|x| x.iov_len).sum()];
+        let result = match self.unix_recv(&socket, nonblock, flags, &mut buf)? {
+            Some(result) => result,
+            None => return Ok(())
+        };
+        // 将数据分散写入IoVec
+        let mut pos = 0;
+        for iov in iovs {
+            let len = iov.iov_len.min(result.len - pos);
+            iov.iov_base.transfer_vec(len).copy_from_slice(&buf[pos..pos + len]);
+            pos += len;
+        }
+        msg.flags = 0;
+        if result.truncated {
+            msg.flags |= MSG_TRUNC as u32;
+        }
+        if msg.name != 0 {
+            msg.namelen = write_unix_addr(msg.name, msg.namelen as usize, &result.from) as u32;
+        }
+        msg.controllen = self.write_rights(msg.control, msg.controllen, result.fds, &mut msg.flags);
+        self.inner.borrow_mut().context.x[10] = result.len;
         Ok(())
     }
 
-    pub fn sys_connect(&self) -> Result<(), RuntimeError> {
-        let mut inner = self.inner.borrow_mut();
-        inner.context.x[10] = 0;
+    pub fn sys_listen(&self, fd: usize, backlog: usize) -> Result<(), RuntimeError> {
+        let ret = match self.get_unix_socket(fd)? {
+            Some((socket, _)) => socket.listen(backlog),
+            None => 0
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
         Ok(())
     }
 
-    pub fn sys_accept(&self) -> Result<(), RuntimeError> {
-        let mut inner = self.inner.borrow_mut();
-        inner.context.x[10] = 0;
+    // 连接到绑定路径的套接字 监听队列已满时阻塞等待accept
+    pub fn sys_connect(&self, fd: usize, addr: UserAddr<u8>, addr_len: usize) -> Result<(), RuntimeError> {
+        let (socket, nonblock) = match self.get_unix_socket(fd)? {
+            Some(socket) => socket,
+            None => {
+                self.inner.borrow_mut().context.x[10] = 0;
+                return Ok(());
+            }
+        };
+        let ret = match self.find_unix_socket(addr.bits(), addr_len) {
+            Ok(target) if target.ty() != socket.ty() => EPROTOTYPE,
+            Ok(target) => {
+                let ret = socket.connect(target.clone());
+                if ret == EAGAIN && !nonblock {
+                    return self.wait_file(target.event());
+                }
+                ret
+            }
+            Err(err) => err
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
         Ok(())
     }
 
-    pub fn sys_fcntl(&self, fd: usize, cmd: usize, _arg: usize) -> Result<(), RuntimeError> {
-        debug!("val: fd {}  cmd {:#x} arg {:#x}", fd, cmd, _arg);
+    pub fn sys_accept(&self, fd: usize, addr: UserAddr<u8>, addr_len: UserAddr<u32>) -> Result<(), RuntimeError> {
+        self.sys_accept4(fd, addr, addr_len, 0)
+    }
+
+    // 接受连接 没有连接时阻塞等待connect
+    pub fn sys_accept4(&self, fd: usize, addr: UserAddr<u8>, addr_len: UserAddr<u32>, flags: usize) -> Result<(), RuntimeError> {
+        let (socket, nonblock) = match self.get_unix_socket(fd)? {
+            Some(socket) => socket,
+            None => {
+                self.inner.borrow_mut().context.x[10] = 0;
+                return Ok(());
+            }
+        };
+        let ret = match socket.accept() {
+            Ok(server) => {
+                if addr.is_valid() && addr_len.is_valid() {
+                    let path = server.peer().and_then(|x| x.path());
+                    let addr_len = addr_len.transfer();
+                    *addr_len = write_unix_addr(addr.bits(), *addr_len as usize, &path) as u32;
+                }
+                self.get_process().borrow_mut().fd_table.push(socket_desc(server, flags))
+            }
+            Err(EAGAIN) if !nonblock => return self.wait_file(socket.event()),
+            Err(err) => err
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 关闭套接字的读取或者写入
+    pub fn sys_shutdown(&self, fd: usize, how: usize) -> Result<(), RuntimeError> {
+        let ret = match self.get_unix_socket(fd)? {
+            None => ENOTSOCK,
+            Some(_) if how > SHUT_RDWR => EINVAL,
+            Some((socket, _)) => match socket.peer() {
+                None if socket.ty() != SOCK_DGRAM => ENOTCONN,
+                _ => {
+                    socket.shutdown(how != SHUT_WR, how != SHUT_RD);
+                    0
+                }
+            }
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    pub fn sys_fcntl(&self, fd: usize, cmd: usize, arg: usize) -> Result<(), RuntimeError> {
+        debug!("val: fd {}  cmd {:#x} arg {:#x}", fd, cmd, arg);
         // let mut inner = self.inner.borrow_mut();
         // let node = self.map.get_mut(&fd).ok_or(SysError::EBADF)?;
         if fd >= 50 {
@@ -175,6 +571,34 @@ impl Task {
                     debug!("copy value");
                     self.sys_dup(fd)?;
                 }
+                // 获取和设置文件状态标志 目前只能修改O_NONBLOCK
+                fcntl_cmd::GETFL => {
+                    let ret = self.get_process().borrow_mut().fd_table.get(fd)?.flags.bits() as usize;
+                    self.inner.borrow_mut().context.x[10] = ret;
+                }
+                fcntl_cmd::SETFL => {
+                    let process = self.get_process();
+                    let mut process = process.borrow_mut();
+                    let file = process.fd_table.get(fd)?;
+                    file.flags.set(OpenFlags::NONBLOCK, arg & OpenFlags::NONBLOCK.bits() as usize != 0);
+                    drop(process);
+                    self.inner.borrow_mut().context.x[10] = 0;
+                }
+                // 获取和设置管道的容量
+                fcntl_cmd::SETPIPE_SZ | fcntl_cmd::GETPIPE_SZ => {
+                    let file = self.get_process().borrow_mut().fd_table.get(fd)?.clone();
+                    let pipe = match (file.downcast::<PipeReader>(), file.downcast::<PipeWriter>()) {
+                        (Ok(reader), _) => Some(reader.0.clone()),
+                        (_, Ok(writer)) => Some(writer.0.clone()),
+                        _ => None
+                    };
+                    let ret = match pipe {
+                        Some(pipe) if cmd == fcntl_cmd::GETPIPE_SZ => pipe.capacity(),
+                        Some(pipe) => pipe.set_capacity(arg).unwrap_or_else(|err| err),
+                        None => EBADF
+                    };
+                    self.inner.borrow_mut().context.x[10] = ret;
+                }
                 _ => {}
             }
         }
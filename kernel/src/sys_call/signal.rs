@@ -2,7 +2,29 @@ use crate::memory::addr::UserAddr;
 use crate::task::task::Task;
 use crate::task::signal::SigSet;
 use crate::task::signal::SigAction;
+use core::mem::size_of;
+use crate::task::signal::SigFrame;
+use crate::task::signal::Signal;
+use crate::task::signal::SignalStack;
+use crate::task::signal::SignalStackFlags;
+use crate::task::signal::MINSIGSTKSZ;
+use crate::task::signal::SIGNAL_COUNT;
+use crate::task::signal::is_unblockable;
+use crate::task::signal::SigInfo;
+use crate::task::signal::SI_TKILL;
+use crate::task::signalfd::SignalFd;
+use crate::task::signalfd::SignalfdSigInfo;
+use crate::task::signalfd::SFD_CLOEXEC;
+use crate::task::signalfd::SFD_NONBLOCK;
+use crate::task::fd_table::FileDesc;
+use crate::task::task_scheduler::wait_current;
+use crate::task::task_scheduler::get_processes;
+use crate::task::task_scheduler::signal_process;
+use crate::task::wait_queue::WaitEvent;
+use crate::interrupt::timer::{get_time_us, TimeSpec};
+use crate::sys_call::consts::{EAGAIN, EFAULT, EINTR, EINVAL, ENOMEM, EPERM, ESRCH};
 use crate::runtime_err::RuntimeError;
+use alloc::rc::Rc;
 
 impl Task {
     pub fn sys_sigprocmask(&self, how: u32, set:  UserAddr<SigSet>, oldset: UserAddr<SigSet>,
@@ -29,8 +51,13 @@ impl Task {
                 1 => inner.sig_mask.unblock(sig),
                 // setmask
                 2 => inner.sig_mask.copy_from(sig),
-                _ => unimplemented!()
+                _ => {
+                    inner.context.x[10] = EINVAL;
+                    return Ok(());
+                }
             }
+            // SIGKILL和SIGSTOP不能被屏蔽
+            inner.sig_mask.remove_unblockable();
         }
         inner.context.x[10] = 0;
         Ok(())
@@ -41,6 +68,12 @@ impl Task {
         let mut inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
 
+        // SIGKILL和SIGSTOP不能被捕获或者忽略
+        if signum == 0 || signum >= SIGNAL_COUNT || (act.is_valid() && is_unblockable(signum)) {
+            drop(process);
+            inner.context.x[10] = EINVAL;
+            return Ok(());
+        }
         if oldact.is_valid() {
             oldact.transfer().copy_from(&process.sig_actions[signum]);
         }
@@ -61,8 +94,245 @@ impl Task {
         Ok(())
     }
 
+    // 从信号处理函数返回 从栈上的rt_sigframe恢复上下文、信号掩码和信号栈
     pub fn sys_sigreturn(&self) -> Result<(), RuntimeError> {
         debug!("sig return");
-        Err(RuntimeError::SigReturn)
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow();
+        let frame_addr = inner.context.x[2];
+        // 信号帧所在的内存无效时结束进程
+        let valid = process.pmm.get_phys_addr(frame_addr.into()).is_ok()
+            && process.pmm.get_phys_addr((frame_addr + size_of::<SigFrame>() - 1).into()).is_ok();
+        drop(process);
+        if !valid {
+            warn!("无效的信号帧 pid: {} 地址: {:#x}", self.pid, frame_addr);
+            drop(inner);
+            self.get_process().borrow_mut().exit_by_signal(Signal::SIGSEGV as usize);
+            return Err(RuntimeError::KillCurrentTask);
+        }
+        // 处理函数可能修改了ucontext中的pc和寄存器
+        let ucontext = &UserAddr::<SigFrame>::from(frame_addr).transfer().ucontext;
+        inner.context.x.copy_from_slice(&ucontext.context.x);
+        inner.context.sepc = ucontext.context.x[0];
+        inner.context.x[0] = 0;
+        let mut sig_mask = ucontext.sig_mask;
+        sig_mask.remove_unblockable();
+        inner.sig_mask = sig_mask;
+        // 恢复信号栈 不在信号栈上时才能修改
+        let mut stack = ucontext.stack;
+        stack.flags.remove(SignalStackFlags::ONSTACK);
+        if !inner.sig_altstack.contains(inner.context.x[2]) {
+            inner.sig_altstack = stack;
+        }
+        Ok(())
+    }
+
+    // 设置和获取信号栈
+    pub fn sys_sigaltstack(&self, ss: UserAddr<SignalStack>, old_ss: UserAddr<SignalStack>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let on_altstack = inner.sig_altstack.contains(inner.context.x[2]);
+        if old_ss.is_valid() {
+            let mut old = inner.sig_altstack;
+            if on_altstack {
+                old.flags |= SignalStackFlags::ONSTACK;
+            }
+            *old_ss.transfer() = old;
+        }
+        let ret = if ss.is_valid() {
+            let mut stack = *ss.transfer();
+            // SS_ONSTACK 为兼容的写法 作用与0相同
+            stack.flags.remove(SignalStackFlags::ONSTACK);
+            if on_altstack {
+                EPERM
+            } else if !(stack.flags - SignalStackFlags::AUTODISARM).is_empty()
+                && stack.flags != SignalStackFlags::DISABLE {
+                EINVAL
+            } else if stack.flags.contains(SignalStackFlags::DISABLE) {
+                inner.sig_altstack = SignalStack::disabled();
+                0
+            } else if stack.size < MINSIGSTKSZ {
+                ENOMEM
+            } else {
+                inner.sig_altstack = stack;
+                0
+            }
+        } else {
+            0
+        };
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
+    // 取出集合中编号最小的等待信号 线程的信号优先于进程的信号
+    fn take_signal(&self, set: &SigSet) -> Option<SigInfo> {
+        let mut inner = self.inner.borrow_mut();
+        let mask = set.complement();
+        match inner.pending.take_unmasked(&mask) {
+            Some(info) => Some(info),
+            None => inner.process.borrow_mut().pending.take_unmasked(&mask)
+        }
+    }
+
+    // 获取被屏蔽的等待信号
+    pub fn sys_sigpending(&self, set: UserAddr<SigSet>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow();
+        let pending = inner.pending.set.union(&process.pending.set).intersect(&inner.sig_mask);
+        drop(process);
+        inner.context.x[10] = if set.is_valid() {
+            *set.transfer() = pending;
+            0
+        } else {
+            EFAULT
+        };
+        Ok(())
+    }
+
+    // 临时替换信号掩码并挂起 直到进入信号处理函数或者进程结束 总是返回EINTR
+    pub fn sys_sigsuspend(&self, mask: UserAddr<SigSet>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        if !mask.is_valid() {
+            inner.context.x[10] = EFAULT;
+            return Ok(());
+        }
+        let mut mask = *mask.transfer();
+        mask.remove_unblockable();
+        inner.saved_mask = Some(inner.sig_mask);
+        inner.sig_mask = mask;
+        inner.context.x[10] = EINTR;
+        // 已经存在可以处理的信号时直接返回 返回用户态之前进入处理函数
+        let process = inner.process.borrow();
+        let pending = process.unignored(inner.pending.set.union(&process.pending.set)).unmasked(&mask);
+        drop(process);
+        drop(inner);
+        if pending.is_empty() {
+            wait_current(WaitEvent::Signal(SigSet::default()), None);
+        }
+        Ok(())
+    }
+
+    // 同步等待集合中的信号 返回信号编号 超时返回EAGAIN
+    pub fn sys_sigtimedwait(&self, set: UserAddr<SigSet>, info: UserAddr<SigInfo>,
+            timeout: UserAddr<TimeSpec>) -> Result<(), RuntimeError> {
+        if !set.is_valid() {
+            self.update_context(|x| x.x[10] = EFAULT);
+            return Ok(());
+        }
+        let mut set = *set.transfer();
+        set.remove_unblockable();
+        let ret = match self.take_signal(&set) {
+            Some(siginfo) => {
+                if info.is_valid() {
+                    *info.transfer() = siginfo;
+                }
+                siginfo.signo()
+            }
+            None => {
+                let timeout = match timeout.is_valid() {
+                    true => Some(timeout.transfer().to_us()),
+                    false => None
+                };
+                if timeout == Some(0) {
+                    EAGAIN
+                } else {
+                    // 超时返回EAGAIN 等待的信号到达时重新执行系统调用取出信号
+                    self.update_context(|x| x.x[10] = EAGAIN);
+                    wait_current(WaitEvent::Signal(set), timeout.map(|x| get_time_us() + x));
+                    return Ok(());
+                }
+            }
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 发送附带数据的信号 用户只能发送si_code为负数的信号给其他进程
+    pub fn sys_sigqueueinfo(&self, pid: usize, signum: usize, uinfo: UserAddr<SigInfo>) -> Result<(), RuntimeError> {
+        let ret = if signum >= SIGNAL_COUNT {
+            EINVAL
+        } else if !uinfo.is_valid() {
+            EFAULT
+        } else {
+            let mut info = *uinfo.transfer();
+            info.si_signo = signum as i32;
+            if (info.si_code >= 0 || info.si_code == SI_TKILL) && pid != self.pid {
+                EPERM
+            } else if signum == 0 {
+                // 信号为0时只检查进程是否存在
+                match get_processes().iter().any(|x| x.borrow().pid == pid) {
+                    true => 0,
+                    false => ESRCH
+                }
+            } else if signal_process(pid, info) {
+                0
+            } else {
+                ESRCH
+            }
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 创建signalfd fd为-1时创建新的文件描述符 否则修改已有signalfd的信号集合
+    pub fn sys_signalfd4(&self, fd: usize, mask: UserAddr<SigSet>, sizemask: usize, flags: usize) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        if !mask.is_valid() || sizemask != size_of::<SigSet>() || flags & !(SFD_NONBLOCK | SFD_CLOEXEC) != 0 {
+            inner.context.x[10] = EINVAL;
+            return Ok(());
+        }
+        // SIGKILL和SIGSTOP不能通过signalfd读取
+        let mut mask = *mask.transfer();
+        mask.remove_unblockable();
+        let mut process = inner.process.borrow_mut();
+        let ret = if fd as isize == -1 {
+            let signalfd = SignalFd::new(mask, flags & SFD_NONBLOCK != 0);
+            process.fd_table.push(FileDesc::new(Rc::new(signalfd)))
+        } else {
+            match process.fd_table.get(fd)?.downcast::<SignalFd>() {
+                Ok(signalfd) => {
+                    *signalfd.mask.borrow_mut() = mask;
+                    fd
+                }
+                Err(_) => EINVAL
+            }
+        };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
+    // 从signalfd读取信号 每个信号为一个signalfd_siginfo 没有信号时挂起等待
+    pub fn read_signalfd(&self, signalfd: Rc<SignalFd>, buf: &mut [u8]) -> Result<(), RuntimeError> {
+        let size = size_of::<SignalfdSigInfo>();
+        if buf.len() < size {
+            self.update_context(|x| x.x[10] = EINVAL);
+            return Ok(());
+        }
+        let mask = *signalfd.mask.borrow();
+        let mut read_size = 0;
+        while read_size + size <= buf.len() {
+            let info = match self.take_signal(&mask) {
+                Some(info) => info,
+                None => break
+            };
+            let ssi = SignalfdSigInfo::from(&info);
+            let bytes = unsafe {
+                core::slice::from_raw_parts(&ssi as *const SignalfdSigInfo as *const u8, size)
+            };
+            buf[read_size..read_size + size].copy_from_slice(bytes);
+            read_size += size;
+        }
+        if read_size == 0 {
+            if signalfd.nonblock {
+                self.update_context(|x| x.x[10] = EAGAIN);
+            } else {
+                // 信号到达时重新执行read
+                self.update_context(|x| x.x[10] = EINTR);
+                wait_current(WaitEvent::Signal(mask), None);
+            }
+            return Ok(());
+        }
+        self.inner.borrow_mut().context.x[10] = read_size;
+        Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,19 +1,18 @@
-use crate::{task::{task::Task, task_scheduler::get_task}, runtime_err::RuntimeError, sys_call::{remove_vfork_wait, SYS_CALL_ERR}, memory::page::get_free_page_num};
+use alloc::vec::Vec;
+
+use crate::{task::{task::Task, task_scheduler::{get_task, get_processes, signal_process, signal_task}, signal::{SIGNAL_COUNT, SigInfo, SI_USER, SI_TKILL}, process::{INIT_PID, exit_status}}, runtime_err::RuntimeError, sys_call::consts::{EINVAL, ESRCH}, memory::page::get_free_page_num};
 
 impl Task {
     /// 退出当前任务 
     pub fn sys_exit(&self, exit_code: usize) -> Result<(), RuntimeError> {
+        // 在释放内存之前清除child_tid 唤醒join的线程
+        self.do_clear_child_tid();
         let inner = self.inner.borrow();
         if self.tid == 0 {
-            inner.process.borrow_mut().exit(exit_code);
+            inner.process.borrow_mut().exit(exit_status(exit_code));
         } else {
             self.exit();
         }
-
-        let clear_child_tid = self.clear_child_tid.borrow().clone();
-        if clear_child_tid.is_valid() {
-            *clear_child_tid.transfer() = 0;
-        }
         Err(RuntimeError::KillCurrentTask)
     }
     
@@ -22,69 +21,62 @@ impl Task {
         let inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
         debug!("exit pid: {}", self.pid);
-        process.exit(exit_code);
-        match &process.parent {
-            Some(parent) => {
-                let parent = parent.upgrade().unwrap();
-                let parent = parent.borrow();
-                remove_vfork_wait(parent.pid);
-
-                // let end: UserAddr<TimeSpec> = 0x10bb78.into();
-                // let start: UserAddr<TimeSpec> = 0x10bad0.into();
-
-                // println!("start: {:?}   end: {:?}",start.transfer(), end.transfer());
-
-                // let target_end: UserAddr<TimeSpec> = parent.pmm.get_phys_addr(0x10bb78usize.into())?.0.into();
-                // let target_start: UserAddr<TimeSpec> = parent.pmm.get_phys_addr(0x10bad0usize.into())?.0.into();
-                // *target_start.transfer() = *start.transfer();
-                // *target_end.transfer() = *end.transfer();
-
-                // let task = parent.tasks[0].clone().upgrade().unwrap();
-                // drop(parent);
-                // // 处理signal 17 SIGCHLD
-                // task.signal(17);
-            }
-            None => {}
-        }
+        process.exit(exit_status(exit_code));
         debug!("剩余页表: {}", get_free_page_num());
         debug!("exit_code: {:#x}", exit_code);
         Err(RuntimeError::ChangeTask)
     }
 
-    // kill task
-    pub fn sys_kill(&self, _pid: usize, _signum: usize) -> Result<(), RuntimeError> {
-        let mut inner = self.inner.borrow_mut();
-        debug!(
-            "kill: thread {} kill process {} with signal {:?}",
-            0,
-            _pid,
-            _signum
-        );
-
-        inner.context.x[10] = 0;
+    // 发送信号 pid大于0时发送给进程 为0时发送给当前进程组
+    // 为-1时发送给除了初始进程和当前进程之外的所有进程 小于-1时发送给进程组-pid
+    pub fn sys_kill(&self, pid: usize, signum: usize) -> Result<(), RuntimeError> {
+        let pid = pid as isize;
+        debug!("kill: thread {} kill process {} with signal {:?}", self.tid, pid, signum);
+        if signum >= SIGNAL_COUNT {
+            self.update_context(|x| x.x[10] = EINVAL);
+            return Ok(());
+        }
+        let pgid = self.inner.borrow().process.borrow().pgid;
+        let targets: Vec<usize> = match pid {
+            pid if pid > 0 => vec![pid as usize],
+            _ => get_processes().iter().map(|x| x.borrow()).filter(|x| match pid {
+                0 => x.pgid == pgid,
+                -1 => x.pid != self.pid && x.pid != INIT_PID,
+                pid => x.pgid == (-pid) as usize
+            }).map(|x| x.pid).collect()
+        };
+        // 信号为0时只检查进程是否存在
+        let mut found = false;
+        for target in targets {
+            found |= match signum {
+                0 => get_processes().iter().any(|x| x.borrow().pid == target),
+                _ => signal_process(target, SigInfo::from_process(signum, SI_USER, self.pid))
+            };
+        }
+        let ret = if found { 0 } else { ESRCH };
+        self.inner.borrow_mut().context.x[10] = ret;
         Ok(())
     }
 
-    // kill task
+    // 向当前进程中的线程发送信号
     pub fn sys_tkill(&self, tid: usize, signum: usize) -> Result<(), RuntimeError> {
-        let mut inner = self.inner.borrow_mut();
-        inner.context.x[10] = 0;
-        let signal_task = get_task(self.pid, tid);
         debug!("signum: {}", signum);
-        if let Some(signal_task) = signal_task {
-            drop(inner);
-            signal_task.signal(signum)?;
-        }
-        Ok(())
+        self.sys_tgkill(self.pid, tid, signum)
     }
 
+    // 向线程发送信号
     pub fn sys_tgkill(&self, tgid: usize, tid: usize, signum: usize) -> Result<(), RuntimeError> {
         debug!("tgkill: tgid: {}  tid: {}  signum {}", tgid, tid, signum);
-        if let Some(task) = get_task(tgid, tid) {
-            task.signal(signum)?;
+        let ret = if signum >= SIGNAL_COUNT {
+            EINVAL
+        } else if signum == 0 {
+            if get_task(tgid, tid).is_some() { 0 } else { ESRCH }
+        } else if signal_task(tgid, tid, SigInfo::from_process(signum, SI_TKILL, self.pid)) {
+            0
         } else {
-            self.update_context(|x| x.x[10] = SYS_CALL_ERR);
-        }
+            ESRCH
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
         Ok(())
     }
     
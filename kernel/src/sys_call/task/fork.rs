@@ -1,7 +1,8 @@
 use alloc::{string::String, vec::Vec, rc::Rc};
-use k210_pac::uart1::tar;
 
-use crate::{runtime_err::RuntimeError, sys_call::{SYS_CALL_ERR, CloneFlags, add_vfork_wait}, memory::{addr::UserAddr, page_table::switch_to_kernel_page}, task::{exec_with_process, task_scheduler::{get_task_num, add_task_to_scheduler}, task::{Task, TaskStatus}, pid::get_next_pid, process::Process}};
+use crate::{runtime_err::RuntimeError, sys_call::{SYS_CALL_ERR, CloneFlags}, memory::{addr::UserAddr, page_table::switch_to_kernel_page}, task::{exec_with_process, task_scheduler::{get_task_num, add_task_to_scheduler, wait_current, wake_up}, wait_queue::WaitEvent, task::{Task, TaskStatus}, pid::get_next_pid, process::Process, signal::SignalStack}};
+
+const WNOHANG: usize = 1;
 
 impl Task {
 
@@ -11,41 +12,8 @@ impl Task {
         Err(RuntimeError::ChangeTask)
     }
     
-    // fork process
-    pub fn sys_fork(&self) -> Result<(), RuntimeError> {
-        let mut inner = self.inner.borrow_mut();
-        let process = inner.process.clone();
-        let mut process = process.borrow_mut();
-
-        let (child_process, child_task) =
-            Process::new(get_next_pid(), Some(Rc::downgrade(&inner.process)))?;
-        process.children.push(child_process.clone());
-        let mut child_task_inner = child_task.inner.borrow_mut();
-        child_task_inner.context.clone_from(&inner.context);
-        child_task_inner.context.x[10] = 0;
-        drop(child_task_inner);
-        add_task_to_scheduler(child_task.clone());
-        let cpid = child_task.pid;
-        inner.context.x[10] = cpid;
-        let mut child_process = child_process.borrow_mut();
-        child_process.mem_set = process.mem_set.clone_with_data()?;
-        child_process.stack = process.stack.clone_with_data(child_process.pmm.clone())?;
-        // 复制fd_table
-        child_process.fd_table = process.fd_table.clone();
-        // 创建新的heap
-        // child_process.heap = UserHeap::new(child_process.pmm.clone())?;
-        child_process.heap = process.heap.clone_with_data(child_process.pmm.clone())?;
-        debug!("heap_pointer: {:#x}", child_process.heap.get_heap_top());
-        child_process.pmm.add_mapping_by_set(&child_process.mem_set)?;
-        drop(process);
-        drop(child_process);
-        drop(inner);
-        // Ok(())
-        Err(RuntimeError::ChangeTask)
-    }
-
-    pub fn sys_spec_fork(&self, flags: usize, _new_sp: usize, _ptid: UserAddr<u32>, _tls: usize, ctid_ptr: UserAddr<u32>) -> Result<(), RuntimeError>{
-        // return self.sys_fork();
+    // fork process 父子进程通过写时复制共享内存
+    pub fn sys_fork(&self, flags: usize, new_sp: usize, ptid: UserAddr<u32>, ctid_ptr: UserAddr<u32>) -> Result<(), RuntimeError> {
         let flags = CloneFlags::from_bits_truncate(flags);
         let mut inner = self.inner.borrow_mut();
         let process = inner.process.clone();
@@ -53,38 +21,54 @@ impl Task {
         let cpid = get_next_pid();
         let (child_process, child_task) =
             Process::fork(cpid, process.clone())?;
-        
+
         let mut process = process.borrow_mut();
         process.children.push(child_process.clone());
+        // 共享信号量的撤销记录
+        if flags.contains(CloneFlags::CLONE_SYSVSEM) {
+            child_process.borrow_mut().sem_undo = process.sem_undo.clone();
+        }
 
         let mut child_task_inner = child_task.inner.borrow_mut();
         child_task_inner.context.clone_from(&inner.context);
         child_task_inner.context.x[10] = 0;
+        // 子进程继承信号掩码和信号栈
+        child_task_inner.sig_mask = inner.sig_mask;
+        child_task_inner.sig_altstack = inner.sig_altstack;
+        if new_sp != 0 {
+            child_task_inner.context.x[2] = new_sp;
+        }
         drop(child_task_inner);
+        *child_task.sched.borrow_mut() = self.sched.borrow().fork();
 
         add_task_to_scheduler(child_task.clone());
-
-        let mut child_process = child_process.borrow_mut();
-        child_process.stack = process.stack.clone_with_data(child_process.pmm.clone())?;
-        // child_process.heap = process.heap.clone_with_data(child_process.pmm.clone())?;
         inner.context.x[10] = cpid;
 
-        if flags.contains(CloneFlags::CLONE_CHILD_SETTID) {
-            *ctid_ptr.transfer() = cpid as u32;
+        if flags.contains(CloneFlags::CLONE_PARENT_SETTID) && ptid.is_valid() {
+            *ptid.transfer() = cpid as u32;
+        }
+
+        // 写入子进程的地址空间
+        if flags.contains(CloneFlags::CLONE_CHILD_SETTID) && ctid_ptr.is_valid() {
+            let child_process = child_process.borrow();
+            let ctid = child_process.pmm.get_writable_phys_addr(ctid_ptr.bits().into())?;
+            *ctid.tranfer::<u32>() = cpid as u32;
         }
 
         if flags.contains(CloneFlags::CLONE_CHILD_CLEARTID) {
-            
+            child_task.set_tid_address(ctid_ptr);
         }
 
         drop(process);
-        drop(child_process);
         drop(inner);
-        // Ok(())
-        add_vfork_wait(self.pid);
+        if flags.contains(CloneFlags::CLONE_VFORK) {
+            // 父进程等待子进程退出或者执行新的程序
+            wait_current(WaitEvent::VFork(cpid), None);
+            return Ok(());
+        }
         Err(RuntimeError::ChangeTask)
     }
-    
+
     // clone task
     pub fn sys_clone(&self, flags: usize, new_sp: usize, ptid: UserAddr<u32>, tls: usize, ctid_ptr: UserAddr<u32>) -> Result<(), RuntimeError> {
         // let flags = flags & 0x4fff;
@@ -92,13 +76,10 @@ impl Task {
             "clone: flags={:#x}, newsp={:#x}, parent_tid={:#x}, child_tid={:#x}, newtls={:#x}",
             flags, new_sp, ptid.bits(), ctid_ptr.0 as usize, tls
         );
-        if flags == 0x4111 || flags == 0x11 {
-            // VFORK | VM | SIGCHILD
-            warn!("sys_clone is calling sys_fork instead, ignoring other args");
-            return self.sys_fork();
-        } else if flags == 0x1200011 {
-            return self.sys_spec_fork(0x11, new_sp, ptid, tls, ctid_ptr);
-            // return self.sys_fork();
+        let clone_flags = CloneFlags::from_bits_truncate(flags);
+        if !clone_flags.contains(CloneFlags::CLONE_VM) || clone_flags.contains(CloneFlags::CLONE_VFORK) {
+            // 不共享地址空间时创建新的进程
+            return self.sys_fork(flags, new_sp, ptid, ctid_ptr);
         }
 
         debug!(
@@ -119,6 +100,9 @@ impl Task {
         new_task_inner.context.x[2] = new_sp;
         new_task_inner.context.x[4] = tls;
         new_task_inner.context.x[10] = 0;
+        // 线程继承信号掩码 不继承信号栈
+        new_task_inner.sig_mask = inner.sig_mask;
+        *new_task.sched.borrow_mut() = self.sched.borrow().fork();
         add_task_to_scheduler(new_task.clone());
         // 添加到process
         inner.context.x[10] = ctid;
@@ -140,7 +124,7 @@ impl Task {
     // 执行文件
     pub fn sys_execve(&self, filename: UserAddr<u8>, argv: UserAddr<UserAddr<u8>>, 
             _envp: UserAddr<UserAddr<u8>>) -> Result<(), RuntimeError> {
-        let inner = self.inner.borrow_mut();
+        let mut inner = self.inner.borrow_mut();
         let mut process = inner.process.borrow_mut();
         let filename = filename.read_string();
 
@@ -155,19 +139,25 @@ impl Task {
         // 获取 envp
         let task = process.tasks[self.tid].clone().upgrade().unwrap();
         process.reset()?;
+        // 执行新程序时删除POSIX定时器 间隔定时器保留 捕获的信号恢复默认行为
+        process.delete_timers();
+        process.reset_sig_actions();
         drop(process);
+        inner.sig_altstack = SignalStack::disabled();
         let process = inner.process.clone();
         drop(inner);
         switch_to_kernel_page();
         exec_with_process(process.clone(), task, &filename, args.iter().map(AsRef::as_ref).collect())?;
         // process.borrow_mut().new_heap()?;
         self.before_run();
+        // vfork的父进程在子进程执行新程序后继续运行
+        wake_up(WaitEvent::VFork(self.pid));
         Ok(())
     }
     
     // wait task
-    pub fn sys_wait4(&self, pid: usize, ptr: UserAddr<i32>, _options: usize) -> Result<(), RuntimeError> {
-        debug!("pid: {:#x}, ptr: {:#x}, _options: {}", pid, ptr.bits(), _options);
+    pub fn sys_wait4(&self, pid: usize, ptr: UserAddr<i32>, options: usize) -> Result<(), RuntimeError> {
+        debug!("pid: {:#x}, ptr: {:#x}, options: {}", pid, ptr.bits(), options);
         let mut inner = self.inner.borrow_mut();
         let process = inner.process.clone();
         let mut process = process.borrow_mut();
@@ -226,13 +216,12 @@ impl Task {
                 return Ok(());
             }
 
-            let cprocess_vec = 
-                process.children.drain_filter(|x| x.borrow().exit_code.is_some()).collect::<Vec<_>>();
+            // 每次只回收一个已经退出的子进程
+            let index = process.children.iter().position(|x| x.borrow().exit_code.is_some());
 
-            debug!("cpro len: {}", cprocess_vec.len());
-
-            if cprocess_vec.len() != 0 {
-                let cprocess = cprocess_vec[0].borrow();
+            if let Some(index) = index {
+                let cprocess = process.children.remove(index);
+                let cprocess = cprocess.borrow();
                 if ptr.is_valid() {
                     *ptr.transfer() = cprocess.exit_code.unwrap() as i32;
                 }
@@ -240,9 +229,16 @@ impl Task {
                 return Ok(());
             }
         }
+        // WNOHANG 没有退出的子进程直接返回
+        if options & WNOHANG != 0 {
+            inner.context.x[10] = 0;
+            return Ok(());
+        }
+        // 挂起等待子进程退出 唤醒后重新执行wait4
         inner.context.sepc -= 4;
         drop(process);
         drop(inner);
-        Err(RuntimeError::ChangeTask)
+        wait_current(WaitEvent::Child(self.pid), None);
+        Ok(())
     }
 }
\ No newline at end of file
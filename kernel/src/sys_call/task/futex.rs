@@ -1,69 +1,118 @@
-use crate::{memory::addr::UserAddr, task::task::Task, runtime_err::RuntimeError};
+use crate::{memory::{addr::UserAddr, vma::PageFaultType}, task::{task::Task, task_scheduler::{wait_current, wake_up_count, wait_requeue}, wait_queue::WaitEvent}, runtime_err::RuntimeError, interrupt::timer::{get_time_us, TimeSpec}, sys_call::consts::{EAGAIN, EFAULT, EINVAL, ENOSYS, ETIMEDOUT}};
 
-bitflags! {
-    struct FutexFlags: u32 {
-        const WAIT      = 0;
-        const WAKE      = 1;
-        const REQUEUE   = 3;
-        const FUTEX_WAKE_OP = 5;
-        const LOCK_PI   = 6;
-        const UNLOCK_PI = 7;
-        const PRIVATE   = 0x80;
+const FUTEX_WAIT: u32 = 0;
+const FUTEX_WAKE: u32 = 1;
+const FUTEX_REQUEUE: u32 = 3;
+const FUTEX_CMP_REQUEUE: u32 = 4;
+const FUTEX_PRIVATE: u32 = 0x80;
+const FUTEX_CLOCK_REALTIME: u32 = 0x100;
+
+// 唤醒futex上的任务 被唤醒的任务返回0
+pub fn futex_wake(key: WaitEvent, count: usize) -> usize {
+    let tasks = wake_up_count(key, count);
+    for task in &tasks {
+        task.inner.borrow_mut().context.x[10] = 0;
     }
+    tasks.len()
 }
 
 impl Task {
+    // 获取futex的等待事件 私有futex使用(页表, 地址) 共享futex使用物理地址
+    // 地址需要4字节对齐并且位于可以读取的区域中
+    pub fn futex_key(&self, uaddr: usize, private: bool) -> Result<WaitEvent, usize> {
+        let inner = self.inner.borrow();
+        let process = inner.process.borrow();
+        if uaddr % 4 != 0 {
+            return Err(EINVAL);
+        }
+        if !process.vmas.find(uaddr).map_or(false, |x| x.allow(PageFaultType::Load)) {
+            return Err(EFAULT);
+        }
+        // 页面可能尚未分配 先处理缺页
+        process.pmm.handle_page_fault(&process.vmas, uaddr, PageFaultType::Load).map_err(|_| EFAULT)?;
+        if private {
+            Ok(WaitEvent::Futex(process.pmm.pte.0, uaddr))
+        } else {
+            let paddr = process.pmm.get_phys_addr(uaddr.into()).map_err(|_| EFAULT)?;
+            Ok(WaitEvent::Futex(0, paddr.0))
+        }
+    }
+
+    // 线程退出时清除child_tid 并唤醒等待的线程
+    pub fn do_clear_child_tid(&self) {
+        let clear_child_tid = self.clear_child_tid.borrow().clone();
+        if !clear_child_tid.is_valid() {
+            return;
+        }
+        *clear_child_tid.transfer() = 0;
+        // 用户态可能使用私有或者共享的方式等待
+        if let Ok(key) = self.futex_key(clear_child_tid.bits(), true) {
+            futex_wake(key, 1);
+        }
+        if let Ok(key) = self.futex_key(clear_child_tid.bits(), false) {
+            futex_wake(key, 1);
+        }
+    }
+
     // wait for futex
-    pub fn sys_futex(&self, uaddr: UserAddr<i32>, op: u32, value: i32, _value2: usize, _value3: usize) -> Result<(), RuntimeError> {
+    pub fn sys_futex(&self, uaddr: UserAddr<i32>, op: u32, value: i32, value2: usize,
+            uaddr2: UserAddr<i32>, value3: i32) -> Result<(), RuntimeError> {
         debug!("sys_futex uaddr: {:#x} op: {:#x} value: {:#x}", uaddr.bits(), op, value);
-        // let uaddr_ref = uaddr.transfer();
-        // let op = FutexFlags::from_bits_truncate(op);
-        // let mut inner = self.inner.borrow_mut();
-        // let process = inner.process.borrow_mut();
+        let private = op & FUTEX_PRIVATE != 0;
+        let cmd = op & !(FUTEX_PRIVATE | FUTEX_CLOCK_REALTIME);
 
-        // let op = op - FutexFlags::PRIVATE;
-        // debug!(
-        //     "Futex uaddr: {:#x}, op: {:?}, val: {:#x}, val2(timeout_addr): {:x}",
-        //     uaddr.bits(), op, value, value2,
-        // );
-        // match op {
-        //     FutexFlags::WAIT => {
-        //         if *uaddr_ref == value {
-        //             drop(process);
-        //             debug!("等待进程");
-        //             inner.context.x[10] = 0;
-        //             inner.status = TaskStatus::WAITING;
-        //             drop(inner);
-        //             // futex_wait(uaddr.bits());
-        //             switch_next();
-        //         } else {
-        //             // *uaddr_value -= 1;
-        //             drop(process);
-        //             inner.context.x[10] = 0;
-        //         }
-        //     },
-        //     FutexFlags::WAKE => {
-        //         // *uaddr_value = -1;
-        //         drop(process);
-        //         debug!("debug for ");
-        //         // 值为唤醒的线程数
-        //         // let count = futex_wake(uaddr.bits(), value as usize);
-        //         // inner.context.x[10] = count;
-        //         // debug!("wake count : {}", count);
-        //         drop(inner);
-        //         switch_next();
-        //     }
-        //     FutexFlags::REQUEUE => {
-        //         drop(process);
-        //         inner.context.x[10] = 0;
+        let key = match self.futex_key(uaddr.bits(), private) {
+            Ok(key) => key,
+            Err(err) => {
+                self.inner.borrow_mut().context.x[10] = err;
+                return Ok(());
+            }
+        };
 
-        //     }
-        //     _ => todo!(),
-        // }
-        // if op.contains(FutexFlags::WAKE) {
-        //     // *uaddr_value = 0;
-        //     // futex_requeue(uaddr.bits(), value as u32, value2, value3 as u32);
-        // }
+        let ret = match cmd {
+            FUTEX_WAIT => {
+                // 值不相同说明已经被修改 直接返回
+                if *uaddr.transfer() != value {
+                    EAGAIN
+                } else {
+                    // value2 为相对超时时间
+                    let timeout_ptr: UserAddr<TimeSpec> = value2.into();
+                    let timeout = match timeout_ptr.is_valid() {
+                        true => {
+                            let timeout = timeout_ptr.transfer();
+                            Some(get_time_us() + timeout.to_us())
+                        }
+                        false => None
+                    };
+                    wait_current(key, timeout);
+                    // 超时后返回ETIMEDOUT 被唤醒时由唤醒者设置为0
+                    match timeout {
+                        Some(_) => ETIMEDOUT,
+                        None => 0
+                    }
+                }
+            }
+            FUTEX_WAKE => futex_wake(key, value as usize),
+            FUTEX_REQUEUE | FUTEX_CMP_REQUEUE => {
+                if cmd == FUTEX_CMP_REQUEUE && *uaddr.transfer() != value3 {
+                    EAGAIN
+                } else {
+                    match self.futex_key(uaddr2.bits(), private) {
+                        // 唤醒value个任务 剩余的最多value2个转移到uaddr2
+                        Ok(key2) => {
+                            let count = futex_wake(key, value as usize);
+                            count + wait_requeue(key, key2, value2)
+                        }
+                        Err(err) => err
+                    }
+                }
+            }
+            _ => {
+                warn!("不支持的futex操作: {:#x}", op);
+                ENOSYS
+            }
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
         Ok(())
-    }    
-}
\ No newline at end of file
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{runtime_err::RuntimeError, sys_call::{SYS_CALL_ERR, UTSname}, task::task::{Task, Rusage}, memory::addr::UserAddr, interrupt::timer::TimeSpec};
+use crate::{runtime_err::RuntimeError, sys_call::{SYS_CALL_ERR, UTSname, consts::{EINVAL, ESRCH}}, task::{task::{Task, Rusage}, task_scheduler::get_processes}, memory::addr::UserAddr, interrupt::timer::TimeSpec};
 
 impl Task {
     // 获取系统信息
@@ -39,6 +39,36 @@ impl Task {
         Ok(())
     }
     
+    // 设置进程组 pid和pgid为0时表示当前进程
+    pub fn sys_setpgid(&self, pid: usize, pgid: usize) -> Result<(), RuntimeError> {
+        let pid = if pid == 0 { self.pid } else { pid };
+        let pgid = if pgid == 0 { pid } else { pgid };
+        let ret = if (pgid as isize) < 0 {
+            EINVAL
+        } else {
+            match get_processes().iter().find(|x| x.borrow().pid == pid) {
+                Some(process) => {
+                    process.borrow_mut().pgid = pgid;
+                    0
+                }
+                None => ESRCH
+            }
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 获取进程组 pid为0时表示当前进程
+    pub fn sys_getpgid(&self, pid: usize) -> Result<(), RuntimeError> {
+        let pid = if pid == 0 { self.pid } else { pid };
+        let ret = match get_processes().iter().find(|x| x.borrow().pid == pid) {
+            Some(process) => process.borrow().pgid,
+            None => ESRCH
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
     // 获取父id
     pub fn sys_getppid(&self) -> Result<(), RuntimeError> {
         let mut inner = self.inner.borrow_mut();
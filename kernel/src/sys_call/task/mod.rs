@@ -1,4 +1,5 @@
 pub mod exit;
 pub mod futex;
 pub mod info;
-pub mod fork;
\ No newline at end of file
+pub mod fork;
+pub mod sched;
\ No newline at end of file
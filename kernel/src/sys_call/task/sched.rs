@@ -0,0 +1,193 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::memory::addr::UserAddr;
+use crate::runtime_err::RuntimeError;
+use crate::sys_call::consts::{EINVAL, ESRCH};
+use crate::task::sched::{NICE_MAX, NICE_MIN, RT_PRIO_MAX, RT_PRIO_MIN,
+    SCHED_BATCH, SCHED_FIFO, SCHED_IDLE, SCHED_OTHER, SCHED_RR};
+use crate::task::task::Task;
+use crate::task::task_scheduler::{get_current_task, get_process_tasks};
+
+// setpriority/getpriority 的 which 参数
+const PRIO_PROCESS: usize = 0;
+const PRIO_PGRP: usize = 1;
+const PRIO_USER: usize = 2;
+
+// 目前只有启动核心进行调度
+const SCHED_CPU_MASK: usize = 1;
+
+impl Task {
+    // 获取pid对应的任务 pid为0时为当前任务
+    fn sched_targets(&self, pid: usize) -> Vec<Rc<Task>> {
+        if pid == 0 || pid == self.pid {
+            // 当前任务在准备队列的头部
+            match get_current_task() {
+                Some(task) if pid == 0 => vec![task],
+                _ => get_process_tasks(self.pid)
+            }
+        } else {
+            get_process_tasks(pid)
+        }
+    }
+
+    // 获取setpriority/getpriority作用的任务 没有进程组和用户的概念 均作用于当前进程
+    fn prio_targets(&self, which: usize, who: usize) -> Option<Vec<Rc<Task>>> {
+        match which {
+            PRIO_PROCESS => Some(self.sched_targets(who)),
+            PRIO_PGRP | PRIO_USER => Some(get_process_tasks(self.pid)),
+            _ => None
+        }
+    }
+
+    // 设置nice值
+    pub fn sys_setpriority(&self, which: usize, who: usize, nice: isize) -> Result<(), RuntimeError> {
+        let ret = match self.prio_targets(which, who) {
+            Some(tasks) if tasks.len() > 0 => {
+                let nice = nice.max(NICE_MIN).min(NICE_MAX);
+                for task in tasks {
+                    task.sched.borrow_mut().nice = nice;
+                }
+                0
+            }
+            Some(_) => ESRCH,
+            None => EINVAL
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 获取nice值 系统调用返回 20 - nice 避免返回负数
+    pub fn sys_getpriority(&self, which: usize, who: usize) -> Result<(), RuntimeError> {
+        let ret = match self.prio_targets(which, who) {
+            Some(tasks) => {
+                match tasks.iter().map(|x| x.sched.borrow().nice).min() {
+                    Some(nice) => (20 - nice) as usize,
+                    None => ESRCH
+                }
+            }
+            None => EINVAL
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 设置调度策略和优先级
+    fn set_scheduler(&self, pid: usize, policy: Option<usize>, param: UserAddr<i32>) -> usize {
+        if !param.is_valid() {
+            return EINVAL;
+        }
+        let priority = *param.transfer();
+        let tasks = self.sched_targets(pid);
+        if tasks.len() == 0 {
+            return ESRCH;
+        }
+        let policy = policy.unwrap_or(tasks[0].sched.borrow().policy);
+        let valid = match policy {
+            SCHED_FIFO | SCHED_RR => priority >= RT_PRIO_MIN as i32 && priority <= RT_PRIO_MAX as i32,
+            SCHED_OTHER | SCHED_BATCH | SCHED_IDLE => priority == 0,
+            _ => false
+        };
+        if !valid {
+            return EINVAL;
+        }
+        for task in tasks {
+            let mut sched = task.sched.borrow_mut();
+            sched.policy = policy;
+            sched.rt_priority = priority as usize;
+            sched.slice_used = 0;
+        }
+        0
+    }
+
+    // 设置调度策略
+    pub fn sys_sched_setscheduler(&self, pid: usize, policy: usize, param: UserAddr<i32>) -> Result<(), RuntimeError> {
+        // 忽略 SCHED_RESET_ON_FORK 标志
+        let ret = self.set_scheduler(pid, Some(policy & !0x40000000), param);
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 设置调度优先级
+    pub fn sys_sched_setparam(&self, pid: usize, param: UserAddr<i32>) -> Result<(), RuntimeError> {
+        let ret = self.set_scheduler(pid, None, param);
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 获取调度策略
+    pub fn sys_sched_getscheduler(&self, pid: usize) -> Result<(), RuntimeError> {
+        let ret = match self.sched_targets(pid).first() {
+            Some(task) => task.sched.borrow().policy,
+            None => ESRCH
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 获取调度优先级
+    pub fn sys_sched_getparam(&self, pid: usize, param: UserAddr<i32>) -> Result<(), RuntimeError> {
+        let ret = match self.sched_targets(pid).first() {
+            Some(_) if !param.is_valid() => EINVAL,
+            Some(task) => {
+                *param.transfer() = task.sched.borrow().rt_priority as i32;
+                0
+            }
+            None => ESRCH
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 获取调度策略的优先级范围
+    pub fn sys_sched_get_priority(&self, policy: usize, max: bool) -> Result<(), RuntimeError> {
+        let ret = match policy {
+            SCHED_FIFO | SCHED_RR => if max { RT_PRIO_MAX } else { RT_PRIO_MIN },
+            SCHED_OTHER | SCHED_BATCH | SCHED_IDLE => 0,
+            _ => EINVAL
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 设置任务可以运行的核心
+    pub fn sys_sched_setaffinity(&self, pid: usize, len: usize, mask: UserAddr<usize>) -> Result<(), RuntimeError> {
+        let ret = if len < core::mem::size_of::<usize>() || !mask.is_valid() {
+            EINVAL
+        } else {
+            let mask = *mask.transfer();
+            let tasks = self.sched_targets(pid);
+            if tasks.len() == 0 {
+                ESRCH
+            } else if mask & SCHED_CPU_MASK == 0 {
+                // 不包含可以调度的核心
+                EINVAL
+            } else {
+                for task in tasks {
+                    task.sched.borrow_mut().affinity = mask;
+                }
+                0
+            }
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+
+    // 获取任务可以运行的核心 返回写入的字节数
+    pub fn sys_sched_getaffinity(&self, pid: usize, len: usize, mask: UserAddr<usize>) -> Result<(), RuntimeError> {
+        let size = core::mem::size_of::<usize>();
+        let ret = if len < size || !mask.is_valid() {
+            EINVAL
+        } else {
+            match self.sched_targets(pid).first() {
+                Some(task) => {
+                    *mask.transfer() = task.sched.borrow().affinity & SCHED_CPU_MASK;
+                    size
+                }
+                None => ESRCH
+            }
+        };
+        self.inner.borrow_mut().context.x[10] = ret;
+        Ok(())
+    }
+}
@@ -1,33 +1,59 @@
 use crate::runtime_err::RuntimeError;
 use crate::task::task::Task;
 use crate::task::fd_table::FD_CWD;
-use crate::interrupt::timer::{get_time_us, TimeSpec};
+use crate::interrupt::timer::{get_time_us, TimeSpec, TimeVal, ITimerVal, ITimerSpec};
 use crate::interrupt::timer::TMS;
-use crate::memory::addr::{VirtAddr, UserAddr};
+use crate::memory::addr::UserAddr;
 use crate::fs::filetree::INode;
 use crate::interrupt::timer::get_ticks;
+use crate::task::task_scheduler::wait_current;
+use crate::task::wait_queue::WaitEvent;
+use crate::task::signal::Signal;
+use crate::task::signal::SIGNAL_COUNT;
+use crate::task::itimer::ITIMER_PROF;
+use crate::sys_call::consts::EINVAL;
+
+// timer_create 支持的时钟
+const CLOCK_REALTIME: usize = 0;
+const CLOCK_MONOTONIC: usize = 1;
+const CLOCK_BOOTTIME: usize = 7;
+
+// timer_settime 的标志 value为绝对时间
+const TIMER_ABSTIME: usize = 1;
+
+// 定时器到期的通知方式
+const SIGEV_SIGNAL: i32 = 0;
+const SIGEV_NONE: i32 = 1;
+const SIGEV_THREAD_ID: i32 = 4;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SigEvent {
+    pub sigev_value: usize,         // 信号附带的值
+    pub sigev_signo: i32,           // 到期时发送的信号
+    pub sigev_notify: i32,          // 通知方式
+    pub sigev_tid: i32              // SIGEV_THREAD_ID 时通知的线程
+}
 
 impl Task {
-    pub fn sys_nanosleep(&self, req_ptr: UserAddr<TimeSpec>, _rem_ptr: VirtAddr) -> Result<(), RuntimeError> {
+    pub fn sys_nanosleep(&self, req_ptr: UserAddr<TimeSpec>, rem_ptr: UserAddr<TimeSpec>) -> Result<(), RuntimeError> {
         let req_time = req_ptr.transfer();
 
         let mut inner = self.inner.borrow_mut();
 
-        // 获取文件参数
-        if inner.wake_time == 0 {
-            inner.wake_time = get_time_us() + (req_time.tv_sec * 1000000) as usize + req_time.tv_nsec as usize;
-            inner.context.sepc -= 4;
-            return Ok(())
+        // 纳秒需要在0~999999999之间 秒不能为负数
+        if req_time.tv_nsec >= 1_000_000_000 || (req_time.tv_sec as isize) < 0 {
+            inner.context.x[10] = EINVAL;
+            return Ok(());
         }
-        let task_wake_time = inner.wake_time;
-
-        if get_time_us() > task_wake_time {
-            // 到达解锁时间
-            inner.wake_time = 0;
-        } else {
-            // 未到达解锁时间 重复执行
-            inner.context.sepc -= 4;
+        // 计算唤醒时间 挂起任务直到超时 被信号打断时在rem中写入剩余的时间
+        let wake_time = get_time_us() + req_time.to_us();
+        if rem_ptr.bits() != 0 {
+            inner.sleep_rem = Some((rem_ptr, wake_time));
         }
+        inner.context.x[10] = 0;
+        drop(inner);
+        wait_current(WaitEvent::Timer, Some(wake_time));
         Ok(())
     }
     
@@ -35,9 +61,7 @@ impl Task {
         let mut inner = self.inner.borrow_mut();
         let process = inner.process.borrow_mut();
         // 等待添加
-        let tms = usize::from(process.pmm.get_phys_addr(tms_ptr.into()).unwrap()) 
-            as *mut TMS;
-        let tms = unsafe { tms.as_mut().unwrap() };
+        let tms = UserAddr::<TMS>::from(tms_ptr).transfer();
     
         // 写入文件时间
         tms.tms_cstime = process.tms.tms_cstime;
@@ -50,11 +74,9 @@ impl Task {
     
     pub fn sys_gettimeofday(&self, ptr: usize) -> Result<(), RuntimeError> {
         let mut inner = self.inner.borrow_mut();
-        let process = inner.process.borrow_mut();
     
-        let timespec = usize::from(process.pmm.get_phys_addr(ptr.into()).unwrap()) as *mut TimeSpec;
-        unsafe { timespec.as_mut().unwrap().get_now() };
-        drop(process);
+        let timespec = UserAddr::<TimeSpec>::from(ptr).transfer();
+        timespec.get_now();
         inner.context.x[10] = 0;
         Ok(())
     }
@@ -74,6 +96,7 @@ impl Task {
         Ok(())
     }
 
+    // 修改文件的访问时间和修改时间
     pub fn sys_utimeat(&self, dir_fd: usize, filename: UserAddr<u8>, times_ptr: UserAddr<TimeSpec>, _flags: usize) -> Result<(), RuntimeError> {
         let mut inner = self.inner.borrow_mut();
         let process = inner.process.borrow_mut();
@@ -87,9 +110,6 @@ impl Task {
             file.get_inode()
         };
 
-        // 更新参数
-        let times = times_ptr.transfer_vec(2);
-
         if filename.bits() != 0 {
             let filename = filename.read_string();
             debug!("dir_fd: {:#x}, filename: {}, _flags: {:#x}", dir_fd, filename, _flags);
@@ -106,32 +126,193 @@ impl Task {
         const UTIME_NOW: usize = 0x3fffffff;
         const UTIME_OMIT: usize = 0x3ffffffe;
 
-        let _inode_inner = inode.0.borrow_mut();
+        // times为空时两个时间都设置为当前时间
+        let now = TimeSpec::now();
+        let (atime, mtime) = match times_ptr.is_valid() {
+            true => {
+                let times = times_ptr.transfer_vec(2);
+                let get_time = |time: TimeSpec| match time.tv_nsec {
+                    UTIME_OMIT => None,
+                    UTIME_NOW => Some(now),
+                    _ => Some(time)
+                };
+                (get_time(times[0]), get_time(times[1]))
+            }
+            false => (Some(now), Some(now))
+        };
+        // 不保存时间的文件系统忽略
+        let _ = inode.set_times(atime, mtime);
 
-        if times[0].tv_nsec as usize != UTIME_OMIT {
-            let _time = if times[0].tv_nsec as usize == UTIME_NOW {
-                TimeSpec::now()
-            } else {
-                times[0]
+        drop(process);
+        inner.context.x[10] = 0;
+        Ok(())
+    }
+
+    // 获取间隔定时器
+    pub fn sys_getitimer(&self, which: usize, curr_value: UserAddr<ITimerVal>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+
+        let ret = if which > ITIMER_PROF || !curr_value.is_valid() {
+            EINVAL
+        } else {
+            let (value, interval) = process.get_itimer(which);
+            *curr_value.transfer() = ITimerVal {
+                it_interval: TimeVal::from_us(interval),
+                it_value: TimeVal::from_us(value)
             };
+            0
+        };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
+    // 设置间隔定时器 alarm 由musl通过setitimer实现
+    pub fn sys_setitimer(&self, which: usize, new_value: UserAddr<ITimerVal>, old_value: UserAddr<ITimerVal>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let mut process = inner.process.borrow_mut();
+
+        let ret = if which > ITIMER_PROF || !new_value.is_valid() {
+            EINVAL
+        } else {
+            let new_value = *new_value.transfer();
+            if new_value.it_value.tv_usec >= 1_000_000 || new_value.it_interval.tv_usec >= 1_000_000 {
+                EINVAL
+            } else {
+                let (value, interval) = process.set_itimer(which,
+                    new_value.it_value.to_us(), new_value.it_interval.to_us());
+                if old_value.is_valid() {
+                    *old_value.transfer() = ITimerVal {
+                        it_interval: TimeVal::from_us(interval),
+                        it_value: TimeVal::from_us(value)
+                    };
+                }
+                0
+            }
+        };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
+    // 创建POSIX定时器 sevp为空时到期发送SIGALRM
+    pub fn sys_timer_create(&self, clock_id: usize, sevp: UserAddr<SigEvent>, timer_id: UserAddr<i32>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let mut process = inner.process.borrow_mut();
+
+        let signo = if sevp.is_valid() {
+            let sevp = sevp.transfer();
+            match sevp.sigev_notify {
+                SIGEV_NONE => Some((0, sevp.sigev_value)),
+                // SIGEV_THREAD_ID 同样发送给进程
+                SIGEV_SIGNAL | SIGEV_THREAD_ID => {
+                    let signo = sevp.sigev_signo as usize;
+                    if signo > 0 && signo < SIGNAL_COUNT { Some((signo, sevp.sigev_value)) } else { None }
+                }
+                _ => None
+            }
+        } else {
+            Some((Signal::SIGALRM as usize, 0))
+        };
 
-            // inode_inner.st_atime_sec = time.tv_sec;
-            // inode_inner.st_atime_nsec = time.tv_nsec as u64;
+        let ret = match signo {
+            Some((signo, value)) if timer_id.is_valid()
+                && [CLOCK_REALTIME, CLOCK_MONOTONIC, CLOCK_BOOTTIME].contains(&clock_id) => {
+                let id = process.create_timer(signo, value);
+                // sigevent为空时sigev_value为定时器编号
+                if !sevp.is_valid() {
+                    process.timers.posix_timers.get_mut(&id).unwrap().value = id;
+                }
+                *timer_id.transfer() = id as i32;
+                0
+            }
+            _ => EINVAL
         };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
 
-        if times[1].tv_nsec as usize != UTIME_OMIT {
-            let _time = if times[1].tv_nsec as usize == UTIME_NOW {
-                TimeSpec::now()
+    // 设置POSIX定时器
+    pub fn sys_timer_settime(&self, timer_id: usize, flags: usize, new_value: UserAddr<ITimerSpec>, old_value: UserAddr<ITimerSpec>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let mut process = inner.process.borrow_mut();
+
+        let ret = if !new_value.is_valid() {
+            EINVAL
+        } else {
+            let new_value = *new_value.transfer();
+            let value = new_value.it_value;
+            let deadline = if value.tv_sec == 0 && value.tv_nsec == 0 {
+                0
+            } else if flags & TIMER_ABSTIME != 0 {
+                // 已经过去的时间立即到期
+                value.to_us().max(get_time_us()).max(1)
             } else {
-                times[1]
+                get_time_us() + value.to_us().max(1)
             };
+            if value.tv_nsec >= 1_000_000_000 || new_value.it_interval.tv_nsec >= 1_000_000_000 {
+                EINVAL
+            } else {
+                match process.set_timer(timer_id, deadline, new_value.it_interval.to_us()) {
+                    Some((value, interval)) => {
+                        if old_value.is_valid() {
+                            *old_value.transfer() = ITimerSpec {
+                                it_interval: TimeSpec::from_us(interval),
+                                it_value: TimeSpec::from_us(value)
+                            };
+                        }
+                        0
+                    }
+                    None => EINVAL
+                }
+            }
+        };
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
 
-            // inode_inner.st_mtime_sec = time.tv_sec;
-            // inode_inner.st_mtime_nsec = time.tv_nsec as u64;
-        }
+    // 获取POSIX定时器
+    pub fn sys_timer_gettime(&self, timer_id: usize, curr_value: UserAddr<ITimerSpec>) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
 
+        let ret = match process.get_timer(timer_id) {
+            Some((value, interval)) if curr_value.is_valid() => {
+                *curr_value.transfer() = ITimerSpec {
+                    it_interval: TimeSpec::from_us(interval),
+                    it_value: TimeSpec::from_us(value)
+                };
+                0
+            }
+            _ => EINVAL
+        };
         drop(process);
-        inner.context.x[10] = 0;
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
+    // 获取POSIX定时器的超限次数
+    pub fn sys_timer_getoverrun(&self, timer_id: usize) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let process = inner.process.borrow_mut();
+
+        let ret = process.get_timer_overrun(timer_id).unwrap_or(EINVAL);
+        drop(process);
+        inner.context.x[10] = ret;
+        Ok(())
+    }
+
+    // 删除POSIX定时器
+    pub fn sys_timer_delete(&self, timer_id: usize) -> Result<(), RuntimeError> {
+        let mut inner = self.inner.borrow_mut();
+        let mut process = inner.process.borrow_mut();
+
+        let ret = if process.delete_timer(timer_id) { 0 } else { EINVAL };
+        drop(process);
+        inner.context.x[10] = ret;
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,178 @@
+use core::cell::RefCell;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::rc::Weak;
+use alloc::vec::Vec;
+
+use crate::fs::file::FileOP;
+use crate::fs::file::PollEvents;
+use crate::sync::mutex::Mutex;
+
+use super::wait_queue::WaitEvent;
+
+// epoll_create1 的标志
+pub const EPOLL_CLOEXEC: usize = 0o2000000;
+
+// epoll_ctl 的操作
+pub const EPOLL_CTL_ADD: usize = 1;
+pub const EPOLL_CTL_DEL: usize = 2;
+pub const EPOLL_CTL_MOD: usize = 3;
+
+// epoll_event 中的标志 低位的事件与poll相同
+pub const EPOLLEXCLUSIVE: u32 = 1 << 28;
+pub const EPOLLWAKEUP: u32 = 1 << 29;
+pub const EPOLLONESHOT: u32 = 1 << 30;
+pub const EPOLLET: u32 = 1 << 31;
+
+// epoll_wait 返回的事件
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EpollEvent {
+    pub events: u32,
+    pub data: u64
+}
+
+// epoll 监听的文件
+pub struct EpollItem {
+    pub file: Weak<dyn FileOP>,     // 监听的文件 文件关闭后自动移除
+    pub events: u32,                // 监听的事件和标志
+    pub data: u64,                  // 用户数据
+    pub event: Option<WaitEvent>,   // 文件状态改变时唤醒的事件
+    pub triggered: bool             // 边缘触发 上次返回之后文件状态是否发生变化
+}
+
+impl EpollItem {
+    pub fn new(file: &Rc<dyn FileOP>, events: u32, data: u64) -> Self {
+        Self { file: Rc::downgrade(file), events, data, event: file.wait_event(), triggered: true }
+    }
+}
+
+// epoll 实例 按照文件描述符保存监听的文件
+pub struct EpollFile(pub RefCell<BTreeMap<usize, EpollItem>>);
+
+lazy_static! {
+    // 所有的epoll实例 文件状态改变时通知
+    static ref EPOLL_LIST: Mutex<Vec<Weak<EpollFile>>> = Mutex::new(vec![]);
+}
+
+impl EpollFile {
+    // 创建epoll实例并加入通知列表
+    pub fn new() -> Rc<Self> {
+        let epoll = Rc::new(Self(RefCell::new(BTreeMap::new())));
+        EPOLL_LIST.lock().push(Rc::downgrade(&epoll));
+        epoll
+    }
+
+    // 等待epoll可以读取的事件
+    pub fn event(&self) -> WaitEvent {
+        WaitEvent::File(self as *const Self as usize)
+    }
+
+    // 判断是否直接或者间接监听了target 用于防止epoll互相监听
+    pub fn contains(&self, target: &EpollFile) -> bool {
+        core::ptr::eq(self, target) || self.0.borrow().values()
+            .filter_map(|x| x.file.upgrade())
+            .filter_map(|x| x.downcast::<EpollFile>().ok())
+            .any(|x| x.contains(target))
+    }
+
+    // 需要等待的事件 任意一个发生时重新检查
+    pub fn wait_events(&self) -> Vec<WaitEvent> {
+        self.0.borrow().values().filter_map(|x| x.event).collect()
+    }
+
+    // 获取就绪的文件 最多返回max_events个 poll用于获取文件的状态
+    // 边缘触发的文件只在状态变化后返回一次 EPOLLONESHOT的文件返回后停止监听
+    pub fn ready_events(&self, max_events: usize, poll: impl Fn(&Rc<dyn FileOP>) -> PollEvents) -> Vec<EpollEvent> {
+        let mut items = self.0.borrow_mut();
+        // 移除已经关闭的文件
+        items.retain(|_, x| x.file.strong_count() > 0);
+        let mut events = vec![];
+        for item in items.values_mut() {
+            if events.len() >= max_events {
+                break;
+            }
+            if item.events & EPOLLET != 0 && !item.triggered {
+                continue;
+            }
+            let file = match item.file.upgrade() {
+                Some(file) => file,
+                None => continue
+            };
+            let mask = PollEvents::from_bits_truncate(item.events as u16) | PollEvents::ERR | PollEvents::HUP;
+            let revents = poll(&file) & mask;
+            if revents.is_empty() {
+                continue;
+            }
+            events.push(EpollEvent { events: revents.bits() as u32, data: item.data });
+            item.triggered = false;
+            if item.events & EPOLLONESHOT != 0 {
+                item.events &= EPOLLET | EPOLLONESHOT;
+            }
+        }
+        events
+    }
+}
+
+impl FileOP for EpollFile {
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writeable(&self) -> bool {
+        false
+    }
+
+    fn read_at(&self, _pos: usize, _data: &mut [u8]) -> usize {
+        usize::MAX
+    }
+
+    fn write_at(&self, _pos: usize, _data: &[u8], _count: usize) -> usize {
+        usize::MAX
+    }
+
+    fn get_size(&self) -> usize {
+        0
+    }
+
+    // 存在就绪的文件时可以读取 不改变边缘触发的状态
+    fn poll(&self) -> PollEvents {
+        let items = self.0.borrow();
+        let ready = items.values().any(|item| {
+            let mask = PollEvents::from_bits_truncate(item.events as u16) | PollEvents::ERR | PollEvents::HUP;
+            (item.events & EPOLLET == 0 || item.triggered)
+                && item.file.upgrade().map_or(false, |file| !(file.poll() & mask).is_empty())
+        });
+        match ready {
+            true => PollEvents::IN,
+            false => PollEvents::empty()
+        }
+    }
+
+    fn wait_event(&self) -> Option<WaitEvent> {
+        Some(self.event())
+    }
+}
+
+// 文件状态改变时标记监听该文件的epoll 返回需要唤醒的epoll事件
+pub fn epoll_notify(event: WaitEvent) -> Vec<WaitEvent> {
+    let mut list = EPOLL_LIST.lock();
+    list.retain(|x| x.strong_count() > 0);
+    let mut events = vec![];
+    for epoll in list.iter().filter_map(|x| x.upgrade()) {
+        // epoll正在被使用时不会产生通知
+        let mut items = match epoll.0.try_borrow_mut() {
+            Ok(items) => items,
+            Err(_) => continue
+        };
+        let mut notified = false;
+        for item in items.values_mut().filter(|x| x.event == Some(event)) {
+            item.triggered = true;
+            notified = true;
+        }
+        if notified {
+            events.push(epoll.event());
+        }
+    }
+    events
+}
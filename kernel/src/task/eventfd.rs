@@ -0,0 +1,104 @@
+use core::cell::RefCell;
+
+use crate::fs::file::FileOP;
+use crate::fs::file::PollEvents;
+use crate::sys_call::consts::{EAGAIN, EINVAL};
+
+use super::task_scheduler::wake_up;
+use super::wait_queue::WaitEvent;
+
+// eventfd2 的标志
+pub const EFD_SEMAPHORE: usize = 1;
+pub const EFD_NONBLOCK: usize = 0o4000;
+pub const EFD_CLOEXEC: usize = 0o2000000;
+
+// 计数器的最大值
+const EVENTFD_MAX: u64 = u64::MAX - 1;
+
+// eventfd 通过8字节的计数器在任务之间通知事件
+pub struct EventFd {
+    count: RefCell<u64>,    // 计数器
+    semaphore: bool         // 信号量模式 每次读取只减1
+}
+
+impl EventFd {
+    pub fn new(count: u64, semaphore: bool) -> Self {
+        Self { count: RefCell::new(count), semaphore }
+    }
+
+    // 等待计数器变化的事件
+    pub fn event(&self) -> WaitEvent {
+        WaitEvent::File(self as *const Self as usize)
+    }
+}
+
+impl FileOP for EventFd {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writeable(&self) -> bool {
+        true
+    }
+
+    // 读取计数器 计数器为0时返回EAGAIN
+    fn read_at(&self, _pos: usize, data: &mut [u8]) -> usize {
+        if data.len() < 8 {
+            return EINVAL;
+        }
+        let mut count = self.count.borrow_mut();
+        if *count == 0 {
+            return EAGAIN;
+        }
+        let value = match self.semaphore {
+            true => 1,
+            false => *count
+        };
+        *count -= value;
+        drop(count);
+        data[..8].copy_from_slice(&value.to_ne_bytes());
+        wake_up(self.event());
+        8
+    }
+
+    // 增加计数器 超过最大值时返回EAGAIN
+    fn write_at(&self, _pos: usize, data: &[u8], count: usize) -> usize {
+        if count.min(data.len()) < 8 {
+            return EINVAL;
+        }
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&data[..8]);
+        let value = u64::from_ne_bytes(bytes);
+        if value == u64::MAX {
+            return EINVAL;
+        }
+        let mut current = self.count.borrow_mut();
+        if EVENTFD_MAX - *current < value {
+            return EAGAIN;
+        }
+        *current += value;
+        drop(current);
+        wake_up(self.event());
+        8
+    }
+
+    fn get_size(&self) -> usize {
+        0
+    }
+
+    fn poll(&self) -> PollEvents {
+        let count = *self.count.borrow();
+        let mut events = PollEvents::empty();
+        if count > 0 {
+            events |= PollEvents::IN;
+        }
+        if count < EVENTFD_MAX {
+            events |= PollEvents::OUT;
+        }
+        events
+    }
+
+    fn wait_event(&self) -> Option<WaitEvent> {
+        Some(self.event())
+    }
+}
@@ -3,12 +3,16 @@ use core::borrow::Borrow;
 use alloc::rc::Rc;
 use hashbrown::HashMap;
 use crate::fs::file::FileOP;
+use crate::fs::file::FileType;
 use crate::fs::file::File;
 use crate::fs::stdio::StdIn;
 use crate::fs::stdio::StdOut;
 use crate::fs::stdio::StdErr;
 use crate::runtime_err::RuntimeError;
 use crate::memory::addr::UserAddr;
+use crate::sys_call::OpenFlags;
+use crate::sys_call::consts::{EAGAIN, EINVAL};
+use crate::task::wait_queue::WaitEvent;
 
 pub const FD_NULL: usize = 0xffffffffffffff9c;
 pub const FD_CWD: usize = -100 as isize as usize;
@@ -17,6 +21,7 @@ pub const FD_RANDOM: usize = usize::MAX;
 #[derive(Clone)]
 pub struct FileDesc {
     pub offset: usize,
+    pub flags: OpenFlags,
     pub file: Rc<dyn FileOP>
 }
 
@@ -24,10 +29,16 @@ impl FileDesc {
     pub fn new(file: Rc<dyn FileOP>) -> Self {
         Self {
             offset: 0,
+            flags: OpenFlags::RDONLY,
             file
         }
     }
 
+    // 是否为非阻塞模式
+    pub fn nonblock(&self) -> bool {
+        self.flags.contains(OpenFlags::NONBLOCK)
+    }
+
     pub fn readable(&self) -> bool {
         self.file.readable()
     }
@@ -41,51 +52,67 @@ impl FileDesc {
     }
 
     pub fn available(&self) -> usize {
-        self.get_size() - self.offset
+        self.get_size().saturating_sub(self.offset)
     }
 
     pub fn read(&mut self, buf: &mut [u8]) -> usize {
         let read_len = self.file.read_at(*self.offset.borrow(), buf);
-        self.offset += read_len;
+        // 返回错误码时不移动读取位置
+        if (read_len as isize) > 0 {
+            self.offset += read_len;
+        }
         read_len
     }
 
-    pub fn write(&self, buf: &[u8], count: usize) -> usize {
-        let pos = self.file.get_size();
-        let write_len = self.file.write_at(pos, buf, count);
+    // 追加模式下每次写入前移动到文件末尾
+    pub fn write(&mut self, buf: &[u8], count: usize) -> usize {
+        if self.flags.contains(OpenFlags::APPEND) {
+            self.offset = self.file.get_size();
+        }
+        let write_len = self.file.write_at(self.offset, buf, count);
+        // 返回错误码时不移动写入位置
+        if (write_len as isize) > 0 {
+            self.offset += write_len;
+        }
         write_len
     }
 
+    // 读写返回EAGAIN并且为阻塞模式时 返回需要等待的事件
+    pub fn block_event(&self, ret: usize) -> Option<WaitEvent> {
+        match ret == EAGAIN && !self.nonblock() {
+            true => self.file.wait_event(),
+            false => None
+        }
+    }
+
+    // 阻塞模式的管道只写入了部分数据时 需要等待剩余的空间继续写入
+    pub fn partial_event(&self, ret: usize, count: usize) -> Option<WaitEvent> {
+        match (ret as isize) >= 0 && ret < count && !self.nonblock() && self.file.file_type() == FileType::Pipeline {
+            true => self.file.wait_event(),
+            false => None
+        }
+    }
+
     pub fn downcast<T:'static>(&self) -> Result<Rc<T>, Rc<dyn FileOP>> {
         self.file.clone().downcast()
     }
 
+    // 移动读写位置 位置不能为负数 可以超过文件末尾
     pub fn lseek(&mut self, offset: usize, whence: usize) -> usize {
-        let file_size = self.file.get_size();
-
-        self.offset = match whence {
+        let base = match whence {
             // SEEK_SET
-            0 => { 
-                if self.offset < file_size {
-                    self.offset
-                } else {
-                    file_size
-                }
-            }
+            0 => 0,
             // SEEK_CUR
-            1 => { 
-                if self.offset + offset < file_size {
-                    self.offset + offset
-                } else {
-                    file_size
-                }
-            }
+            1 => self.offset,
             // SEEK_END
-            2 => {
-                file_size + self.offset
-            }
-            _ => { 0 }
+            2 => self.file.get_size(),
+            _ => return EINVAL
         };
+        let pos = base as isize + offset as isize;
+        if pos < 0 {
+            return EINVAL;
+        }
+        self.offset = pos as usize;
         self.offset
     }
 }
@@ -124,6 +151,11 @@ impl FDTable {
         self.0.remove(&index);
     }
 
+    // 关闭所有的文件描述符 进程退出时调用
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
     // 获取fd内容
     pub fn get(&mut self, index: usize) -> Result<&mut FileDesc, RuntimeError> {
         self.0.get_mut(&index).ok_or(RuntimeError::NoMatchedFileDesc)
@@ -0,0 +1,392 @@
+use alloc::collections::BTreeMap;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::interrupt::timer::get_time_sec;
+use crate::memory::addr::PAGE_SIZE;
+use crate::memory::mem_map::MemMap;
+use crate::memory::page_table::PTEFlags;
+use crate::memory::vma::Vma;
+use crate::memory::vma::VmaType;
+use crate::runtime_err::RuntimeError;
+use crate::sync::mutex::Mutex;
+use crate::sys_call::consts::{EAGAIN, EEXIST, EFBIG, EIDRM, ENOENT, ERANGE};
+
+use super::task_scheduler::get_wait_event;
+use super::task_scheduler::wake_up_count;
+use super::wait_queue::WaitEvent;
+
+// IPC对象的key和get的标志
+pub const IPC_PRIVATE: usize = 0;
+pub const IPC_CREAT: usize = 0o1000;
+pub const IPC_EXCL: usize = 0o2000;
+pub const IPC_NOWAIT: usize = 0o4000;
+
+// ctl的命令
+pub const IPC_RMID: usize = 0;
+pub const IPC_SET: usize = 1;
+pub const IPC_STAT: usize = 2;
+
+// 共享内存的标志 SHM_DEST表示已经标记删除
+pub const SHM_RDONLY: usize = 0o10000;
+pub const SHM_RND: usize = 0o20000;
+pub const SHM_REMAP: usize = 0o40000;
+pub const SHM_EXEC: usize = 0o100000;
+pub const SHM_DEST: u32 = 0o1000;
+
+// 信号量的限制和标志
+pub const SEMMSL: usize = 32000;
+pub const SEMOPM: usize = 500;
+pub const SEMVMX: i32 = 32767;
+pub const SEM_UNDO: i16 = 0x1000;
+
+// semctl的命令
+pub const GETPID: usize = 11;
+pub const GETVAL: usize = 12;
+pub const GETALL: usize = 13;
+pub const GETNCNT: usize = 14;
+pub const GETZCNT: usize = 15;
+pub const SETVAL: usize = 16;
+pub const SETALL: usize = 17;
+
+// 消息队列的限制和标志
+pub const MSGMAX: usize = 8192;
+pub const MSGMNB: usize = 16384;
+pub const MSG_NOERROR: usize = 0o10000;
+pub const MSG_EXCEPT: usize = 0o20000;
+
+// IPC对象的权限 与linux的ipc64_perm布局相同
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct IpcPerm {
+    pub key: i32,
+    pub uid: u32,
+    pub gid: u32,
+    pub cuid: u32,
+    pub cgid: u32,
+    pub mode: u32,
+    pub seq: u16,
+    _pad: u16,
+    _unused: [usize; 2]
+}
+
+impl IpcPerm {
+    pub fn new(key: usize, mode: usize) -> Self {
+        Self { key: key as i32, uid: 0, gid: 0, cuid: 0, cgid: 0, mode: (mode & 0o777) as u32, seq: 0, _pad: 0, _unused: [0; 2] }
+    }
+}
+
+// key到id的对应表 IPC_PRIVATE每次创建新的对象 删除后key可以重新使用
+pub struct IpcTable<T> {
+    pub objects: BTreeMap<usize, T>,
+    keys: BTreeMap<usize, usize>,
+    next_id: usize
+}
+
+impl<T> IpcTable<T> {
+    pub fn new() -> Self {
+        Self { objects: BTreeMap::new(), keys: BTreeMap::new(), next_id: 0 }
+    }
+
+    // 根据key获取对象的id 不存在时根据IPC_CREAT调用create创建 返回(id, 是否新建)
+    pub fn get_or_create(&mut self, key: usize, flags: usize, create: impl FnOnce() -> Result<T, usize>) -> Result<(usize, bool), usize> {
+        if key != IPC_PRIVATE {
+            if let Some(id) = self.keys.get(&key) {
+                return match flags & IPC_CREAT != 0 && flags & IPC_EXCL != 0 {
+                    true => Err(EEXIST),
+                    false => Ok((*id, false))
+                };
+            }
+            if flags & IPC_CREAT == 0 {
+                return Err(ENOENT);
+            }
+        }
+        let object = create()?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.objects.insert(id, object);
+        if key != IPC_PRIVATE {
+            self.keys.insert(key, id);
+        }
+        Ok((id, true))
+    }
+
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.objects.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut T> {
+        self.objects.get_mut(&id)
+    }
+
+    // 删除key的对应关系 对象只能继续通过id访问
+    pub fn remove_key(&mut self, id: usize) {
+        self.keys.retain(|_, x| *x != id);
+    }
+
+    // 删除对象
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        self.remove_key(id);
+        self.objects.remove(&id)
+    }
+}
+
+// 共享内存的物理页 每页单独分配 由共享内存段和映射它的区域共同持有
+pub struct ShmMemory {
+    pub pages: Vec<Rc<MemMap>>
+}
+
+// 进程映射的共享内存段 区域拆分后仍然只算一次映射
+#[derive(Clone)]
+pub struct ShmAttach {
+    pub id: usize,                  // 段的id
+    pub addr: usize,                // 映射的地址
+    pub memory: Rc<ShmMemory>       // 段的物理页
+}
+
+impl ShmAttach {
+    // 判断区域是否属于这次映射
+    pub fn owns(&self, vma: &Vma) -> bool {
+        vma.start >= self.addr && vma.start < self.addr + self.memory.pages.len() * PAGE_SIZE
+            && matches!(&vma.vma_type, VmaType::Shm(memory, _) if Rc::ptr_eq(memory, &self.memory))
+    }
+}
+
+// 共享内存段
+pub struct ShmSegment {
+    pub perm: IpcPerm,
+    pub size: usize,                // 段的大小
+    pub memory: Rc<ShmMemory>,      // 物理页
+    pub nattch: usize,              // 映射的数量 shmat和fork时增加 shmdt和进程退出时减少
+    pub cpid: usize,                // 创建者的pid
+    pub lpid: usize,                // 最后attach或者detach的pid
+    pub atime: usize,
+    pub dtime: usize,
+    pub ctime: usize
+}
+
+impl ShmSegment {
+    pub fn new(key: usize, size: usize, mode: usize, pid: usize) -> Result<Self, RuntimeError> {
+        let mut pages = vec![];
+        for _ in 0..(size + PAGE_SIZE - 1) / PAGE_SIZE {
+            pages.push(MemMap::new(0usize.into(), 1, PTEFlags::UVRWX)?);
+        }
+        Ok(Self {
+            perm: IpcPerm::new(key, mode),
+            size,
+            memory: Rc::new(ShmMemory { pages }),
+            nattch: 0,
+            cpid: pid,
+            lpid: 0,
+            atime: 0,
+            dtime: 0,
+            ctime: get_time_sec()
+        })
+    }
+}
+
+// 信号量
+#[derive(Clone, Copy)]
+pub struct Sem {
+    pub val: i32,       // 信号量的值
+    pub pid: usize      // 最后操作的pid
+}
+
+// semop的操作
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SemBuf {
+    pub sem_num: u16,
+    pub sem_op: i16,
+    pub sem_flg: i16
+}
+
+// 信号量集合
+pub struct SemSet {
+    pub perm: IpcPerm,
+    pub sems: Vec<Sem>,
+    pub waiters: BTreeMap<(usize, usize), Vec<SemBuf>>,   // 阻塞的任务(pid, tid)和等待的操作
+    pub otime: usize,
+    pub ctime: usize
+}
+
+impl SemSet {
+    pub fn new(key: usize, nsems: usize, mode: usize) -> Self {
+        Self {
+            perm: IpcPerm::new(key, mode),
+            sems: vec![Sem { val: 0, pid: 0 }; nsems],
+            waiters: BTreeMap::new(),
+            otime: 0,
+            ctime: get_time_sec()
+        }
+    }
+
+    // 原子地执行一组操作 有操作需要等待时不修改任何信号量并返回EAGAIN
+    pub fn apply(&mut self, ops: &[SemBuf], pid: usize) -> Result<(), usize> {
+        if ops.iter().any(|x| x.sem_num as usize >= self.sems.len()) {
+            return Err(EFBIG);
+        }
+        let mut vals: Vec<i32> = self.sems.iter().map(|x| x.val).collect();
+        for op in ops {
+            let val = &mut vals[op.sem_num as usize];
+            match op.sem_op {
+                // 等待信号量变为0
+                0 if *val != 0 => return Err(EAGAIN),
+                sem_op if *val + (sem_op as i32) < 0 => return Err(EAGAIN),
+                sem_op if *val + (sem_op as i32) > SEMVMX => return Err(ERANGE),
+                sem_op => *val += sem_op as i32
+            }
+        }
+        for op in ops {
+            self.sems[op.sem_num as usize].pid = pid;
+        }
+        for (sem, val) in self.sems.iter_mut().zip(vals) {
+            sem.val = val;
+        }
+        self.otime = get_time_sec();
+        Ok(())
+    }
+
+    // 统计等待信号量增加(zero为false)或者变为0(zero为true)的任务数量 忽略已经不再等待的任务
+    pub fn wait_count(&self, id: usize, num: usize, zero: bool) -> usize {
+        self.waiters.iter()
+            .filter(|((pid, tid), _)| get_wait_event(*pid, *tid) == Some(WaitEvent::Sem(id)))
+            .filter(|(_, ops)| ops.iter().any(|x| x.sem_num as usize == num && (x.sem_op == 0) == zero && x.sem_op <= 0))
+            .count()
+    }
+}
+
+// 进程的信号量调整值 进程退出时撤销SEM_UNDO的操作 CLONE_SYSVSEM时在进程间共享
+pub struct SemUndo(pub BTreeMap<(usize, usize), i32>);
+
+impl SemUndo {
+    pub fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    // 记录带有SEM_UNDO的操作
+    pub fn record(&mut self, semid: usize, ops: &[SemBuf]) {
+        for op in ops.iter().filter(|x| x.sem_flg & SEM_UNDO != 0) {
+            *self.0.entry((semid, op.sem_num as usize)).or_insert(0) -= op.sem_op as i32;
+        }
+        self.0.retain(|_, adj| *adj != 0);
+    }
+
+    // 撤销所有记录的操作 调整后的值限制在合法范围内
+    pub fn apply(&mut self, pid: usize) {
+        let mut ns = IPC_NS.lock();
+        let mut changed = vec![];
+        for ((semid, num), adj) in core::mem::take(&mut self.0) {
+            if let Some(sem) = ns.sem.get_mut(semid).and_then(|x| x.sems.get_mut(num)) {
+                sem.val = (sem.val + adj).clamp(0, SEMVMX);
+                sem.pid = pid;
+                changed.push(semid);
+            }
+        }
+        drop(ns);
+        changed.dedup();
+        for semid in changed {
+            ipc_wake(WaitEvent::Sem(semid));
+        }
+    }
+}
+
+// 消息队列
+pub struct MsgQueue {
+    pub perm: IpcPerm,
+    pub messages: VecDeque<(usize, Vec<u8>)>,   // (消息类型, 内容)
+    pub bytes: usize,                           // 队列中的字节数
+    pub qbytes: usize,                          // 队列的最大字节数
+    pub lspid: usize,
+    pub lrpid: usize,
+    pub stime: usize,
+    pub rtime: usize,
+    pub ctime: usize
+}
+
+impl MsgQueue {
+    pub fn new(key: usize, mode: usize) -> Self {
+        Self {
+            perm: IpcPerm::new(key, mode),
+            messages: VecDeque::new(),
+            bytes: 0,
+            qbytes: MSGMNB,
+            lspid: 0,
+            lrpid: 0,
+            stime: 0,
+            rtime: 0,
+            ctime: get_time_sec()
+        }
+    }
+
+    // 按照msgrcv的规则查找消息
+    // msgtyp为0时取第一个 大于0时取第一个类型相同(MSG_EXCEPT为不同)的 小于0时取类型不超过|msgtyp|的最小类型
+    pub fn find(&self, msgtyp: isize, except: bool) -> Option<usize> {
+        let mut iter = self.messages.iter().enumerate();
+        match msgtyp {
+            0 => iter.next().map(|(i, _)| i),
+            msgtyp if msgtyp > 0 => iter.find(|(_, (ty, _))| (*ty == msgtyp as usize) != except).map(|(i, _)| i),
+            msgtyp => iter.filter(|(_, (ty, _))| *ty <= msgtyp.unsigned_abs())
+                .min_by_key(|(_, (ty, _))| *ty).map(|(i, _)| i)
+        }
+    }
+}
+
+// IPC命名空间 所有进程共享
+pub struct IpcNamespace {
+    pub shm: IpcTable<ShmSegment>,
+    pub sem: IpcTable<SemSet>,
+    pub msg: IpcTable<MsgQueue>
+}
+
+impl IpcNamespace {
+    pub fn new() -> Self {
+        Self { shm: IpcTable::new(), sem: IpcTable::new(), msg: IpcTable::new() }
+    }
+
+    // 释放已经标记删除并且没有映射的共享内存段
+    pub fn release_shm(&mut self) {
+        let ids: Vec<usize> = self.shm.objects.iter()
+            .filter(|(_, x)| x.perm.mode & SHM_DEST != 0 && x.nattch == 0)
+            .map(|(id, _)| *id).collect();
+        for id in ids {
+            self.shm.remove(id);
+        }
+    }
+
+    // 共享内存段的映射数量加一
+    pub fn shm_attach(&mut self, id: usize) {
+        if let Some(segment) = self.shm.get_mut(id) {
+            segment.nattch += 1;
+        }
+    }
+
+    // 共享内存段的映射数量减一 并释放可以释放的段
+    pub fn shm_detach(&mut self, id: usize) {
+        if let Some(segment) = self.shm.get_mut(id) {
+            segment.nattch -= 1;
+        }
+        self.release_shm();
+    }
+}
+
+lazy_static! {
+    pub static ref IPC_NS: Mutex<IpcNamespace> = Mutex::new(IpcNamespace::new());
+}
+
+// 唤醒等待IPC对象的任务 回退pc并恢复第一个参数使其重新执行系统调用
+pub fn ipc_wake(event: WaitEvent) {
+    for task in wake_up_count(event, usize::MAX) {
+        let mut inner = task.inner.borrow_mut();
+        inner.context.sepc -= 4;
+        inner.context.x[10] = inner.orig_a0;
+    }
+}
+
+// IPC对象被删除 等待的任务返回EIDRM
+pub fn ipc_wake_removed(event: WaitEvent) {
+    for task in wake_up_count(event, usize::MAX) {
+        task.inner.borrow_mut().context.x[10] = EIDRM;
+    }
+}
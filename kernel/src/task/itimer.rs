@@ -0,0 +1,232 @@
+use alloc::collections::BTreeMap;
+
+use crate::interrupt::timer::TimerEvent;
+use crate::interrupt::timer::TimerId;
+use crate::interrupt::timer::add_timer;
+use crate::interrupt::timer::cancel_timer;
+use crate::interrupt::timer::get_time_us;
+
+use super::process::Process;
+use super::signal::Signal;
+use super::signal::SigInfo;
+use super::signal::SI_KERNEL;
+
+// 间隔定时器的种类
+pub const ITIMER_REAL: usize = 0;
+pub const ITIMER_VIRTUAL: usize = 1;
+pub const ITIMER_PROF: usize = 2;
+
+// 间隔定时器 时间单位为us
+#[derive(Clone, Copy)]
+pub struct ITimer {
+    pub interval: usize,            // 间隔时间 0表示只触发一次
+    pub value: usize,               // ITIMER_REAL为到期时间 其他为剩余的运行时间 0表示未启动
+    pub timer: Option<TimerId>      // ITIMER_REAL 对应的定时器
+}
+
+impl ITimer {
+    pub const fn new() -> Self {
+        Self { interval: 0, value: 0, timer: None }
+    }
+}
+
+// POSIX定时器 时间单位为us
+pub struct PosixTimer {
+    pub signo: usize,               // 到期时发送的信号 0表示不发送(SIGEV_NONE)
+    pub value: usize,               // 信号附带的sigev_value
+    pub interval: usize,            // 间隔时间 0表示只触发一次
+    pub deadline: usize,            // 到期时间 0表示未启动
+    pub timer: Option<TimerId>,     // 对应的定时器
+    pub overrun: usize              // 信号未处理期间额外到期的次数
+}
+
+impl PosixTimer {
+    pub fn new(signo: usize, value: usize) -> Self {
+        Self { signo, value, interval: 0, deadline: 0, timer: None, overrun: 0 }
+    }
+
+    // 停止定时器
+    fn stop(&mut self) {
+        if let Some(timer) = self.timer.take() {
+            cancel_timer(timer);
+        }
+        self.deadline = 0;
+    }
+}
+
+// 进程的定时器
+pub struct ProcessTimers {
+    pub itimers: [ITimer; 3],                       // 间隔定时器
+    pub posix_timers: BTreeMap<usize, PosixTimer>,  // POSIX定时器 key为定时器编号
+    next_id: usize
+}
+
+impl ProcessTimers {
+    pub fn new() -> Self {
+        Self {
+            itimers: [ITimer::new(); 3],
+            posix_timers: BTreeMap::new(),
+            next_id: 0
+        }
+    }
+}
+
+impl Process {
+    // 获取间隔定时器 返回(剩余时间, 间隔时间)
+    pub fn get_itimer(&self, which: usize) -> (usize, usize) {
+        let itimer = &self.timers.itimers[which];
+        let remain = match which {
+            ITIMER_REAL if itimer.value != 0 => itimer.value.saturating_sub(get_time_us()).max(1),
+            _ => itimer.value
+        };
+        (remain, itimer.interval)
+    }
+
+    // 设置间隔定时器 value为0时停止 返回原来的(剩余时间, 间隔时间)
+    pub fn set_itimer(&mut self, which: usize, value: usize, interval: usize) -> (usize, usize) {
+        let old = self.get_itimer(which);
+        let itimer = &mut self.timers.itimers[which];
+        if let Some(timer) = itimer.timer.take() {
+            cancel_timer(timer);
+        }
+        itimer.interval = interval;
+        itimer.value = value;
+        if which == ITIMER_REAL && value != 0 {
+            itimer.value = get_time_us() + value;
+            itimer.timer = Some(add_timer(itimer.value, TimerEvent::ITimerReal(self.pid)));
+        }
+        old
+    }
+
+    // ITIMER_REAL 到期 发送SIGALRM并重新设置
+    pub fn itimer_real_expired(&mut self) {
+        let itimer = &mut self.timers.itimers[ITIMER_REAL];
+        itimer.timer = None;
+        if itimer.value == 0 {
+            return;
+        }
+        if itimer.interval != 0 {
+            itimer.value += itimer.interval;
+            itimer.timer = Some(add_timer(itimer.value, TimerEvent::ITimerReal(self.pid)));
+        } else {
+            itimer.value = 0;
+        }
+        self.send_signal(SigInfo::new(Signal::SIGALRM as usize, SI_KERNEL));
+    }
+
+    // 记录任务运行的时间 处理 ITIMER_VIRTUAL 和 ITIMER_PROF
+    pub fn account_time(&mut self, user: usize, system: usize) {
+        let timers = [
+            (ITIMER_VIRTUAL, user, Signal::SIGVTALRM),
+            (ITIMER_PROF, user + system, Signal::SIGPROF)
+        ];
+        for (which, delta, signal) in timers {
+            let itimer = &mut self.timers.itimers[which];
+            if itimer.value == 0 || delta == 0 {
+                continue;
+            }
+            if itimer.value > delta {
+                itimer.value -= delta;
+                continue;
+            }
+            itimer.value = itimer.interval;
+            self.send_signal(SigInfo::new(signal as usize, SI_KERNEL));
+        }
+    }
+
+    // 创建POSIX定时器 返回定时器编号
+    pub fn create_timer(&mut self, signo: usize, value: usize) -> usize {
+        let id = self.timers.next_id;
+        self.timers.next_id += 1;
+        self.timers.posix_timers.insert(id, PosixTimer::new(signo, value));
+        id
+    }
+
+    // 获取POSIX定时器 返回(剩余时间, 间隔时间)
+    pub fn get_timer(&self, id: usize) -> Option<(usize, usize)> {
+        let timer = self.timers.posix_timers.get(&id)?;
+        let remain = match timer.deadline {
+            0 => 0,
+            deadline => deadline.saturating_sub(get_time_us()).max(1)
+        };
+        Some((remain, timer.interval))
+    }
+
+    // 设置POSIX定时器 deadline为绝对到期时间 0表示停止 返回原来的(剩余时间, 间隔时间)
+    pub fn set_timer(&mut self, id: usize, deadline: usize, interval: usize) -> Option<(usize, usize)> {
+        let old = self.get_timer(id)?;
+        let pid = self.pid;
+        let timer = self.timers.posix_timers.get_mut(&id)?;
+        timer.stop();
+        timer.interval = interval;
+        timer.overrun = 0;
+        if deadline != 0 {
+            timer.deadline = deadline;
+            timer.timer = Some(add_timer(deadline, TimerEvent::PosixTimer(pid, id)));
+        }
+        Some(old)
+    }
+
+    // POSIX定时器到期
+    pub fn posix_timer_expired(&mut self, id: usize) {
+        let pid = self.pid;
+        let timer = match self.timers.posix_timers.get_mut(&id) {
+            Some(timer) => timer,
+            None => return
+        };
+        timer.timer = None;
+        if timer.deadline == 0 {
+            return;
+        }
+        let signo = timer.signo;
+        // 信号仍未处理时记录超限次数 不重复发送
+        let pending = signo != 0 && self.pending.contains(signo);
+        if pending {
+            timer.overrun += 1;
+        } else {
+            timer.overrun = 0;
+        }
+        if timer.interval != 0 {
+            timer.deadline += timer.interval;
+            timer.timer = Some(add_timer(timer.deadline, TimerEvent::PosixTimer(pid, id)));
+        } else {
+            timer.deadline = 0;
+        }
+        if signo != 0 && !pending {
+            let info = SigInfo::from_timer(signo, id, timer.overrun, timer.value);
+            self.send_signal(info);
+        }
+    }
+
+    // 获取POSIX定时器的超限次数
+    pub fn get_timer_overrun(&self, id: usize) -> Option<usize> {
+        self.timers.posix_timers.get(&id).map(|x| x.overrun)
+    }
+
+    // 删除POSIX定时器
+    pub fn delete_timer(&mut self, id: usize) -> bool {
+        match self.timers.posix_timers.remove(&id) {
+            Some(mut timer) => {
+                timer.stop();
+                true
+            }
+            None => false
+        }
+    }
+
+    // 删除所有的POSIX定时器 执行新的程序时调用
+    pub fn delete_timers(&mut self) {
+        for (_, timer) in self.timers.posix_timers.iter_mut() {
+            timer.stop();
+        }
+        self.timers.posix_timers.clear();
+    }
+
+    // 停止所有定时器 进程退出时调用
+    pub fn release_timers(&mut self) {
+        self.delete_timers();
+        for which in [ITIMER_REAL, ITIMER_VIRTUAL, ITIMER_PROF] {
+            self.set_itimer(which, 0, 0);
+        }
+    }
+}
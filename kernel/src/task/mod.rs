@@ -11,9 +11,12 @@ use crate::fs::filetree::INode;
 use crate::memory::addr::get_pages_num;
 use crate::memory::addr::get_buf_from_phys_page;
 use crate::memory::mem_map::MemMap;
+use crate::memory::vma::Vma;
+use crate::memory::vma::VmaType;
 use crate::memory::page::alloc_more;
 use crate::runtime_err::RuntimeError;
 use crate::task::process::Process;
+use crate::task::process::get_init_process;
 use crate::task::task_scheduler::start_tasks;
 use crate::memory::page_table::PTEFlags;
 use crate::memory::addr::PAGE_SIZE;
@@ -33,6 +36,14 @@ pub mod signal;
 pub mod fd_table;
 pub mod task_scheduler;
 pub mod user_heap;
+pub mod wait_queue;
+pub mod sched;
+pub mod itimer;
+pub mod signalfd;
+pub mod unix_socket;
+pub mod ipc;
+pub mod epoll;
+pub mod eventfd;
 
 pub const STDIN: usize = 0;
 pub const STDOUT: usize = 1;
@@ -45,12 +56,15 @@ pub fn get_new_pid() -> usize {
 
 pub fn exec_with_process<'a>(process: Rc<RefCell<Process>>, task: Rc<Task>, path: &'a str, args: Vec<&'a str>) 
         -> Result<Rc<Task>, RuntimeError> {
-    // 如果存在write
-    let file = INode::open(None, path)?;
+    // 通过页缓存读取整个elf文件到临时的连续页 解析重定位信息需要完整的文件
+    let inode = INode::get(None, path)?;
+    let file_size = inode.get_file_size();
+    let elf_buf = MemMap::new_kernel_buf(get_pages_num(file_size))?;
+    let elf_data = &mut get_buf_from_phys_page(elf_buf.ppn, elf_buf.page_num)[..file_size];
+    inode.read_at(0, elf_data)?;
 
-    let file_inner = file.0.borrow_mut();
     // 读取elf信息
-    let elf = xmas_elf::ElfFile::new(&file_inner.buf).unwrap();
+    let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
     let elf_header = elf.header;    
     let magic = elf_header.pt1.magic;
 
@@ -114,9 +128,11 @@ pub fn exec_with_process<'a>(process: Rc<RefCell<Process>>, task: Rc<Task>, path
                 alloc_pages, PTEFlags::VRWX | PTEFlags::U));
 
             // 初始化
-            temp_buf[vr_offset..vr_offset_end].copy_from_slice(&file_inner.buf[ph_offset..ph_offset+read_size]);
+            temp_buf[vr_offset..vr_offset_end].copy_from_slice(&elf_data[ph_offset..ph_offset+read_size]);
             process.pmm.add_mapping_range(PhysAddr::from(phy_start) + PhysAddr::from(offset), 
                 start_va, ph.mem_size() as usize, PTEFlags::VRWX | PTEFlags::U)?;
+            process.vmas.push(Vma::new(start_va.0, start_va.0 + ph.mem_size() as usize, 
+                PTEFlags::VRWX, VmaType::Anonymous));
         }
     }
     if base > 0 {
@@ -159,6 +175,8 @@ pub fn exec_with_process<'a>(process: Rc<RefCell<Process>>, task: Rc<Task>, path
     // 设置heap_bottom
     process.new_heap()?;
     process.heap.set_heap_top(heap_bottom)?;
+    let (heap_start, heap_end) = (process.heap.start, process.heap.end);
+    process.vmas.set_heap(heap_start, heap_end);
     drop(task_inner);
     drop(process);
 
@@ -168,8 +186,10 @@ pub fn exec_with_process<'a>(process: Rc<RefCell<Process>>, task: Rc<Task>, path
 
 // 执行一个程序 path: 文件名 思路：加入程序准备池  等待执行  每过一个时钟周期就执行一次
 pub fn exec<'a>(path: &'a str, args: Vec<&'a str>) -> Result<Rc<Task>, RuntimeError> { 
-    // 创建新的任务控制器 并映射栈
-    let (process, task) = Process::new(get_new_pid(), None)?;
+    // 创建新的任务控制器 并映射栈 由初始进程作为父进程
+    let init = get_init_process();
+    let (process, task) = Process::new(get_new_pid(), Some(Rc::downgrade(&init)))?;
+    init.borrow_mut().children.push(process.clone());
     exec_with_process(process, task, path, args)
 }
 
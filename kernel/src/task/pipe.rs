@@ -1,14 +1,31 @@
 use core::cell::RefCell;
+use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use alloc::rc::Rc;
-use alloc::vec::Vec;
 use crate::fs::file::FileOP;
+use crate::fs::file::FileType;
+use crate::fs::file::PollEvents;
+use crate::memory::addr::PAGE_SIZE;
+use crate::sys_call::OpenFlags;
+use crate::sys_call::consts::{EAGAIN, EBUSY, ENXIO, EPERM, EPIPE};
 use super::fd_table::FileDesc;
+use super::task_scheduler::wake_up;
+use super::wait_queue::WaitEvent;
+
+// 管道的默认容量
+pub const PIPE_DEFAULT_SIZE: usize = 16 * PAGE_SIZE;
+// 管道的最大容量
+pub const PIPE_MAX_SIZE: usize = 1024 * 1024;
+// 不超过该长度的写入不会被拆分
+pub const PIPE_BUF: usize = 4096;
 
 pub struct PipeBufInner {
-    pub buf: Vec<u8>,
-    pub read_offset: usize,
-    pub write_offset: usize
+    pub buf: VecDeque<u8>,      // 环形缓冲区
+    pub capacity: usize,        // 缓冲区容量
+    pub readers: usize,         // 打开的读端数量
+    pub writers: usize,         // 打开的写端数量
+    pub reader_wakes: usize,    // 命名管道 被写端唤醒后可以直接打开的读端数量
+    pub writer_wakes: usize     // 命名管道 被读端唤醒后可以直接打开的写端数量
 }
 
 #[derive(Clone)]
@@ -18,70 +35,195 @@ impl PipeBuf {
     // 创建pipeBuf
     pub fn new() -> Self {
         Self(Arc::new(RefCell::new(PipeBufInner {
-            buf: Vec::new(),
-            read_offset: 0,
-            write_offset: 0
+            buf: VecDeque::with_capacity(PIPE_DEFAULT_SIZE),
+            capacity: PIPE_DEFAULT_SIZE,
+            readers: 0,
+            writers: 0,
+            reader_wakes: 0,
+            writer_wakes: 0
         })))
     }
-    // 读取字节
-    pub fn read_at(&self, mut pos: usize, buf: &mut [u8]) -> usize {
-        let mut read_index = 0;
-        let pipe = self.0.borrow_mut();
-        loop {
-            if read_index >= buf.len() {
-                break;
-            }
 
-            if pos < pipe.buf.len() {
-                buf[read_index] = pipe.buf[pos];
-            } else {
-                break;
-            }
+    // 等待管道读写的事件 使用缓冲区地址区分管道
+    pub fn wait_event(&self) -> WaitEvent {
+        WaitEvent::Pipe(Arc::as_ptr(&self.0) as usize)
+    }
 
-            pos += 1;
-            read_index += 1;
+    // 读取字节 缓冲区为空时 写端关闭返回0 否则返回EAGAIN
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        let mut pipe = self.0.borrow_mut();
+        if buf.len() == 0 {
+            return 0;
         }
-        read_index
+        if pipe.buf.is_empty() {
+            return match pipe.writers == 0 {
+                true => 0,
+                false => EAGAIN
+            };
+        }
+        let len = buf.len().min(pipe.buf.len());
+        for (i, byte) in pipe.buf.drain(..len).enumerate() {
+            buf[i] = byte;
+        }
+        drop(pipe);
+        // 唤醒等待写入的任务
+        wake_up(self.wait_event());
+        len
     }
 
-    pub fn write_at(&self, mut pos: usize, buf: &[u8], count: usize) -> usize{
-        let mut write_index = 0;
+    // 写入字节 读端关闭返回EPIPE 没有空间时返回EAGAIN
+    pub fn write(&self, buf: &[u8], count: usize) -> usize {
         let mut pipe = self.0.borrow_mut();
+        if pipe.readers == 0 {
+            return EPIPE;
+        }
+        let count = count.min(buf.len());
+        if count == 0 {
+            return 0;
+        }
+        // 不超过PIPE_BUF的写入需要一次完成
+        let free = pipe.capacity - pipe.buf.len();
+        if free == 0 || (count <= PIPE_BUF && free < count) {
+            return EAGAIN;
+        }
+        let len = count.min(free);
+        pipe.buf.extend(&buf[..len]);
+        drop(pipe);
+        // 唤醒等待读取的任务
+        wake_up(self.wait_event());
+        len
+    }
+
+    // 获取可以读取的大小
+    pub fn available(&self) -> usize {
+        self.0.borrow().buf.len()
+    }
 
-        if pipe.buf.len() > 4096 {
-            let start = pipe.buf.len() - 4096;
-            pipe.buf = pipe.buf[start..].to_vec();
+    // 获取管道容量
+    pub fn capacity(&self) -> usize {
+        self.0.borrow().capacity
+    }
+
+    // 设置管道容量 按页对齐 返回新的容量或者错误码
+    pub fn set_capacity(&self, size: usize) -> Result<usize, usize> {
+        let mut pipe = self.0.borrow_mut();
+        if size > PIPE_MAX_SIZE {
+            return Err(EPERM);
         }
+        let size = ((size + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE).max(PAGE_SIZE);
+        // 不能小于已经保存的数据
+        if size < pipe.buf.len() {
+            return Err(EBUSY);
+        }
+        pipe.capacity = size;
+        drop(pipe);
+        wake_up(self.wait_event());
+        Ok(size)
+    }
 
-        loop {
-            if write_index >= buf.len() || write_index >= count {
-                break;
-            }
-            
-            // queue.push_back(buf[write_index]);
-            pipe.buf.push(buf[write_index]);
-            pos += 1;
-            write_index += 1;
+    // 命名管道等待另一端打开的事件 reader表示等待的任务是否为读端
+    pub fn open_event(&self, reader: bool) -> WaitEvent {
+        WaitEvent::FifoOpen(Arc::as_ptr(&self.0) as usize, reader)
+    }
+
+    // 打开管道的一端
+    fn open(&self, reader: bool) {
+        let mut pipe = self.0.borrow_mut();
+        match reader {
+            true => pipe.readers += 1,
+            false => pipe.writers += 1
         }
-        // 如果栈过大则重新分配
-        write_index
     }
 
-    // 获取可获取的大小
-    pub fn available(&self) -> usize {
-        let pipe = self.0.borrow_mut();
-        pipe.write_offset - pipe.read_offset
+    // 管道的一端关闭 唤醒另一端等待的任务
+    fn close(&self, reader: bool) {
+        let mut pipe = self.0.borrow_mut();
+        match reader {
+            true => pipe.readers -= 1,
+            false => pipe.writers -= 1
+        }
+        // 命名管道的两端全部关闭后丢弃未读取的数据
+        if pipe.readers == 0 && pipe.writers == 0 {
+            pipe.buf.clear();
+        }
+        drop(pipe);
+        wake_up(self.wait_event());
     }
 
-    pub fn get_size(&self) -> usize {
-        let pipe = self.0.borrow_mut();
-        pipe.buf.len()
+    // 打开命名管道 阻塞模式下读端和写端需要等待对方打开
+    // 返回EAGAIN时需要等待open_event 非阻塞的写端在没有读端时返回ENXIO
+    pub fn open_fifo(&self, flags: OpenFlags) -> Result<FileDesc, usize> {
+        let nonblock = flags & OpenFlags::NONBLOCK;
+        // 读写模式不需要等待
+        if flags.contains(OpenFlags::RDWR) {
+            self.wake_opener(true);
+            self.wake_opener(false);
+            let mut file = FileDesc::new(Rc::new(PipeRdwr(PipeReader::new(self.clone()), PipeWriter::new(self.clone()))));
+            file.flags = nonblock | OpenFlags::RDWR;
+            return Ok(file);
+        }
+        let reader = !flags.contains(OpenFlags::WRONLY);
+        let mut pipe = self.0.borrow_mut();
+        let (peers, wakes) = match reader {
+            true => (pipe.writers, &mut pipe.reader_wakes),
+            false => (pipe.readers, &mut pipe.writer_wakes)
+        };
+        // 另一端已经打开或者之前被另一端唤醒
+        let ready = peers > 0 || *wakes > 0;
+        *wakes = wakes.saturating_sub(1);
+        drop(pipe);
+        // 唤醒等待当前端的任务 存在等待的任务时也可以直接打开
+        let ready = self.wake_opener(!reader) || ready;
+        if !ready && nonblock.is_empty() {
+            return Err(EAGAIN);
+        }
+        if !ready && !reader {
+            return Err(ENXIO);
+        }
+        let mut file = match reader {
+            true => FileDesc::new(Rc::new(PipeReader::new(self.clone()))),
+            false => FileDesc::new(Rc::new(PipeWriter::new(self.clone())))
+        };
+        file.flags = match reader {
+            true => nonblock,
+            false => nonblock | OpenFlags::WRONLY
+        };
+        Ok(file)
+    }
+
+    // 唤醒等待打开的读端或者写端 返回是否存在等待的任务
+    fn wake_opener(&self, reader: bool) -> bool {
+        let count = wake_up(self.open_event(reader));
+        let mut pipe = self.0.borrow_mut();
+        match reader {
+            true => pipe.reader_wakes += count,
+            false => pipe.writer_wakes += count
+        }
+        count > 0
     }
 }
 
-pub struct PipeReader(PipeBuf);
+// 管道的读端和写端 通过Rc在文件描述符之间共享 最后一个引用释放时关闭
+pub struct PipeReader(pub PipeBuf);
+
+pub struct PipeWriter(pub PipeBuf);
+
+// 以读写模式打开的命名管道
+pub struct PipeRdwr(pub PipeReader, pub PipeWriter);
 
-pub struct PipeWriter(PipeBuf);
+impl PipeReader {
+    pub fn new(pipe: PipeBuf) -> Self {
+        pipe.open(true);
+        Self(pipe)
+    }
+}
+
+impl PipeWriter {
+    pub fn new(pipe: PipeBuf) -> Self {
+        pipe.open(false);
+        Self(pipe)
+    }
+}
 
 impl FileOP for PipeReader {
     fn readable(&self) -> bool {
@@ -92,19 +234,42 @@ impl FileOP for PipeReader {
         false
     }
 
-    fn read_at(&self, pos: usize, data: &mut [u8]) -> usize {
-        debug!("write?");
-        self.0.read_at(pos, data)
+    fn read_at(&self, _pos: usize, data: &mut [u8]) -> usize {
+        self.0.read(data)
     }
 
     fn write_at(&self, _pos: usize, _data: &[u8], _count: usize) -> usize {
-        debug!("write?");
-        todo!()
+        usize::MAX
     }
 
     fn get_size(&self) -> usize {
-        // self.0.available()
-        self.0.get_size()
+        self.0.available()
+    }
+
+    fn poll(&self) -> PollEvents {
+        let pipe = self.0.0.borrow();
+        let mut events = PollEvents::empty();
+        if !pipe.buf.is_empty() {
+            events |= PollEvents::IN;
+        }
+        if pipe.writers == 0 {
+            events |= PollEvents::HUP;
+        }
+        events
+    }
+
+    fn wait_event(&self) -> Option<WaitEvent> {
+        Some(self.0.wait_event())
+    }
+
+    fn file_type(&self) -> FileType {
+        FileType::Pipeline
+    }
+}
+
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        self.0.close(true);
     }
 }
 
@@ -118,23 +283,83 @@ impl FileOP for PipeWriter {
     }
 
     fn read_at(&self, _pos: usize, _data: &mut [u8]) -> usize {
-        debug!("write?");
-        todo!()
+        usize::MAX
+    }
+
+    fn write_at(&self, _pos: usize, data: &[u8], count: usize) -> usize {
+        self.0.write(data, count)
+    }
+
+    fn get_size(&self) -> usize {
+        self.0.available()
+    }
+
+    fn poll(&self) -> PollEvents {
+        let pipe = self.0.0.borrow();
+        if pipe.readers == 0 {
+            return PollEvents::ERR;
+        }
+        match pipe.capacity - pipe.buf.len() >= PIPE_BUF.min(pipe.capacity) {
+            true => PollEvents::OUT,
+            false => PollEvents::empty()
+        }
+    }
+
+    fn wait_event(&self) -> Option<WaitEvent> {
+        Some(self.0.wait_event())
+    }
+
+    fn file_type(&self) -> FileType {
+        FileType::Pipeline
+    }
+}
+
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        self.0.close(false);
+    }
+}
+
+impl FileOP for PipeRdwr {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writeable(&self) -> bool {
+        true
+    }
+
+    fn read_at(&self, pos: usize, data: &mut [u8]) -> usize {
+        self.0.read_at(pos, data)
     }
 
     fn write_at(&self, pos: usize, data: &[u8], count: usize) -> usize {
-        debug!("write?");
-        self.0.write_at(pos, data, count)
+        self.1.write_at(pos, data, count)
     }
 
     fn get_size(&self) -> usize {
         self.0.get_size()
     }
+
+    fn poll(&self) -> PollEvents {
+        self.0.poll() | self.1.poll()
+    }
+
+    fn wait_event(&self) -> Option<WaitEvent> {
+        self.0.wait_event()
+    }
+
+    fn file_type(&self) -> FileType {
+        FileType::Pipeline
+    }
 }
 
-pub fn new_pipe() -> (FileDesc, FileDesc) {
+// 创建管道 返回读端和写端 flags中的O_NONBLOCK作用于两端
+pub fn new_pipe(flags: OpenFlags) -> (FileDesc, FileDesc) {
     let pipe_buf = PipeBuf::new();
-    let pipe_reader  = FileDesc::new(Rc::new(PipeReader(pipe_buf.clone())));
-    let pipe_writer = FileDesc::new(Rc::new(PipeWriter(pipe_buf.clone())));
+    let mut pipe_reader = FileDesc::new(Rc::new(PipeReader::new(pipe_buf.clone())));
+    let mut pipe_writer = FileDesc::new(Rc::new(PipeWriter::new(pipe_buf)));
+    pipe_reader.flags = flags & OpenFlags::NONBLOCK;
+    pipe_writer.flags = (flags & OpenFlags::NONBLOCK) | OpenFlags::WRONLY;
     (pipe_reader, pipe_writer)
-}
\ No newline at end of file
+}
@@ -4,7 +4,11 @@ use alloc::rc::Rc;
 use alloc::rc::Weak;
 use crate::memory::page_table::PageMappingManager;
 use crate::memory::mem_set::MemSet;
+use crate::memory::vma::VmaList;
 use crate::memory::addr::VirtAddr;
+use crate::memory::addr::PAGE_SIZE;
+use crate::memory::page_table::PTEFlags;
+use crate::memory::vma::PageFaultType;
 use crate::runtime_err::RuntimeError;
 use crate::interrupt::timer::TMS;
 use crate::fs::filetree::INode;
@@ -13,7 +17,31 @@ use super::task::TaskStatus;
 use super::stack::UserStack;
 use super::fd_table::FDTable;
 use super::task_scheduler::kill_process;
+use super::task_scheduler::wake_up;
+use super::task_scheduler::signal_process;
+use crate::sync::mutex::Mutex;
+use crate::sys_call::SignalFlag;
+use super::wait_queue::WaitEvent;
 use super::signal::SigAction;
+use super::signal::SigSet;
+use super::signal::SigInfo;
+use super::signal::SigPending;
+use super::signal::CLD_EXITED;
+use super::signal::CLD_KILLED;
+use super::signal::CLD_DUMPED;
+use super::signal::CLD_CONTINUED;
+use super::signal::Signal;
+use super::signal::SigDefault;
+use super::signal::SIG_DFL;
+use super::signal::SIG_IGN;
+use super::signal::SIGNAL_COUNT;
+use super::signal::default_action;
+use super::signal::is_stop_signal;
+use super::signal::is_unblockable;
+use super::itimer::ProcessTimers;
+use super::ipc::SemUndo;
+use super::ipc::ShmAttach;
+use super::ipc::IPC_NS;
 use super::user_heap::UserHeap;
 
 pub struct Process {
@@ -21,6 +49,7 @@ pub struct Process {
     pub parent: Option<Weak<RefCell<Process>>>, // 父进程
     pub pmm: Rc<PageMappingManager>,            // 内存页映射管理 
     pub mem_set: MemSet,                        // 内存使用集
+    pub vmas: VmaList,                          // 虚拟内存区域
     pub tasks: Vec<Weak<Task>>,                 // 任务管理器
     pub entry: VirtAddr,                        // 入口地址
     pub stack: UserStack,                       // 用户栈
@@ -28,29 +57,75 @@ pub struct Process {
     pub workspace: Rc<INode>,                   // 工作目录
     pub fd_table: FDTable,                      // 文件描述表
     pub tms: TMS,                               // 时间记录结构
-    pub sig_actions: [SigAction; 64],           // 信号结构
+    pub sig_actions: [SigAction; SIGNAL_COUNT], // 信号结构
+    pub pending: SigPending,                    // 等待处理的信号
+    pub stopped: bool,                          // 是否被信号停止
+    pub pgid: usize,                            // 进程组id
+    pub timers: ProcessTimers,                  // 间隔定时器和POSIX定时器
+    pub sem_undo: Rc<RefCell<SemUndo>>,         // 信号量的撤销记录 CLONE_SYSVSEM时共享
+    pub shm_attaches: Vec<ShmAttach>,           // 映射的共享内存段
     pub children: Vec<Rc<RefCell<Process>>>,    // 子结构
     pub exit_code: Option<usize>                // 退出代码
 }
 
+// wait4获取的进程状态 正常退出时退出码保存在8~15位
+pub fn exit_status(code: usize) -> usize {
+    (code & 0xff) << 8
+}
+
+// 被信号结束时的进程状态 低7位为信号编号 转储时设置0x80
+pub fn signal_status(signum: usize, core: bool) -> usize {
+    match core {
+        true => signum | 0x80,
+        false => signum
+    }
+}
+
+// 初始进程的pid 孤儿进程由初始进程收养 退出后立即回收
+pub const INIT_PID: usize = 1;
+
+lazy_static! {
+    static ref INIT_PROCESS: Mutex<Option<Rc<RefCell<Process>>>> = Mutex::new(None);
+}
+
+// 获取初始进程 初始进程不运行 只作为顶层进程和孤儿进程的父进程
+pub fn get_init_process() -> Rc<RefCell<Process>> {
+    let mut init = INIT_PROCESS.lock();
+    if init.is_none() {
+        let (process, _) = Process::new(INIT_PID, None).expect("初始进程创建失败");
+        *init = Some(process);
+    }
+    init.as_ref().unwrap().clone()
+}
+
 impl Process {
     pub fn new(pid: usize, parent: Option<Weak<RefCell<Process>>>)
         -> Result<(Rc<RefCell<Process>>, Rc<Task>), RuntimeError> {
         let pmm = Rc::new(PageMappingManager::new()?);
         let heap = UserHeap::new(pmm.clone())?;
+        let stack = UserStack::new(pmm.clone())?;
+        let mut vmas = VmaList::new();
+        vmas.push(stack.get_vma());
         let process = Self { 
             pid, 
             parent, 
             pmm: pmm.clone(), 
             mem_set: MemSet::new(), 
+            vmas,
             tasks: vec![], 
             entry: 0usize.into(), 
-            stack: UserStack::new(pmm.clone())?, 
+            stack, 
             heap, 
             workspace: INode::root(),
             fd_table: FDTable::new(),
             children: vec![],
-            sig_actions: [SigAction::empty(); 64],
+            sig_actions: [SigAction::empty(); SIGNAL_COUNT],
+            pending: SigPending::new(),
+            stopped: false,
+            pgid: pid,
+            timers: ProcessTimers::new(),
+            sem_undo: Rc::new(RefCell::new(SemUndo::new())),
+            shm_attaches: vec![],
             tms: TMS::new(),
             exit_code: None
         };
@@ -63,26 +138,48 @@ impl Process {
     }
 
     pub fn fork(pid: usize, parent: Rc<RefCell<Process>>) -> Result<(Rc<RefCell<Process>>, Rc<Task>), RuntimeError> {
-        let parent_inner = parent.borrow_mut();
+        let mut parent_inner = parent.borrow_mut();
         let pmm = Rc::new(PageMappingManager::new()?);
-        pmm.add_mapping_by_set(&parent_inner.mem_set)?;
-        pmm.add_mapping_by_set(&parent_inner.heap.mem_set)?;
+        // 写时复制后需要单独释放每一页 ELF段和栈的多页内存拆分后交给页表管理器持有
+        let mut pages = parent_inner.mem_set.take_user_pages();
+        parent_inner.pmm.add_mem_set(&mut pages);
+        let mut pages = parent_inner.stack.mem_set.take_user_pages();
+        parent_inner.pmm.add_mem_set(&mut pages);
+        // 共享父进程的页面 可写页面写时复制
+        parent_inner.pmm.share_cow(&pmm, &parent_inner.vmas)?;
+        let mut stack = parent_inner.stack.clone();
+        stack.pmm = pmm.clone();
+        let mut heap = parent_inner.heap.clone();
+        heap.pmm = pmm.clone();
         let process = Rc::new(RefCell::new(Self { 
             pid, 
             parent: Some(Rc::downgrade(&parent)), 
             pmm: pmm, 
             mem_set: parent_inner.mem_set.clone(), 
+            vmas: parent_inner.vmas.clone(),
             tasks: vec![], 
             entry: parent_inner.entry, 
-            stack: parent_inner.stack.clone(), 
-            heap: parent_inner.heap.clone(), 
-            workspace: INode::root(),
+            stack, 
+            heap, 
+            workspace: parent_inner.workspace.clone(),
             fd_table: parent_inner.fd_table.clone(),
             children: vec![],
-            sig_actions: [SigAction::empty(); 64],
+            sig_actions: parent_inner.sig_actions,
+            pending: SigPending::new(),
+            stopped: false,
+            pgid: parent_inner.pgid,
+            timers: ProcessTimers::new(),
+            sem_undo: Rc::new(RefCell::new(SemUndo::new())),
+            shm_attaches: parent_inner.shm_attaches.clone(),
             tms: TMS::new(),
             exit_code: None
         }));
+        // 子进程继承父进程映射的共享内存段
+        let mut ns = IPC_NS.lock();
+        for attach in &parent_inner.shm_attaches {
+            ns.shm_attach(attach.id);
+        }
+        drop(ns);
         let task = Task::new(0, process.clone());
         Ok((process, task))
     }
@@ -119,31 +216,219 @@ impl Process {
         self.tasks[0].upgrade().unwrap()
     }
 
-    // 结束进程
-    pub fn exit(&mut self, exit_code: usize) {
+    // 结束进程 进程成为僵尸进程 直到父进程通过wait4回收
+    // status为wait4获取的状态 由exit_status或者signal_status生成
+    pub fn exit(&mut self, status: usize) {
+        self.release_timers();
+        self.release_sem_undo();
         self.release();
+        // 关闭文件描述符 管道的另一端可以得到EOF或者EPIPE
+        self.fd_table.clear();
         // 如果没有子进程
-        self.exit_code = Some(exit_code);
+        self.exit_code = Some(status);
         // 进程回收
         kill_process(self.pid);
+        // 子进程由初始进程收养
+        self.reparent_children();
+        // 唤醒等待的父进程
+        wake_up(WaitEvent::VFork(self.pid));
+        if let Some(parent) = self.parent.as_ref().and_then(|x| x.upgrade()) {
+            let mut parent_inner = parent.borrow_mut();
+            let ppid = parent_inner.pid;
+            let action = parent_inner.sig_actions[Signal::SIGCHLD as usize];
+            let flags = SignalFlag::from_bits_truncate(action.flags);
+            // 初始进程和设置了SA_NOCLDWAIT或者忽略SIGCHLD的父进程不保留僵尸进程
+            if ppid == INIT_PID || action.handler == SIG_IGN || flags.contains(SignalFlag::SA_NOCLDWAIT) {
+                parent_inner.children.retain(|x| x.as_ptr() as *const Process != self as *const Process);
+            }
+            drop(parent_inner);
+            // 先唤醒等待的父进程 使wait4不被SIGCHLD打断
+            wake_up(WaitEvent::Child(ppid));
+            // 正常退出时si_status为退出码 被信号结束时为信号编号
+            let (code, si_status) = match status & 0x7f {
+                0 => (CLD_EXITED, status >> 8 & 0xff),
+                signum if status & 0x80 != 0 => (CLD_DUMPED, signum),
+                signum => (CLD_KILLED, signum)
+            };
+            signal_process(ppid, SigInfo::from_child(self.pid, code, si_status));
+        }
+    }
+
+    // 撤销进程的信号量操作 与其他进程共享撤销记录时由最后一个退出的进程撤销
+    fn release_sem_undo(&mut self) {
+        let undo = core::mem::replace(&mut self.sem_undo, Rc::new(RefCell::new(SemUndo::new())));
+        if Rc::strong_count(&undo) == 1 {
+            undo.borrow_mut().apply(self.pid);
+        }
+    }
+
+    // 进程被信号结束 默认行为为转储的信号设置core标志
+    pub fn exit_by_signal(&mut self, signum: usize) {
+        let core = default_action(signum) == SigDefault::Core;
+        self.exit(signal_status(signum, core));
+    }
+
+    // 将子进程交给初始进程 已经退出的子进程直接回收
+    fn reparent_children(&mut self) {
+        let init = get_init_process();
+        let mut init_inner = init.borrow_mut();
+        for child in self.children.drain(..) {
+            if child.borrow().exit_code.is_some() {
+                continue;
+            }
+            child.borrow_mut().parent = Some(Rc::downgrade(&init));
+            init_inner.children.push(child);
+        }
+    }
+
+    // 进程被停止或者继续运行时通知父进程 父进程设置了SA_NOCLDSTOP时不通知
+    pub fn notify_parent_stop(&self, code: i32, signum: usize) {
+        if let Some(parent) = self.parent.as_ref().and_then(|x| x.upgrade()) {
+            let parent_inner = parent.borrow();
+            let ppid = parent_inner.pid;
+            let flags = SignalFlag::from_bits_truncate(parent_inner.sig_actions[Signal::SIGCHLD as usize].flags);
+            drop(parent_inner);
+            if !flags.contains(SignalFlag::SA_NOCLDSTOP) {
+                signal_process(ppid, SigInfo::from_child(self.pid, code, signum));
+            }
+        }
+    }
+
+    // 产生发送给进程的信号 由进程中的任务在返回用户态之前处理
+    pub fn send_signal(&mut self, info: SigInfo) {
+        self.prepare_signal(info.signo());
+        self.pending.add(info);
+    }
+
+    // 处理停止和继续信号 两者互相取消
+    pub fn prepare_signal(&mut self, signum: usize) {
+        if is_stop_signal(signum) {
+            self.pending.remove(Signal::SIGCONT as usize);
+        } else if signum == Signal::SIGCONT as usize || signum == Signal::SIGKILL as usize {
+            if self.stopped && signum == Signal::SIGCONT as usize {
+                self.notify_parent_stop(CLD_CONTINUED, signum);
+            }
+            self.stopped = false;
+            for stop in [Signal::SIGSTOP, Signal::SIGTSTP, Signal::SIGTTIN, Signal::SIGTTOU] {
+                self.pending.remove(stop as usize);
+            }
+        }
+    }
+
+    // 判断信号是否会被忽略
+    pub fn sig_ignored(&self, signum: usize) -> bool {
+        if is_unblockable(signum) {
+            return false;
+        }
+        match self.sig_actions[signum].handler {
+            SIG_IGN => true,
+            SIG_DFL => matches!(default_action(signum), SigDefault::Ignore | SigDefault::Continue),
+            _ => false
+        }
+    }
+
+    // 获取不会被忽略的信号
+    pub fn unignored(&self, set: SigSet) -> SigSet {
+        let mut set = set;
+        for signum in 1..SIGNAL_COUNT {
+            if set.contains(signum) && self.sig_ignored(signum) {
+                set.remove(signum);
+            }
+        }
+        set
+    }
+
+    // 执行新程序时将捕获的信号恢复为默认行为 忽略的信号保持不变
+    pub fn reset_sig_actions(&mut self) {
+        for action in self.sig_actions.iter_mut().filter(|x| x.handler != SIG_IGN) {
+            *action = SigAction::empty();
+        }
+    }
+
+    // 确保用户地址范围可以访问 处理写时复制和尚未分配的页面
+    fn prepare_user_access(&self, start: usize, len: usize, fault_type: PageFaultType) -> bool {
+        let flags = match fault_type {
+            PageFaultType::Load => PTEFlags::U | PTEFlags::R,
+            PageFaultType::Store => PTEFlags::U | PTEFlags::W,
+            PageFaultType::Instruction => PTEFlags::U | PTEFlags::X
+        };
+        let mut addr = start / PAGE_SIZE * PAGE_SIZE;
+        while addr < start + len {
+            let allowed = self.pmm.get_entry(addr.into())
+                .map_or(false, |x| x.flags().contains(flags));
+            if !allowed && !self.pmm.handle_page_fault(&self.vmas, addr, fault_type).unwrap_or(false) {
+                return false;
+            }
+            addr += PAGE_SIZE;
+        }
+        true
+    }
+
+    // 确保用户地址范围可以写入
+    pub fn prepare_user_write(&self, start: usize, len: usize) -> bool {
+        self.prepare_user_access(start, len, PageFaultType::Store)
+    }
+
+    // 确保用户地址范围可以读取
+    pub fn prepare_user_read(&self, start: usize, len: usize) -> bool {
+        self.prepare_user_access(start, len, PageFaultType::Load)
+    }
+
+    // 取消范围内的内存映射
+    pub fn unmap(&mut self, start: usize, end: usize) {
+        // 取消映射前先将共享文件映射写回
+        if let Err(err) = self.vmas.sync(start, end) {
+            warn!("写回共享映射失败: {:?}", err);
+        }
+        self.vmas.remove(start, end);
+        self.pmm.unmap_range(start, end);
+        self.mem_set.remove_range(VirtAddr::from(start).into(), VirtAddr::from(end).into());
+        // 区域全部被取消映射的共享内存段不再计入映射数量
+        let vmas = &self.vmas;
+        let detached: Vec<ShmAttach> = self.shm_attaches
+            .drain_filter(|attach| !vmas.0.iter().any(|x| attach.owns(x))).collect();
+        let mut ns = IPC_NS.lock();
+        for attach in detached {
+            ns.shm_detach(attach.id);
+        }
+    }
+
+    // 取消所有共享内存段的映射
+    fn detach_shm(&mut self) {
+        let mut ns = IPC_NS.lock();
+        for attach in self.shm_attaches.drain(..) {
+            ns.shm_detach(attach.id);
+        }
     }
 
     // 重置内存信息
     pub fn reset(&mut self) -> Result<(), RuntimeError>{
+        self.vmas.sync(0, usize::MAX)?;
+        self.pmm.release_cow();
         let pmm = Rc::new(PageMappingManager::new()?);
         let mem_set = MemSet::new();
         self.pmm = pmm;
         self.mem_set = mem_set;
         self.stack = UserStack::new(self.pmm.clone())?;
+        self.vmas.clear();
+        self.detach_shm();
+        self.vmas.push(self.stack.get_vma());
         Ok(())
     }
 
     // 释放内存
     pub fn release(&mut self) {
+        // 进程退出时将共享文件映射写回
+        if let Err(err) = self.vmas.sync(0, usize::MAX) {
+            warn!("写回共享映射失败: {:?}", err);
+        }
         self.stack.release();
         self.heap.mem_set.release();
         self.mem_set.release();
         self.pmm.release();
+        // 释放区域持有的共享内存
+        self.vmas.clear();
+        self.detach_shm();
     }
 }
     
\ No newline at end of file
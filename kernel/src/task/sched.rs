@@ -0,0 +1,249 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use super::task::Task;
+
+// 调度策略 与linux保持一致
+pub const SCHED_OTHER: usize = 0;
+pub const SCHED_FIFO: usize = 1;
+pub const SCHED_RR: usize = 2;
+pub const SCHED_BATCH: usize = 3;
+pub const SCHED_IDLE: usize = 5;
+
+// nice值范围
+pub const NICE_MIN: isize = -20;
+pub const NICE_MAX: isize = 19;
+// 实时任务优先级范围
+pub const RT_PRIO_MIN: usize = 1;
+pub const RT_PRIO_MAX: usize = 99;
+
+// SCHED_RR的时间片 单位us
+pub const RR_TIMESLICE_US: usize = 100_000;
+// 公平调度时 当前任务领先最小虚拟时间超过该值时让出 单位us
+const FAIR_GRANULARITY_US: usize = 4_000;
+// 唤醒的任务最多补偿的虚拟时间 防止长时间睡眠后独占
+const FAIR_SLEEPER_BONUS_US: usize = 20_000;
+
+// nice值对应的权重 与linux的sched_prio_to_weight相同
+const NICE_0_WEIGHT: usize = 1024;
+const NICE_TO_WEIGHT: [usize; 40] = [
+    /* -20 */ 88761, 71755, 56483, 46273, 36291,
+    /* -15 */ 29154, 23254, 18705, 14949, 11916,
+    /* -10 */ 9548, 7620, 6100, 4904, 3906,
+    /*  -5 */ 3121, 2501, 1991, 1586, 1277,
+    /*   0 */ 1024, 820, 655, 526, 423,
+    /*   5 */ 335, 272, 215, 172, 137,
+    /*  10 */ 110, 87, 70, 56, 45,
+    /*  15 */ 36, 29, 23, 18, 15,
+];
+// SCHED_IDLE 使用最低的权重
+const IDLE_WEIGHT: usize = 3;
+
+// 任务的调度信息
+#[derive(Clone, Copy)]
+pub struct SchedEntity {
+    pub policy: usize,          // 调度策略
+    pub nice: isize,            // 普通任务的nice值 -20 ~ 19
+    pub rt_priority: usize,     // 实时任务优先级 1 ~ 99 普通任务为0
+    pub vruntime: usize,        // 虚拟运行时间 单位us
+    pub sum_exec: usize,        // 总运行时间 单位us
+    pub slice_used: usize,      // 当前时间片已经使用的时间 单位us
+    pub affinity: usize         // 允许运行的核心掩码
+}
+
+impl SchedEntity {
+    pub fn new() -> Self {
+        Self {
+            policy: SCHED_OTHER,
+            nice: 0,
+            rt_priority: 0,
+            vruntime: 0,
+            sum_exec: 0,
+            slice_used: 0,
+            affinity: 1
+        }
+    }
+
+    // fork时子任务继承调度策略 运行时间重新计算
+    pub fn fork(&self) -> Self {
+        Self {
+            sum_exec: 0,
+            slice_used: 0,
+            ..*self
+        }
+    }
+
+    // 是否为实时任务
+    pub fn is_rt(&self) -> bool {
+        self.policy == SCHED_FIFO || self.policy == SCHED_RR
+    }
+
+    // 任务权重
+    pub fn weight(&self) -> usize {
+        match self.policy {
+            SCHED_IDLE => IDLE_WEIGHT,
+            _ => NICE_TO_WEIGHT[(self.nice - NICE_MIN) as usize]
+        }
+    }
+}
+
+// 调度类 调度器按照优先级依次询问调度类选择任务
+pub trait SchedClass {
+    // 判断任务是否属于该调度类
+    fn manages(&self, entity: &SchedEntity) -> bool;
+    // 从准备队列中选择下一个任务 返回下标
+    fn pick_next(&self, queue: &VecDeque<Rc<Task>>) -> Option<usize>;
+    // 任务加入准备队列
+    fn enqueue(&self, _entity: &mut SchedEntity, _queue: &VecDeque<Rc<Task>>) {}
+    // 记录任务运行的时间
+    fn update_curr(&self, entity: &mut SchedEntity, delta: usize);
+    // 判断当前任务是否需要让出
+    fn need_resched(&self, curr: &SchedEntity, queue: &VecDeque<Rc<Task>>) -> bool;
+}
+
+// 实时调度类 SCHED_FIFO/SCHED_RR 优先级高的任务先运行 同优先级按照队列顺序
+pub struct RtSched;
+
+impl SchedClass for RtSched {
+    fn manages(&self, entity: &SchedEntity) -> bool {
+        entity.is_rt()
+    }
+
+    fn pick_next(&self, queue: &VecDeque<Rc<Task>>) -> Option<usize> {
+        let mut next: Option<(usize, usize)> = None;
+        for (i, task) in queue.iter().enumerate() {
+            let entity = *task.sched.borrow();
+            if !entity.is_rt() {
+                continue;
+            }
+            if next.map_or(true, |(_, prio)| entity.rt_priority > prio) {
+                next = Some((i, entity.rt_priority));
+            }
+        }
+        next.map(|(i, _)| i)
+    }
+
+    fn update_curr(&self, entity: &mut SchedEntity, delta: usize) {
+        entity.sum_exec += delta;
+        if entity.policy == SCHED_RR {
+            entity.slice_used += delta;
+        }
+    }
+
+    fn need_resched(&self, curr: &SchedEntity, queue: &VecDeque<Rc<Task>>) -> bool {
+        // 存在更高优先级的任务
+        let higher = queue.iter().skip(1).any(|x| {
+            let entity = *x.sched.borrow();
+            entity.is_rt() && entity.rt_priority > curr.rt_priority
+        });
+        if higher {
+            return true;
+        }
+        // SCHED_RR 时间片用完后让给同优先级的任务 SCHED_FIFO 一直运行
+        curr.policy == SCHED_RR && curr.slice_used >= RR_TIMESLICE_US && queue.iter().skip(1).any(|x| {
+            let entity = *x.sched.borrow();
+            entity.is_rt() && entity.rt_priority == curr.rt_priority
+        })
+    }
+}
+
+// 公平调度类 SCHED_OTHER/SCHED_BATCH/SCHED_IDLE 选择虚拟运行时间最小的任务
+// 虚拟运行时间按照权重增长 nice值越小增长越慢
+pub struct FairSched;
+
+impl FairSched {
+    // 准备队列中普通任务的最小虚拟运行时间
+    fn min_vruntime(queue: &VecDeque<Rc<Task>>) -> Option<usize> {
+        queue.iter().map(|x| *x.sched.borrow())
+            .filter(|x| !x.is_rt())
+            .map(|x| x.vruntime).min()
+    }
+}
+
+impl SchedClass for FairSched {
+    fn manages(&self, entity: &SchedEntity) -> bool {
+        !entity.is_rt()
+    }
+
+    fn pick_next(&self, queue: &VecDeque<Rc<Task>>) -> Option<usize> {
+        let mut next: Option<(usize, usize)> = None;
+        for (i, task) in queue.iter().enumerate() {
+            let entity = *task.sched.borrow();
+            if entity.is_rt() {
+                continue;
+            }
+            if next.map_or(true, |(_, vruntime)| entity.vruntime < vruntime) {
+                next = Some((i, entity.vruntime));
+            }
+        }
+        next.map(|(i, _)| i)
+    }
+
+    fn enqueue(&self, entity: &mut SchedEntity, queue: &VecDeque<Rc<Task>>) {
+        // 新加入或者被唤醒的任务 虚拟时间不低于队列最小值减去补偿
+        if let Some(min) = Self::min_vruntime(queue) {
+            entity.vruntime = entity.vruntime.max(min.saturating_sub(FAIR_SLEEPER_BONUS_US));
+        }
+    }
+
+    fn update_curr(&self, entity: &mut SchedEntity, delta: usize) {
+        entity.sum_exec += delta;
+        entity.vruntime += delta * NICE_0_WEIGHT / entity.weight();
+    }
+
+    fn need_resched(&self, curr: &SchedEntity, queue: &VecDeque<Rc<Task>>) -> bool {
+        // 存在实时任务或者其他任务的虚拟时间落后太多
+        queue.iter().skip(1).any(|x| {
+            let entity = *x.sched.borrow();
+            entity.is_rt() || entity.vruntime + FAIR_GRANULARITY_US < curr.vruntime
+        })
+    }
+}
+
+// 调度策略 按照优先级保存调度类
+pub struct SchedPolicy(Vec<Box<dyn SchedClass>>);
+
+impl SchedPolicy {
+    // 默认的调度策略 实时任务优先于普通任务
+    pub fn new() -> Self {
+        Self(vec![Box::new(RtSched), Box::new(FairSched)])
+    }
+
+    // 获取任务对应的调度类
+    fn class_of(&self, entity: &SchedEntity) -> Option<&Box<dyn SchedClass>> {
+        self.0.iter().find(|x| x.manages(entity))
+    }
+
+    // 选择下一个任务 返回在准备队列中的下标
+    pub fn pick_next(&self, queue: &VecDeque<Rc<Task>>) -> Option<usize> {
+        self.0.iter().find_map(|x| x.pick_next(queue))
+    }
+
+    // 任务加入准备队列之前调用
+    pub fn enqueue(&self, task: &Rc<Task>, queue: &VecDeque<Rc<Task>>) {
+        let mut entity = *task.sched.borrow();
+        if let Some(class) = self.class_of(&entity) {
+            class.enqueue(&mut entity, queue);
+        }
+        *task.sched.borrow_mut() = entity;
+    }
+
+    // 记录任务运行的时间
+    pub fn update_curr(&self, task: &Rc<Task>, delta: usize) {
+        let mut entity = task.sched.borrow_mut();
+        if let Some(class) = self.class_of(&entity) {
+            class.update_curr(&mut entity, delta);
+        }
+    }
+
+    // 判断当前任务(队列头部)是否需要让出
+    pub fn need_resched(&self, queue: &VecDeque<Rc<Task>>) -> bool {
+        let curr = match queue.front() {
+            Some(task) => *task.sched.borrow(),
+            None => return false
+        };
+        self.class_of(&curr).map_or(false, |x| x.need_resched(&curr, queue))
+    }
+}
@@ -1,5 +1,35 @@
+use alloc::vec::Vec;
+
 use crate::interrupt::Context;
 
+// 信号编号需要小于该值 有效的信号为1~64
+pub const SIGNAL_COUNT: usize = 65;
+// 实时信号的范围 实时信号可以重复排队
+pub const SIGRTMIN: usize = 32;
+pub const SIGRTMAX: usize = 64;
+
+// siginfo_t 中的 si_code
+pub const SI_USER: i32 = 0;             // kill
+pub const SI_KERNEL: i32 = 0x80;        // 内核产生
+pub const SI_QUEUE: i32 = -1;           // sigqueue
+pub const SI_TIMER: i32 = -2;           // POSIX定时器
+pub const SI_TKILL: i32 = -6;           // tkill/tgkill
+pub const CLD_EXITED: i32 = 1;          // 子进程退出
+pub const CLD_KILLED: i32 = 2;          // 子进程被信号结束
+pub const CLD_DUMPED: i32 = 3;          // 子进程被信号结束并转储
+pub const CLD_STOPPED: i32 = 5;         // 子进程被停止
+pub const CLD_CONTINUED: i32 = 6;       // 子进程继续运行
+pub const SEGV_MAPERR: i32 = 1;         // 地址没有映射
+pub const SEGV_ACCERR: i32 = 2;         // 没有访问权限
+pub const ILL_ILLOPC: i32 = 1;          // 非法指令
+pub const ILL_ILLTRP: i32 = 4;          // 非法的异常
+pub const BUS_ADRALN: i32 = 1;          // 地址未对齐
+pub const TRAP_BRKPT: i32 = 1;          // 断点
+
+// 特殊的信号处理函数
+pub const SIG_DFL: usize = 0;       // 默认行为
+pub const SIG_IGN: usize = 1;       // 忽略信号
+
 pub enum Signal {
     SIGHUP = 1,
     SIGINT = 2,
@@ -68,7 +98,54 @@ pub enum Signal {
     SIGRT64 = 64,
 }
 
-#[derive(Clone, Copy, Debug)]
+// 信号的默认行为
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SigDefault {
+    Terminate,      // 结束进程
+    Core,           // 结束进程并转储
+    Stop,           // 停止进程
+    Continue,       // 继续运行被停止的进程
+    Ignore          // 忽略
+}
+
+// 获取信号的默认行为 实时信号默认结束进程
+pub fn default_action(signum: usize) -> SigDefault {
+    const SIGQUIT: usize = Signal::SIGQUIT as usize;
+    const SIGILL: usize = Signal::SIGILL as usize;
+    const SIGTRAP: usize = Signal::SIGTRAP as usize;
+    const SIGABRT: usize = Signal::SIGABRT as usize;
+    const SIGBUS: usize = Signal::SIGBUS as usize;
+    const SIGFPE: usize = Signal::SIGFPE as usize;
+    const SIGSEGV: usize = Signal::SIGSEGV as usize;
+    const SIGXCPU: usize = Signal::SIGXCPU as usize;
+    const SIGXFSZ: usize = Signal::SIGXFSZ as usize;
+    const SIGSYS: usize = Signal::SIGSYS as usize;
+    const SIGCHLD: usize = Signal::SIGCHLD as usize;
+    const SIGURG: usize = Signal::SIGURG as usize;
+    const SIGWINCH: usize = Signal::SIGWINCH as usize;
+    const SIGCONT: usize = Signal::SIGCONT as usize;
+    match signum {
+        SIGQUIT | SIGILL | SIGTRAP | SIGABRT | SIGBUS | SIGFPE | SIGSEGV
+            | SIGXCPU | SIGXFSZ | SIGSYS => SigDefault::Core,
+        SIGCHLD | SIGURG | SIGWINCH => SigDefault::Ignore,
+        SIGCONT => SigDefault::Continue,
+        _ if is_stop_signal(signum) => SigDefault::Stop,
+        _ => SigDefault::Terminate
+    }
+}
+
+// 判断是否为停止进程的信号
+pub fn is_stop_signal(signum: usize) -> bool {
+    signum == Signal::SIGSTOP as usize || signum == Signal::SIGTSTP as usize
+        || signum == Signal::SIGTTIN as usize || signum == Signal::SIGTTOU as usize
+}
+
+// 判断信号是否不能被捕获、忽略或者屏蔽
+pub fn is_unblockable(signum: usize) -> bool {
+    signum == Signal::SIGKILL as usize || signum == Signal::SIGSTOP as usize
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct SigSet(u64);
 
 impl SigSet {
@@ -87,6 +164,60 @@ impl SigSet {
     pub fn new(val: u64) -> Self {
         Self(val)
     }
+
+    // 添加信号
+    pub fn add(&mut self, signum: usize) {
+        self.0 |= 1 << (signum - 1);
+    }
+
+    // 移除信号
+    pub fn remove(&mut self, signum: usize) {
+        self.0 &= !(1 << (signum - 1));
+    }
+
+    // 判断是否包含信号
+    pub fn contains(&self, signum: usize) -> bool {
+        self.0 & (1 << (signum - 1)) != 0
+    }
+
+    // 判断是否为空
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    // 获取未被屏蔽的信号
+    pub fn unmasked(&self, mask: &SigSet) -> SigSet {
+        Self(self.0 & !mask.0)
+    }
+
+    // 获取同时在两个集合中的信号
+    pub fn intersect(&self, other: &SigSet) -> SigSet {
+        Self(self.0 & other.0)
+    }
+
+    // 获取两个集合中所有的信号
+    pub fn union(&self, other: &SigSet) -> SigSet {
+        Self(self.0 | other.0)
+    }
+
+    // 获取不在集合中的信号
+    pub fn complement(&self) -> SigSet {
+        Self(!self.0)
+    }
+
+    // 移除不能被屏蔽的信号 设置信号掩码时使用
+    pub fn remove_unblockable(&mut self) {
+        self.remove(Signal::SIGKILL as usize);
+        self.remove(Signal::SIGSTOP as usize);
+    }
+
+    // 获取编号最小的信号
+    pub fn first(&self) -> Option<usize> {
+        match self.0 {
+            0 => None,
+            bits => Some(bits.trailing_zeros() as usize + 1)
+        }
+    }
 }
 
 impl Default for SigSet {
@@ -143,6 +274,102 @@ impl SigAction {
     }
 }
 
+// 信号信息 与linux的siginfo_t布局相同 共128字节
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SigInfo {
+    pub si_signo: i32,
+    pub si_errno: i32,
+    pub si_code: i32,
+    _pad: i32,
+    pub fields: [usize; 14]     // 根据信号来源使用的联合体
+}
+
+impl SigInfo {
+    pub fn new(signo: usize, code: i32) -> Self {
+        Self { si_signo: signo as i32, si_errno: 0, si_code: code, _pad: 0, fields: [0; 14] }
+    }
+
+    // 由进程发送的信号 记录发送者的pid和uid
+    pub fn from_process(signo: usize, code: i32, pid: usize) -> Self {
+        let mut info = Self::new(signo, code);
+        info.fields[0] = pid as u32 as usize;
+        info
+    }
+
+    // 定时器产生的信号 记录定时器编号、超限次数和sigev_value
+    pub fn from_timer(signo: usize, id: usize, overrun: usize, value: usize) -> Self {
+        let mut info = Self::new(signo, SI_TIMER);
+        info.fields[0] = id as u32 as usize | (overrun as u32 as usize) << 32;
+        info.fields[1] = value;
+        info
+    }
+
+    // SIGCHLD 记录子进程的pid和状态
+    pub fn from_child(pid: usize, code: i32, status: usize) -> Self {
+        let mut info = Self::from_process(Signal::SIGCHLD as usize, code, pid);
+        info.fields[1] = status as u32 as usize;
+        info
+    }
+
+    // 异常产生的信号 记录出错的地址
+    pub fn from_fault(signo: usize, code: i32, addr: usize) -> Self {
+        let mut info = Self::new(signo, code);
+        info.fields[0] = addr;
+        info
+    }
+
+    // 信号编号
+    pub fn signo(&self) -> usize {
+        self.si_signo as usize
+    }
+}
+
+// 等待处理的信号 普通信号最多保存一个 实时信号按照发送顺序排队
+#[derive(Clone)]
+pub struct SigPending {
+    pub set: SigSet,
+    queue: Vec<SigInfo>
+}
+
+impl SigPending {
+    pub fn new() -> Self {
+        Self { set: SigSet::new(0), queue: vec![] }
+    }
+
+    // 添加信号 已经存在的普通信号不再重复添加
+    pub fn add(&mut self, info: SigInfo) {
+        let signum = info.signo();
+        if signum < SIGRTMIN && self.set.contains(signum) {
+            return;
+        }
+        self.set.add(signum);
+        self.queue.push(info);
+    }
+
+    // 判断是否包含信号
+    pub fn contains(&self, signum: usize) -> bool {
+        self.set.contains(signum)
+    }
+
+    // 移除信号的所有实例
+    pub fn remove(&mut self, signum: usize) {
+        self.set.remove(signum);
+        self.queue.retain(|x| x.signo() != signum);
+    }
+
+    // 取出编号最小的未被屏蔽的信号 同一个信号按照发送顺序取出
+    pub fn take_unmasked(&mut self, mask: &SigSet) -> Option<SigInfo> {
+        let signum = self.set.unmasked(mask).first()?;
+        let index = self.queue.iter().position(|x| x.signo() == signum)?;
+        let info = self.queue.remove(index);
+        if self.queue.iter().all(|x| x.signo() != signum) {
+            self.set.remove(signum);
+        }
+        Some(info)
+    }
+}
+
 bitflags! {
     pub struct SignalStackFlags : u32 {
         const ONSTACK = 1;
@@ -159,6 +386,21 @@ pub struct SignalStack {
     pub size: usize,
 }
 
+// 信号栈的最小大小
+pub const MINSIGSTKSZ: usize = 2048;
+
+impl SignalStack {
+    // 未启用的信号栈
+    pub fn disabled() -> Self {
+        Self { sp: 0, flags: SignalStackFlags::DISABLE, size: 0 }
+    }
+
+    // 判断地址是否在信号栈中
+    pub fn contains(&self, sp: usize) -> bool {
+        !self.flags.contains(SignalStackFlags::DISABLE) && sp > self.sp && sp <= self.sp + self.size
+    }
+}
+
 
 #[repr(C)]
 #[derive(Clone)]
@@ -169,4 +411,12 @@ pub struct SignalUserContext {
     pub sig_mask: SigSet,       // 5
     pub _pad: [u64; 16], // very strange, maybe a bug of musl libc
     pub context: Context,       // pc offset = 22 - 6=16
+}
+
+// 进入信号处理函数时压入用户栈的结构 与linux的rt_sigframe布局相同
+#[repr(C)]
+pub struct SigFrame {
+    pub info: SigInfo,
+    pub ucontext: SignalUserContext,
+    _fp_state: [u64; 64]        // 浮点寄存器的位置 目前不保存
 }
\ No newline at end of file
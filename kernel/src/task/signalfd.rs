@@ -0,0 +1,116 @@
+use core::cell::RefCell;
+
+use crate::fs::file::FileOP;
+
+use super::signal::SigInfo;
+use super::signal::SigSet;
+use super::signal::Signal;
+use super::signal::SI_TIMER;
+
+// signalfd4 的标志
+pub const SFD_NONBLOCK: usize = 0o4000;
+pub const SFD_CLOEXEC: usize = 0o2000000;
+
+// 从signalfd读取的信号信息 与linux的signalfd_siginfo布局相同 共128字节
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalfdSigInfo {
+    pub ssi_signo: u32,
+    pub ssi_errno: i32,
+    pub ssi_code: i32,
+    pub ssi_pid: u32,
+    pub ssi_uid: u32,
+    pub ssi_fd: i32,
+    pub ssi_tid: u32,
+    pub ssi_band: u32,
+    pub ssi_overrun: u32,
+    pub ssi_trapno: u32,
+    pub ssi_status: i32,
+    pub ssi_int: i32,
+    pub ssi_ptr: u64,
+    pub ssi_utime: u64,
+    pub ssi_stime: u64,
+    pub ssi_addr: u64,
+    pub ssi_addr_lsb: u16,
+    _pad2: u16,
+    pub ssi_syscall: i32,
+    pub ssi_call_addr: u64,
+    pub ssi_arch: u32,
+    _pad: [u8; 28]
+}
+
+impl From<&SigInfo> for SignalfdSigInfo {
+    // 根据信号来源解析siginfo_t中的联合体
+    fn from(info: &SigInfo) -> Self {
+        let mut ssi = Self {
+            ssi_signo: info.si_signo as u32,
+            ssi_errno: info.si_errno,
+            ssi_code: info.si_code,
+            ssi_pid: 0, ssi_uid: 0, ssi_fd: 0, ssi_tid: 0, ssi_band: 0,
+            ssi_overrun: 0, ssi_trapno: 0, ssi_status: 0, ssi_int: 0, ssi_ptr: 0,
+            ssi_utime: 0, ssi_stime: 0, ssi_addr: 0, ssi_addr_lsb: 0, _pad2: 0,
+            ssi_syscall: 0, ssi_call_addr: 0, ssi_arch: 0, _pad: [0; 28]
+        };
+        let signo = info.signo();
+        let fault = signo == Signal::SIGSEGV as usize || signo == Signal::SIGBUS as usize
+            || signo == Signal::SIGILL as usize || signo == Signal::SIGFPE as usize;
+        if info.si_code == SI_TIMER {
+            // 定时器编号、超限次数和sigev_value
+            ssi.ssi_tid = info.fields[0] as u32;
+            ssi.ssi_overrun = (info.fields[0] >> 32) as u32;
+            ssi.ssi_int = info.fields[1] as i32;
+            ssi.ssi_ptr = info.fields[1] as u64;
+        } else if fault && info.si_code > 0 {
+            // 异常产生的信号 出错的地址
+            ssi.ssi_addr = info.fields[0] as u64;
+        } else {
+            // 发送者的pid和uid 子进程的状态或者sigqueue附带的数据
+            ssi.ssi_pid = info.fields[0] as u32;
+            ssi.ssi_uid = (info.fields[0] >> 32) as u32;
+            if signo == Signal::SIGCHLD as usize {
+                ssi.ssi_status = info.fields[1] as i32;
+                ssi.ssi_utime = info.fields[2] as u64;
+                ssi.ssi_stime = info.fields[3] as u64;
+            } else {
+                ssi.ssi_int = info.fields[1] as i32;
+                ssi.ssi_ptr = info.fields[1] as u64;
+            }
+        }
+        ssi
+    }
+}
+
+// signalfd 以文件的形式读取等待处理的信号
+// 读取需要访问任务的信号队列 由sys_read调用Task::read_signalfd完成
+pub struct SignalFd {
+    pub mask: RefCell<SigSet>,      // 可以读取的信号
+    pub nonblock: bool              // 没有信号时是否立即返回EAGAIN
+}
+
+impl SignalFd {
+    pub fn new(mask: SigSet, nonblock: bool) -> Self {
+        Self { mask: RefCell::new(mask), nonblock }
+    }
+}
+
+impl FileOP for SignalFd {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writeable(&self) -> bool {
+        false
+    }
+
+    fn read_at(&self, _pos: usize, _data: &mut [u8]) -> usize {
+        0
+    }
+
+    fn write_at(&self, _pos: usize, _data: &[u8], _count: usize) -> usize {
+        0
+    }
+
+    fn get_size(&self) -> usize {
+        0
+    }
+}
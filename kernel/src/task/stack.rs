@@ -8,12 +8,16 @@ use crate::memory::addr::VirtAddr;
 use crate::memory::addr::PAGE_SIZE;
 use crate::memory::mem_set::MemSet;
 use crate::memory::mem_map::MemMap;
+use crate::memory::vma::Vma;
+use crate::memory::vma::VmaType;
 use crate::runtime_err::RuntimeError;
 
 
 pub const PTR_SIZE: usize = 8;
 pub const DEFAULT_STACK_PAGE_NUM: usize = 40;
 pub const DEFAULT_STACK_ADDR: usize = 0xf0010000;
+// 栈可以增长到的最低地址
+pub const DEFAULT_STACK_LIMIT: usize = 0xef000000;
 
 #[derive(Clone)]
 pub struct UserStack {
@@ -131,15 +135,8 @@ impl UserStack {
         self.mem_set.release();
     }
 
-    pub fn alloc_until(&mut self, until_addr: usize) -> Result<(), RuntimeError> {
-        loop {
-            if until_addr >= self.top { break; }
-            let start_page = self.top / PAGE_SIZE - 1;
-            let mem_map = MemMap::new(start_page.into(), 1, PTEFlags::UVRWX)?;
-            self.pmm.add_mapping_by_map(&mem_map)?;
-            self.mem_set.inner().push(mem_map);
-            self.top -= PAGE_SIZE;
-        }
-        Ok(())
+    // 获取栈的内存区域 超出已映射部分的页面在缺页时分配
+    pub fn get_vma(&self) -> Vma {
+        Vma::new(DEFAULT_STACK_LIMIT, self.bottom, PTEFlags::UVRWX, VmaType::Stack)
     }
 }
\ No newline at end of file
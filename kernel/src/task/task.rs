@@ -1,12 +1,17 @@
 use core::cell::{RefCell, RefMut};
 use alloc::rc::Rc;
 use crate::interrupt::timer::TimeSpec;
+use crate::interrupt::timer::get_time_us;
+use crate::sys_call::consts::EINTR;
 use crate::memory::addr::UserAddr;
 use crate::interrupt::Context;
 use crate::task::task_scheduler::kill_task;
 
 use super::process::Process;
 use super::signal::SigSet;
+use super::signal::SigPending;
+use super::signal::SignalStack;
+use super::sched::SchedEntity;
 
 #[allow(unused)]
 #[derive(Clone, Copy)]
@@ -45,8 +50,46 @@ pub struct TaskInner {
     pub context: Context,
     pub process: Rc<RefCell<Process>>,
     pub status: TaskStatus,
-    pub wake_time: usize,
-    pub sig_mask: SigSet
+    pub sig_mask: SigSet,
+    pub pending: SigPending,        // 发送给该线程的信号
+    pub sig_altstack: SignalStack,  // 信号处理函数使用的栈
+    pub orig_a0: usize,             // 系统调用的第一个参数 重新执行系统调用时恢复
+    pub sig_restart: bool,          // 系统调用被信号打断 处理信号后可以重新执行
+    pub saved_mask: Option<SigSet>, // rt_sigsuspend 替换前的信号掩码 进入信号处理函数时恢复
+    pub poll_deadline: Option<usize>,   // poll、select和epoll等待的截止时间 唤醒后重新执行时继续使用
+    pub pipe_written: usize,        // 阻塞的管道写入已经写入的长度 重新执行时继续写入剩余的部分
+    pub sleep_rem: Option<(UserAddr<TimeSpec>, usize)>  // nanosleep的rem和唤醒时间 被信号打断时写入剩余的时间
+}
+
+impl TaskInner {
+    // 被信号打断的系统调用 restart为true时回退到ecall重新执行 否则返回EINTR
+    pub fn restart_syscall(&mut self, restart: bool) {
+        if self.sig_restart {
+            self.sig_restart = false;
+            if restart {
+                self.context.x[10] = self.orig_a0;
+                self.context.sepc -= 4;
+            }
+        }
+    }
+
+    // 被信号打断的nanosleep在rem中写入剩余的时间 需要在任务的页表下调用
+    pub fn finish_sleep(&mut self) {
+        if let Some((rem, wake_time)) = self.sleep_rem.take() {
+            if self.context.x[10] == EINTR {
+                *rem.transfer() = TimeSpec::from_us(wake_time.saturating_sub(get_time_us()));
+            }
+        }
+    }
+
+    // 进入信号处理函数时结束被打断的管道写入 已经写入部分数据时返回写入的长度
+    pub fn finish_partial_write(&mut self) {
+        if self.sig_restart && self.pipe_written > 0 {
+            self.sig_restart = false;
+            self.context.x[10] = self.pipe_written;
+        }
+        self.pipe_written = 0;
+    }
 }
 
 #[derive(Clone)]
@@ -54,6 +97,7 @@ pub struct Task {
     pub tid: usize,
     pub pid: usize,
     pub clear_child_tid: RefCell<UserAddr<u32>>,
+    pub sched: RefCell<SchedEntity>,
     pub inner: Rc<RefCell<TaskInner>>
 }
 
@@ -66,12 +110,20 @@ impl Task {
             tid,
             pid,
             clear_child_tid: RefCell::new(0.into()),
+            sched: RefCell::new(SchedEntity::new()),
             inner: Rc::new(RefCell::new(TaskInner {
                 context: Context::new(), 
                 process: process.clone(), 
                 status: TaskStatus::READY,
-                wake_time: 0,
-                sig_mask: SigSet::new(0)
+                sig_mask: SigSet::new(0),
+                pending: SigPending::new(),
+                sig_altstack: SignalStack::disabled(),
+                orig_a0: 0,
+                sig_restart: false,
+                saved_mask: None,
+                poll_deadline: None,
+                pipe_written: 0,
+                sleep_rem: None
             }))
         });
         process_mut.tasks.push(Rc::downgrade(&task));
@@ -21,7 +21,7 @@ lazy_static! {
         "busybox sh test.sh sin30.lua",
         "busybox sh test.sh sort.lua",
         "busybox sh test.sh strings.lua",
-        "busybox sh run-dynamic.sh"
+        "busybox sh run-dynamic.sh",
         
         // // lmbench_all
         // "busybox mkdir -p /var/tmp",
@@ -38,7 +38,7 @@ lazy_static! {
         // "lmbench_all lat_select -n 100 -P 1 file",
         // "lmbench_all lat_sig -P 1 install",
         // "lmbench_all lat_sig -P 1 catch",
-        // "lmbench_all lat_proc -P 1 fork",
+        "lmbench_all lat_proc -P 1 fork",
         // "lmbench_all lat_proc -P 1 exec",
         // "lmbench_all lat_proc -P 1 shell",
         // "lmbench_all lat_mmap -P 1 512k /var/tmp/XXX",
@@ -1,19 +1,35 @@
 use core::arch::asm;
+use riscv::register::sstatus;
 
 use alloc::collections::VecDeque;
 use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
 use crate::sync::mutex::Mutex;
-use crate::sys_call::is_vfork_wait;
 use crate::task::pid::PidGenerater;
 use crate::interrupt::timer::task_time_refresh;
+use crate::interrupt::timer::get_time_us;
+use crate::interrupt::timer::set_next_event;
+use crate::interrupt::timer::take_expired_timers;
+use crate::interrupt::timer::next_timer_deadline;
+use crate::interrupt::timer::TimerEvent;
 use crate::memory::page_table::switch_to_kernel_page;
 use super::task::Task;
+use super::process::Process;
 use super::task::TaskStatus;
 use super::task_queue::load_next_task;
+use super::wait_queue::WaitQueue;
+use super::wait_queue::WaitEvent;
+use super::sched::SchedPolicy;
+use super::signal::SigInfo;
+use super::epoll::epoll_notify;
+use crate::sys_call::consts::EINTR;
 
 // 任务控制器管理器
 pub struct TaskScheduler {
     pub queue: VecDeque<Rc<Task>>,          // 准备队列
+    pub wait_queue: WaitQueue,              // 等待队列
+    pub policy: SchedPolicy,                // 调度策略
     pub is_run: bool                    // 任务运行标志
 }
 
@@ -22,33 +38,203 @@ impl TaskScheduler {
     pub fn new() -> Self {
         Self {
             queue: VecDeque::new(),
+            wait_queue: WaitQueue::new(),
+            policy: SchedPolicy::new(),
             is_run: false
         }
     }
 
     // 添加任务调度器
     pub fn add_task(&mut self, task: Rc<Task>) {
+        self.policy.enqueue(&task, &self.queue);
         self.queue.push_back(task.clone());
     }
 
-    // 执行下一个任务
+    // 执行下一个任务 当前任务放到队尾 由调度策略选择下一个任务放到队首
     pub fn switch_next(&mut self) {
         if let Some(task) = self.queue.pop_front() {
             // task.before_run();
             task.inner.borrow_mut().status = TaskStatus::READY;
+            task.sched.borrow_mut().slice_used = 0;
             self.queue.push_back(task);
+            if let Some(index) = self.policy.pick_next(&self.queue) {
+                let next = self.queue.remove(index).unwrap();
+                self.queue.push_front(next);
+            }
             self.queue[0].before_run();
         }
         task_time_refresh();     
     }
 
+    // 判断当前任务是否需要让出
+    pub fn need_resched(&self) -> bool {
+        self.policy.need_resched(&self.queue)
+    }
+
+    // 挂起当前任务 从准备队列移动到等待队列
+    pub fn wait_current(&mut self, event: WaitEvent, timeout: Option<usize>) {
+        if let Some(task) = self.queue.pop_front() {
+            self.wait_queue.push(task, event, timeout);
+        }
+        task_time_refresh();
+    }
+
+    // 挂起当前任务 同时等待多个事件
+    pub fn wait_current_poll(&mut self, events: Vec<WaitEvent>, timeout: Option<usize>) {
+        if let Some(task) = self.queue.pop_front() {
+            self.wait_queue.push_poll(task, events, timeout);
+        }
+        task_time_refresh();
+    }
+
+    // 唤醒等待事件的任务 最多唤醒count个 返回被唤醒的任务
+    pub fn wake_up(&mut self, event: WaitEvent, count: usize) -> Vec<Rc<Task>> {
+        let tasks = self.wait_queue.take(event, count);
+        for task in &tasks {
+            task.inner.borrow_mut().status = TaskStatus::READY;
+            self.policy.enqueue(task, &self.queue);
+            self.queue.push_back(task.clone());
+        }
+        // 通知监听该事件的epoll 唤醒等待epoll的任务
+        for epoll_event in epoll_notify(event) {
+            self.wake_up(epoll_event, usize::MAX);
+        }
+        tasks
+    }
+
+    // 处理到期的定时器 唤醒已经超时的任务
+    pub fn wake_expired(&mut self) {
+        for event in take_expired_timers() {
+            match event {
+                TimerEvent::Wake(pid, tid) => {
+                    if let Some(task) = self.wait_queue.take_timeout(pid, tid) {
+                        task.inner.borrow_mut().status = TaskStatus::READY;
+                        self.policy.enqueue(&task, &self.queue);
+                        self.queue.push_back(task);
+                    }
+                }
+                TimerEvent::ITimerReal(pid) => {
+                    if let Some(process) = self.get_process(pid) {
+                        process.borrow_mut().itimer_real_expired();
+                        self.notify_signal(pid);
+                    }
+                }
+                TimerEvent::PosixTimer(pid, id) => {
+                    if let Some(process) = self.get_process(pid) {
+                        process.borrow_mut().posix_timer_expired(id);
+                        self.notify_signal(pid);
+                    }
+                }
+            }
+        }
+    }
+
+    // 根据pid查找进程
+    pub fn get_process(&self, pid: usize) -> Option<Rc<RefCell<Process>>> {
+        self.queue.iter().find(|x| x.pid == pid).cloned()
+            .or_else(|| self.wait_queue.get_process_tasks(pid).pop())
+            .map(|x| x.get_process())
+    }
+
+    // 获取所有进程
+    pub fn get_processes(&self) -> Vec<Rc<RefCell<Process>>> {
+        let mut processes: Vec<Rc<RefCell<Process>>> = vec![];
+        for task in self.queue.iter().cloned().chain(self.wait_queue.get_tasks()) {
+            if processes.iter().all(|x| x.borrow().pid != task.pid) {
+                processes.push(task.get_process());
+            }
+        }
+        processes
+    }
+
+    // 进程收到信号后 恢复被停止的任务并唤醒可以处理信号的任务
+    pub fn notify_signal(&mut self, pid: usize) {
+        let process = match self.get_process(pid) {
+            Some(process) => process,
+            None => return
+        };
+        let process = process.borrow();
+        if process.stopped {
+            return;
+        }
+        for task in self.wait_queue.take(WaitEvent::Stopped(pid), usize::MAX) {
+            task.inner.borrow_mut().status = TaskStatus::READY;
+            self.policy.enqueue(&task, &self.queue);
+            self.queue.push_back(task);
+        }
+        // 准备队列中有任务可以处理进程的信号时不需要唤醒其他任务
+        let mut shared = process.unignored(process.pending.set);
+        for task in self.queue.iter().filter(|x| x.pid == pid) {
+            shared = shared.intersect(&task.inner.borrow().sig_mask);
+        }
+        for task in self.wait_queue.get_process_tasks(pid) {
+            let mut inner = task.inner.borrow_mut();
+            // 等待的信号到达时唤醒任务 重新执行系统调用取出信号 poll监听signalfd时同样处理
+            let pending = inner.pending.set.union(&process.pending.set);
+            let waited = match self.wait_queue.get_event(pid, task.tid) {
+                Some(WaitEvent::Signal(set)) => !pending.intersect(&set).is_empty(),
+                Some(WaitEvent::Poll) => self.wait_queue.get_polled(pid, task.tid).iter()
+                    .any(|x| matches!(x, WaitEvent::Signal(set) if !pending.intersect(set).is_empty())),
+                _ => false
+            };
+            let own = process.unignored(inner.pending.set).unmasked(&inner.sig_mask);
+            if !waited && own.is_empty() && shared.unmasked(&inner.sig_mask).is_empty() {
+                continue;
+            }
+            let event = match self.wait_queue.take_interrupted(pid, task.tid) {
+                Some(event) => event,
+                None => continue
+            };
+            shared = shared.intersect(&inner.sig_mask);
+            // 被打断的系统调用返回EINTR 处理信号时根据SA_RESTART决定是否重新执行 睡眠不重新执行
+            match event {
+                // wait4、阻塞的管道和套接字读写、命名管道的打开在等待之前回退了pc
+                WaitEvent::Child(_) | WaitEvent::Pipe(_) | WaitEvent::FifoOpen(..) | WaitEvent::Socket(_) | WaitEvent::File(_) => {
+                    inner.context.sepc += 4;
+                    inner.sig_restart = true;
+                }
+                WaitEvent::Signal(_) if waited => inner.sig_restart = true,
+                // poll等待的signalfd可以读取时重新执行 被其他信号打断时返回EINTR
+                WaitEvent::Poll => {
+                    inner.context.sepc += 4;
+                    inner.sig_restart = waited;
+                    if !waited {
+                        inner.poll_deadline = None;
+                    }
+                }
+                // 睡眠和等待信号被其他信号打断时不重新执行 System V IPC的等待不会重新执行
+                WaitEvent::Timer | WaitEvent::Signal(_) | WaitEvent::Sem(_) | WaitEvent::Msg(_) => {}
+                _ => inner.sig_restart = true
+            }
+            inner.context.x[10] = EINTR;
+            inner.status = TaskStatus::READY;
+            drop(inner);
+            self.policy.enqueue(&task, &self.queue);
+            self.queue.push_back(task);
+        }
+    }
+
     // 执行第一个任务
     /// 进行调度更新
     pub fn start(&mut self) {
         info!("开始执行任务");
+        let mut last_task: Option<Rc<Task>> = None;
         loop {
+            // 唤醒已经超时的任务
+            self.wake_expired();
             // 没有任务时从任务队列取出任务
             if self.queue.len() == 0 {
+                // 存在未到期的定时器 设置单次时钟中断后等待
+                // wfi不受SIE影响 中断已经到来时直接返回 之后短暂打开中断处理时钟中断
+                if next_timer_deadline().is_some() {
+                    set_next_event(false);
+                    unsafe {
+                        asm!("wfi");
+                        sstatus::set_sie();
+                        sstatus::clear_sie();
+                    }
+                    continue;
+                }
                 if !load_next_task() {
                     break;
                 }
@@ -59,27 +245,44 @@ impl TaskScheduler {
                     }
                 }
             }
-            // TODO: 判断是否存在等待中的任务 如果存在就切换任务
             let task = self.queue[0].clone();
-            if is_vfork_wait(task.pid) {
-                switch_next();
-                continue;
+            // 任务发生变化时切换页表
+            if !last_task.as_ref().map_or(false, |x| Rc::ptr_eq(x, &task)) {
+                task.before_run();
+                last_task = Some(task.clone());
             }
             self.is_run = true;
+            // 有其他任务时在时间片结束时产生中断 否则只在定时器到期时产生
+            set_next_event(self.queue.len() > 1);
             warn!("执行pid: {}   tid: {}   tasks len: {}", task.pid, task.tid, self.queue.len());
+            // 返回用户态之前处理信号 任务被停止或者结束时重新调度
+            if let Err(err) = task.handle_signals() {
+                warn!("信号处理失败: {:?}", err);
+            }
+            if !self.queue.front().map_or(false, |x| Rc::ptr_eq(x, &task)) {
+                continue;
+            }
+            let start = get_time_us();
             task.run();
+            let user_end = get_time_us();
+            // 记录任务运行的时间 供调度策略使用
+            self.policy.update_curr(&task, user_end - start);
             task.catch();
+            // 更新进程的运行时间 处理ITIMER_VIRTUAL和ITIMER_PROF
+            task.get_process().borrow_mut().account_time(user_end - start, get_time_us() - user_end);
         }
     }
 
     // 关闭进程
     pub fn kill_process(&mut self, pid: usize) {
         self.queue = self.queue.clone().into_iter().filter(|x| x.pid != pid).collect();
+        self.wait_queue.remove_process(pid);
     }
 
     // 关闭进程
     pub fn kill_task(&mut self, pid: usize, tid: usize) {
         self.queue = self.queue.clone().into_iter().filter(|x| x.pid != pid || x.tid != tid).collect();
+        self.wait_queue.remove_task(pid, tid);
     }
 
 }
@@ -116,6 +319,46 @@ pub fn switch_next() {
     TASK_SCHEDULER.force_get().switch_next();
 }
 
+// 判断当前任务是否需要让出
+pub fn need_resched() -> bool {
+    TASK_SCHEDULER.force_get().need_resched()
+}
+
+// 处理到期的定时器
+pub fn wake_expired() {
+    TASK_SCHEDULER.force_get().wake_expired();
+}
+
+// 挂起当前任务 等待事件唤醒或者超时(单位us)
+pub fn wait_current(event: WaitEvent, timeout: Option<usize>) {
+    TASK_SCHEDULER.force_get().wait_current(event, timeout);
+}
+
+// 挂起当前任务 等待任意一个事件唤醒或者超时(单位us)
+pub fn wait_poll(events: Vec<WaitEvent>, timeout: Option<usize>) {
+    TASK_SCHEDULER.force_get().wait_current_poll(events, timeout);
+}
+
+// 唤醒等待事件的所有任务 返回唤醒的数量
+pub fn wake_up(event: WaitEvent) -> usize {
+    TASK_SCHEDULER.force_get().wake_up(event, usize::MAX).len()
+}
+
+// 唤醒等待事件的任务 最多唤醒count个
+pub fn wake_up_count(event: WaitEvent, count: usize) -> Vec<Rc<Task>> {
+    TASK_SCHEDULER.force_get().wake_up(event, count)
+}
+
+// 将等待事件的任务转移到新的事件上
+pub fn wait_requeue(from: WaitEvent, to: WaitEvent, count: usize) -> usize {
+    TASK_SCHEDULER.force_get().wait_queue.requeue(from, to, count)
+}
+
+// 获取任务正在等待的事件
+pub fn get_wait_event(pid: usize, tid: usize) -> Option<WaitEvent> {
+    TASK_SCHEDULER.force_get().wait_queue.get_event(pid, tid)
+}
+
 pub fn get_current_task() -> Option<Rc<Task>> {
     match TASK_SCHEDULER.force_get().queue.front() {
         Some(task) => Some(task.clone()),
@@ -131,7 +374,7 @@ pub fn get_task(pid: usize, tid: usize) -> Option<Rc<Task>> {
             return Some(task.clone());
         }
     }
-    None
+    task_scheduler.wait_queue.get_task(pid, tid)
 }
 
 pub fn switch_to_task(pid: usize, tid: usize) {
@@ -146,6 +389,46 @@ pub fn switch_to_task(pid: usize, tid: usize) {
     }
 }
 
+// 获取进程的所有任务 包括等待中的任务
+pub fn get_process_tasks(pid: usize) -> Vec<Rc<Task>> {
+    let task_scheduler = TASK_SCHEDULER.force_get();
+    let mut tasks: Vec<Rc<Task>> = task_scheduler.queue.iter().filter(|x| x.pid == pid).cloned().collect();
+    tasks.extend(task_scheduler.wait_queue.get_process_tasks(pid));
+    tasks
+}
+
+// 获取所有进程
+pub fn get_processes() -> Vec<Rc<RefCell<Process>>> {
+    TASK_SCHEDULER.force_get().get_processes()
+}
+
+// 向进程发送信号 进程不存在时返回false
+pub fn signal_process(pid: usize, info: SigInfo) -> bool {
+    let mut task_scheduler = TASK_SCHEDULER.force_get();
+    match task_scheduler.get_process(pid) {
+        Some(process) => {
+            process.borrow_mut().send_signal(info);
+            task_scheduler.notify_signal(pid);
+            true
+        }
+        None => false
+    }
+}
+
+// 向线程发送信号 线程不存在时返回false
+pub fn signal_task(pid: usize, tid: usize, info: SigInfo) -> bool {
+    let task = match get_task(pid, tid) {
+        Some(task) => task,
+        None => return false
+    };
+    let mut inner = task.inner.borrow_mut();
+    inner.process.borrow_mut().prepare_signal(info.signo());
+    inner.pending.add(info);
+    drop(inner);
+    TASK_SCHEDULER.force_get().notify_signal(pid);
+    true
+}
+
 // 获取当前的任务数量
 pub fn get_task_num() -> usize {
     TASK_SCHEDULER.force_get().queue.len()
@@ -0,0 +1,452 @@
+use core::cell::RefCell;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::rc::Weak;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::fs::file::FileOP;
+use crate::fs::file::FileType;
+use crate::fs::file::PollEvents;
+use crate::sys_call::consts::{EAGAIN, ECONNREFUSED, EDESTADDRREQ, EINVAL, EISCONN, EMSGSIZE, ENOTCONN, EOPNOTSUPP, EPIPE, ETOOMANYREFS};
+use super::fd_table::FileDesc;
+use super::task_scheduler::wake_up;
+use super::wait_queue::WaitEvent;
+
+// 本地套接字的协议族
+pub const AF_UNIX: usize = 1;
+
+// 套接字类型 低4位为类型 其余为标志
+pub const SOCK_STREAM: usize = 1;
+pub const SOCK_DGRAM: usize = 2;
+pub const SOCK_SEQPACKET: usize = 5;
+pub const SOCK_TYPE_MASK: usize = 0xf;
+pub const SOCK_NONBLOCK: usize = 0o4000;
+pub const SOCK_CLOEXEC: usize = 0o2000000;
+
+// sockaddr_un中路径的最大长度
+pub const UNIX_PATH_MAX: usize = 108;
+// 接收队列的容量
+pub const UNIX_BUF_SIZE: usize = 64 * 1024;
+
+// 套接字之间传递的消息
+pub struct UnixMessage {
+    pub data: Vec<u8>,              // 数据
+    pub pos: usize,                 // 流式套接字已经读取的位置
+    pub fds: Vec<FileDesc>,         // SCM_RIGHTS传递的文件描述符
+    pub from: Option<String>        // 发送端绑定的路径
+}
+
+// 一次接收的结果
+pub struct UnixRecv {
+    pub len: usize,                 // 读取的长度
+    pub fds: Vec<FileDesc>,         // 随数据接收的文件描述符
+    pub from: Option<String>,       // 发送端绑定的路径
+    pub truncated: bool             // 数据报是否被截断
+}
+
+impl UnixRecv {
+    // 对端关闭 读取到文件结尾
+    fn eof() -> Self {
+        Self { len: 0, fds: vec![], from: None, truncated: false }
+    }
+}
+
+// 套接字状态
+pub enum UnixState {
+    Unconnected,                    // 未连接
+    Listening(usize),               // 监听中 参数为等待accept的最大连接数
+    Connected(Weak<UnixSocket>)     // 已连接 数据报套接字为默认的发送目标
+}
+
+pub struct UnixSocketInner {
+    pub ty: usize,                          // 套接字类型
+    pub path: Option<String>,               // 绑定的路径
+    pub state: UnixState,                   // 连接状态
+    pub backlog: VecDeque<Rc<UnixSocket>>,  // 等待accept的连接
+    pub messages: VecDeque<UnixMessage>,    // 接收队列
+    pub size: usize,                        // 接收队列中未读取的字节数
+    pub shut_read: bool,                    // 是否关闭读取
+    pub shut_write: bool,                   // 是否关闭写入
+    pub senders: Vec<WaitEvent>             // 接收队列已满时等待的发送端
+}
+
+// 本地套接字 通过Rc在文件描述符之间共享 最后一个引用释放时关闭连接
+pub struct UnixSocket(pub RefCell<UnixSocketInner>);
+
+impl UnixSocket {
+    pub fn new(ty: usize) -> Rc<Self> {
+        Rc::new(Self(RefCell::new(UnixSocketInner {
+            ty,
+            path: None,
+            state: UnixState::Unconnected,
+            backlog: VecDeque::new(),
+            messages: VecDeque::new(),
+            size: 0,
+            shut_read: false,
+            shut_write: false,
+            senders: vec![]
+        })))
+    }
+
+    // 创建一对互相连接的套接字
+    pub fn pair(ty: usize) -> (Rc<Self>, Rc<Self>) {
+        let first = Self::new(ty);
+        let second = Self::new(ty);
+        first.0.borrow_mut().state = UnixState::Connected(Rc::downgrade(&second));
+        second.0.borrow_mut().state = UnixState::Connected(Rc::downgrade(&first));
+        (first, second)
+    }
+
+    // 等待套接字状态改变的事件 使用套接字地址区分
+    pub fn event(&self) -> WaitEvent {
+        WaitEvent::Socket(self as *const Self as usize)
+    }
+
+    pub fn ty(&self) -> usize {
+        self.0.borrow().ty
+    }
+
+    // 是否为面向连接的套接字
+    fn connection(&self) -> bool {
+        self.ty() != SOCK_DGRAM
+    }
+
+    // 是否保留消息边界
+    fn message(&self) -> bool {
+        self.ty() != SOCK_STREAM
+    }
+
+    // 获取绑定的路径
+    pub fn path(&self) -> Option<String> {
+        self.0.borrow().path.clone()
+    }
+
+    // 获取连接的对端 对端关闭时返回None
+    pub fn peer(&self) -> Option<Rc<UnixSocket>> {
+        match &self.0.borrow().state {
+            UnixState::Connected(peer) => peer.upgrade(),
+            _ => None
+        }
+    }
+
+    // 绑定路径 已经绑定时返回EINVAL
+    pub fn bind(&self, path: String) -> usize {
+        let mut inner = self.0.borrow_mut();
+        if inner.path.is_some() {
+            return EINVAL;
+        }
+        inner.path = Some(path);
+        0
+    }
+
+    // 开始监听连接
+    pub fn listen(&self, backlog: usize) -> usize {
+        if !self.connection() {
+            return EOPNOTSUPP;
+        }
+        let mut inner = self.0.borrow_mut();
+        match inner.state {
+            UnixState::Connected(_) => EINVAL,
+            _ if inner.path.is_none() => EINVAL,
+            _ => {
+                inner.state = UnixState::Listening(backlog.max(1));
+                0
+            }
+        }
+    }
+
+    // 连接到监听的套接字 创建服务端的套接字放入等待队列
+    // 等待队列已满时返回EAGAIN 阻塞模式需要等待target的事件
+    pub fn connect(self: &Rc<Self>, target: Rc<UnixSocket>) -> usize {
+        // 数据报套接字只设置默认的发送目标
+        if !self.connection() {
+            self.0.borrow_mut().state = UnixState::Connected(Rc::downgrade(&target));
+            return 0;
+        }
+        match self.0.borrow().state {
+            UnixState::Connected(_) => return EISCONN,
+            UnixState::Listening(_) => return EINVAL,
+            UnixState::Unconnected => {}
+        }
+        let mut listener = target.0.borrow_mut();
+        let backlog = match listener.state {
+            UnixState::Listening(backlog) => backlog,
+            _ => return ECONNREFUSED
+        };
+        if listener.backlog.len() >= backlog {
+            return EAGAIN;
+        }
+        let server = Self::new(self.ty());
+        let mut server_inner = server.0.borrow_mut();
+        server_inner.path = listener.path.clone();
+        server_inner.state = UnixState::Connected(Rc::downgrade(self));
+        drop(server_inner);
+        self.0.borrow_mut().state = UnixState::Connected(Rc::downgrade(&server));
+        listener.backlog.push_back(server);
+        drop(listener);
+        // 唤醒等待accept的任务
+        wake_up(target.event());
+        0
+    }
+
+    // 取出一个等待的连接 没有连接时返回EAGAIN
+    pub fn accept(&self) -> Result<Rc<UnixSocket>, usize> {
+        let mut inner = self.0.borrow_mut();
+        if !matches!(inner.state, UnixState::Listening(_)) {
+            return Err(EINVAL);
+        }
+        let server = inner.backlog.pop_front().ok_or(EAGAIN)?;
+        drop(inner);
+        // 唤醒等待队列已满时阻塞的connect
+        wake_up(self.event());
+        Ok(server)
+    }
+
+    // 关闭读取或者写入 唤醒对端等待的任务
+    pub fn shutdown(&self, read: bool, write: bool) {
+        let mut inner = self.0.borrow_mut();
+        inner.shut_read |= read;
+        inner.shut_write |= write;
+        drop(inner);
+        wake_up(self.event());
+        if let Some(peer) = self.peer() {
+            wake_up(peer.event());
+        }
+    }
+
+    // 发送数据 target为数据报的发送目标 返回发送的长度或者错误码
+    pub fn send(&self, data: &[u8], fds: Vec<FileDesc>, target: Option<Rc<UnixSocket>>) -> usize {
+        let inner = self.0.borrow();
+        if inner.shut_write {
+            return EPIPE;
+        }
+        let peer = match (&inner.state, target) {
+            // 数据报套接字可以指定发送目标
+            (_, Some(target)) if inner.ty == SOCK_DGRAM => target,
+            (UnixState::Connected(peer), _) => match peer.upgrade() {
+                Some(peer) => peer,
+                None if inner.ty == SOCK_DGRAM => return ECONNREFUSED,
+                None => return EPIPE
+            },
+            _ if inner.ty == SOCK_DGRAM => return EDESTADDRREQ,
+            _ => return ENOTCONN
+        };
+        let from = inner.path.clone();
+        let message = inner.ty != SOCK_STREAM;
+        drop(inner);
+        peer.deliver(data, fds, from, message, self.event())
+    }
+
+    // 接收队列中传递的套接字和等待accept的连接 这些套接字由当前套接字持有
+    fn held_sockets(&self) -> Vec<Rc<UnixSocket>> {
+        let inner = self.0.borrow();
+        let mut sockets: Vec<Rc<UnixSocket>> = inner.backlog.iter().cloned().collect();
+        for message in &inner.messages {
+            sockets.extend(message.fds.iter().filter_map(|x| x.file.clone().downcast::<UnixSocket>().ok()));
+        }
+        sockets
+    }
+
+    // 判断socket是否直接或者间接持有当前套接字
+    fn held_by(&self, socket: Rc<UnixSocket>) -> bool {
+        let mut stack = vec![socket];
+        let mut visited: Vec<Rc<UnixSocket>> = vec![];
+        while let Some(curr) = stack.pop() {
+            if core::ptr::eq(curr.as_ref(), self) {
+                return true;
+            }
+            if visited.iter().any(|x| Rc::ptr_eq(x, &curr)) {
+                continue;
+            }
+            stack.extend(curr.held_sockets());
+            visited.push(curr);
+        }
+        false
+    }
+
+    // 将消息放入接收队列 空间不足时记录发送端的等待事件
+    fn deliver(&self, data: &[u8], fds: Vec<FileDesc>, from: Option<String>, message: bool, sender: WaitEvent) -> usize {
+        // 传递的套接字持有接收端时形成引用循环 关闭所有文件描述符后套接字也不会释放
+        if fds.iter().filter_map(|x| x.file.clone().downcast::<UnixSocket>().ok()).any(|x| self.held_by(x)) {
+            return ETOOMANYREFS;
+        }
+        let mut inner = self.0.borrow_mut();
+        if inner.shut_read {
+            return EPIPE;
+        }
+        if message && data.len() > UNIX_BUF_SIZE {
+            return EMSGSIZE;
+        }
+        let free = UNIX_BUF_SIZE.saturating_sub(inner.size);
+        // 数据报需要一次发送完成 流式套接字可以只发送一部分
+        if free == 0 || (message && free < data.len()) {
+            if !inner.senders.contains(&sender) {
+                inner.senders.push(sender);
+            }
+            return EAGAIN;
+        }
+        let len = data.len().min(free);
+        if len == 0 && !message && fds.is_empty() {
+            return 0;
+        }
+        inner.messages.push_back(UnixMessage { data: data[..len].to_vec(), pos: 0, fds, from });
+        inner.size += len;
+        drop(inner);
+        // 唤醒等待读取的任务
+        wake_up(self.event());
+        len
+    }
+
+    // 接收数据 peek为真时不移出接收队列 没有数据时返回EAGAIN 对端关闭时返回长度为0
+    pub fn recv(&self, buf: &mut [u8], peek: bool) -> Result<UnixRecv, usize> {
+        let mut inner = self.0.borrow_mut();
+        if inner.messages.is_empty() {
+            if inner.shut_read {
+                return Ok(UnixRecv::eof());
+            }
+            return match &inner.state {
+                UnixState::Connected(peer) if inner.ty != SOCK_DGRAM => match peer.upgrade() {
+                    Some(peer) if !peer.0.borrow().shut_write => Err(EAGAIN),
+                    _ => Ok(UnixRecv::eof())
+                },
+                UnixState::Listening(_) => Err(EINVAL),
+                _ if inner.ty != SOCK_DGRAM => Err(ENOTCONN),
+                _ => Err(EAGAIN)
+            };
+        }
+        let mut result = UnixRecv::eof();
+        if self.message() {
+            // 每次读取一个消息 超出缓冲区的部分被丢弃
+            let message = inner.messages.front_mut().unwrap();
+            let len = buf.len().min(message.data.len());
+            buf[..len].copy_from_slice(&message.data[..len]);
+            result.len = len;
+            result.truncated = len < message.data.len();
+            result.from = message.from.clone();
+            if !peek {
+                let message = inner.messages.pop_front().unwrap();
+                inner.size -= message.data.len();
+                result.fds = message.fds;
+            }
+        } else {
+            // 按字节读取 携带文件描述符的消息作为读取的边界
+            let mut consumed = 0;
+            for (i, message) in inner.messages.iter_mut().enumerate() {
+                if result.len == buf.len() || (i > 0 && !message.fds.is_empty()) {
+                    break;
+                }
+                let remain = &message.data[message.pos..];
+                let len = remain.len().min(buf.len() - result.len);
+                buf[result.len..result.len + len].copy_from_slice(&remain[..len]);
+                result.len += len;
+                if i == 0 {
+                    result.from = message.from.clone();
+                }
+                if !peek {
+                    if i == 0 {
+                        result.fds = core::mem::take(&mut message.fds);
+                    }
+                    message.pos += len;
+                    if message.pos == message.data.len() {
+                        consumed += 1;
+                    }
+                }
+            }
+            if !peek {
+                inner.messages.drain(..consumed);
+                inner.size -= result.len;
+            }
+        }
+        if peek {
+            return Ok(result);
+        }
+        // 唤醒等待空间的发送端
+        let senders = core::mem::take(&mut inner.senders);
+        drop(inner);
+        for sender in senders {
+            wake_up(sender);
+        }
+        Ok(result)
+    }
+}
+
+impl FileOP for UnixSocket {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writeable(&self) -> bool {
+        true
+    }
+
+    // 使用read读取时丢弃传递的文件描述符
+    fn read_at(&self, _pos: usize, data: &mut [u8]) -> usize {
+        match self.recv(data, false) {
+            Ok(result) => result.len,
+            Err(err) => err
+        }
+    }
+
+    fn write_at(&self, _pos: usize, data: &[u8], count: usize) -> usize {
+        self.send(&data[..count.min(data.len())], vec![], None)
+    }
+
+    fn get_size(&self) -> usize {
+        self.0.borrow().size
+    }
+
+    fn poll(&self) -> PollEvents {
+        let inner = self.0.borrow();
+        if let UnixState::Listening(_) = inner.state {
+            return match inner.backlog.is_empty() {
+                true => PollEvents::empty(),
+                false => PollEvents::IN
+            };
+        }
+        let mut events = PollEvents::empty();
+        if !inner.messages.is_empty() || inner.shut_read {
+            events |= PollEvents::IN;
+        }
+        match &inner.state {
+            UnixState::Connected(peer) => match peer.upgrade() {
+                Some(peer) => {
+                    let peer = peer.0.borrow();
+                    if peer.shut_write {
+                        events |= PollEvents::IN;
+                    }
+                    if !inner.shut_write && peer.size < UNIX_BUF_SIZE {
+                        events |= PollEvents::OUT;
+                    }
+                }
+                None if inner.ty == SOCK_DGRAM => events |= PollEvents::ERR,
+                None => events |= PollEvents::IN | PollEvents::HUP
+            },
+            _ if inner.ty == SOCK_DGRAM => events |= PollEvents::OUT,
+            _ => events |= PollEvents::HUP
+        }
+        events
+    }
+
+    fn wait_event(&self) -> Option<WaitEvent> {
+        Some(self.event())
+    }
+
+    fn file_type(&self) -> FileType {
+        FileType::Socket
+    }
+}
+
+impl Drop for UnixSocket {
+    // 关闭时唤醒对端和等待发送的任务 对端通过Weak发现连接已经断开
+    fn drop(&mut self) {
+        let inner = self.0.get_mut();
+        if let UnixState::Connected(peer) = &inner.state {
+            if let Some(peer) = peer.upgrade() {
+                wake_up(peer.event());
+            }
+        }
+        for sender in inner.senders.drain(..) {
+            wake_up(sender);
+        }
+    }
+}
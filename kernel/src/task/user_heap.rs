@@ -55,30 +55,21 @@ impl UserHeap {
         self.pointer
     }
 
+    // 设置堆顶 堆的页面在缺页时分配
     pub fn set_heap_top(&mut self, top: usize) -> Result<usize, RuntimeError>{
         if self.start == 0 {
             debug!("设置heap: {:#x}", top);
             self.start = top;
             self.pointer = top;
-            let mem_map = MemMap::new((top / PAGE_SIZE).into(), DEFAULT_HEAP_PAGE_NUM, PTEFlags::VRWX | PTEFlags::U)?;
-            self.pmm.add_mapping_by_map(&mem_map)?;
-            self.mem_set.0.push(mem_map);
             self.end = top + DEFAULT_HEAP_PAGE_NUM * PAGE_SIZE;
             return Ok(top);
         }
 
         self.pointer = top;
-        // origin_top
-        loop {
-            if self.pointer < self.end {
-                break Ok(top);
-            } else {
-                let page = MemMap::new((self.end / PAGE_SIZE).into(), 1, PTEFlags::UVRWX)?;
-                self.pmm.add_mapping_by_map(&page)?;
-                self.mem_set.0.push(page);
-                self.end += PAGE_SIZE;
-            }
+        if self.pointer >= self.end {
+            self.end = (self.pointer / PAGE_SIZE + 1) * PAGE_SIZE;
         }
+        Ok(top)
     }
 
     // 获取临时页表
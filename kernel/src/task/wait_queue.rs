@@ -0,0 +1,164 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::interrupt::timer::TimerEvent;
+use crate::interrupt::timer::TimerId;
+use crate::interrupt::timer::add_timer;
+use crate::interrupt::timer::cancel_timer;
+
+use super::signal::SigSet;
+use super::task::Task;
+use super::task::TaskStatus;
+
+// 等待事件 任务挂起在对应的内核对象上 由事件源唤醒
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WaitEvent {
+    Child(usize),       // 等待子进程退出 参数为父进程pid
+    VFork(usize),       // 等待vfork的子进程退出 参数为子进程pid
+    Futex(usize, usize),    // 等待futex 参数为(页表, 用户地址) 共享futex页表为0 地址为物理地址
+    Stopped(usize),     // 进程被信号停止 等待SIGCONT 参数为pid
+    Signal(SigSet),     // 等待信号到达 参数为sigtimedwait和signalfd等待的信号
+    Pipe(usize),        // 等待管道可以读写 参数为管道缓冲区的地址
+    FifoOpen(usize, bool),  // 等待命名管道的另一端打开 参数为(管道缓冲区的地址, 是否为读端)
+    Socket(usize),      // 等待套接字可以读写或者连接 参数为套接字的地址
+    Sem(usize),         // 等待System V信号量 参数为信号量集合的id
+    Msg(usize),         // 等待System V消息队列可以收发 参数为消息队列的id
+    File(usize),        // 等待eventfd或者epoll的状态改变 参数为文件对象的地址
+    Poll,               // poll、select和epoll同时等待多个文件 实际等待的事件保存在WaitEntry的polled中
+    Timer               // 仅等待超时
+}
+
+impl WaitEvent {
+    // 判断等待是否可以被信号打断
+    pub fn interruptible(&self) -> bool {
+        !matches!(self, WaitEvent::VFork(_) | WaitEvent::Stopped(_))
+    }
+}
+
+// 等待中的任务
+pub struct WaitEntry {
+    pub task: Rc<Task>,             // 挂起的任务
+    pub event: WaitEvent,           // 等待的事件
+    pub polled: Vec<WaitEvent>,     // 等待Poll时监听的事件 任意一个发生时唤醒
+    pub timer: Option<TimerId>      // 超时定时器
+}
+
+impl WaitEntry {
+    // 离开等待队列时取消超时定时器
+    fn cancel_timer(&self) {
+        if let Some(timer) = self.timer {
+            cancel_timer(timer);
+        }
+    }
+}
+
+// 等待队列
+pub struct WaitQueue(Vec<WaitEntry>);
+
+impl WaitQueue {
+    // 创建等待队列
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    // 添加等待任务 timeout为超时时间 单位us 超时由定时器列表唤醒
+    pub fn push(&mut self, task: Rc<Task>, event: WaitEvent, timeout: Option<usize>) {
+        task.inner.borrow_mut().status = TaskStatus::WAITING;
+        let timer = timeout.map(|x| add_timer(x, TimerEvent::Wake(task.pid, task.tid)));
+        self.0.push(WaitEntry { task, event, polled: vec![], timer });
+    }
+
+    // 添加同时等待多个事件的任务
+    pub fn push_poll(&mut self, task: Rc<Task>, events: Vec<WaitEvent>, timeout: Option<usize>) {
+        self.push(task, WaitEvent::Poll, timeout);
+        self.0.last_mut().unwrap().polled = events;
+    }
+
+    // 取出等待事件的任务 最多取出count个 按照等待的先后顺序
+    pub fn take(&mut self, event: WaitEvent, count: usize) -> Vec<Rc<Task>> {
+        let mut tasks = vec![];
+        let mut i = 0;
+        while i < self.0.len() && tasks.len() < count {
+            if self.0[i].event == event || self.0[i].polled.contains(&event) {
+                let entry = self.0.remove(i);
+                entry.cancel_timer();
+                tasks.push(entry.task);
+            } else {
+                i += 1;
+            }
+        }
+        tasks
+    }
+
+    // 将等待事件的任务转移到新的事件上 最多转移count个 返回转移的数量
+    pub fn requeue(&mut self, from: WaitEvent, to: WaitEvent, count: usize) -> usize {
+        let mut num = 0;
+        for entry in self.0.iter_mut().filter(|x| x.event == from) {
+            if num >= count {
+                break;
+            }
+            entry.event = to;
+            num += 1;
+        }
+        num
+    }
+
+    // 取出等待超时的任务
+    pub fn take_timeout(&mut self, pid: usize, tid: usize) -> Option<Rc<Task>> {
+        let index = self.0.iter().position(|x| x.task.pid == pid && x.task.tid == tid)?;
+        Some(self.0.remove(index).task)
+    }
+
+    // 取出被信号打断的任务 返回等待的事件
+    pub fn take_interrupted(&mut self, pid: usize, tid: usize) -> Option<WaitEvent> {
+        let index = self.0.iter().position(|x| x.task.pid == pid && x.task.tid == tid
+            && x.event.interruptible())?;
+        let entry = self.0.remove(index);
+        entry.cancel_timer();
+        Some(entry.event)
+    }
+
+    // 获取任务等待的事件
+    pub fn get_event(&self, pid: usize, tid: usize) -> Option<WaitEvent> {
+        self.0.iter().find(|x| x.task.pid == pid && x.task.tid == tid).map(|x| x.event)
+    }
+
+    // 获取任务等待Poll时监听的事件
+    pub fn get_polled(&self, pid: usize, tid: usize) -> Vec<WaitEvent> {
+        self.0.iter().find(|x| x.task.pid == pid && x.task.tid == tid).map_or(vec![], |x| x.polled.clone())
+    }
+
+    // 获取所有等待中的任务
+    pub fn get_tasks(&self) -> Vec<Rc<Task>> {
+        self.0.iter().map(|x| x.task.clone()).collect()
+    }
+
+    // 查找等待中的任务
+    pub fn get_task(&self, pid: usize, tid: usize) -> Option<Rc<Task>> {
+        self.0.iter().find(|x| x.task.pid == pid && x.task.tid == tid).map(|x| x.task.clone())
+    }
+
+    // 查找进程所有等待中的任务
+    pub fn get_process_tasks(&self, pid: usize) -> Vec<Rc<Task>> {
+        self.0.iter().filter(|x| x.task.pid == pid).map(|x| x.task.clone()).collect()
+    }
+
+    // 移除进程的所有等待任务
+    pub fn remove_process(&mut self, pid: usize) {
+        for entry in self.0.drain_filter(|x| x.task.pid == pid) {
+            entry.cancel_timer();
+        }
+    }
+
+    // 移除等待中的任务
+    pub fn remove_task(&mut self, pid: usize, tid: usize) {
+        for entry in self.0.drain_filter(|x| x.task.pid == pid && x.task.tid == tid) {
+            entry.cancel_timer();
+        }
+    }
+
+    // 判断是否为空
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}